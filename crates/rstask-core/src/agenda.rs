@@ -0,0 +1,327 @@
+// Agenda - tasks laid out on the calendar
+use crate::constants::*;
+use crate::graph::DEFAULT_SCHEDULE_DURATION;
+use crate::task::Task;
+use chrono::{DateTime, Days, Duration, NaiveDate, Utc};
+use chrono_tz::Tz;
+use std::collections::BTreeMap;
+
+/// Returns the tasks due on the given calendar date as seen from `tz`.
+/// Deleted tasks and tasks without a due date are left out.
+pub fn tasks_due_on(tasks: &[Task], date: NaiveDate, tz: Tz) -> Vec<&Task> {
+    tasks
+        .iter()
+        .filter(|task| !task.deleted)
+        .filter(|task| {
+            task.due
+                .is_some_and(|due| due.with_timezone(&tz).date_naive() == date)
+        })
+        .collect()
+}
+
+/// Open tasks grouped by when they are due
+#[derive(Debug, Clone, Default)]
+pub struct AgendaBuckets<'a> {
+    /// Due on a day before today
+    pub overdue: Vec<&'a Task>,
+    pub today: Vec<&'a Task>,
+    pub tomorrow: Vec<&'a Task>,
+    /// Due within the rest of the next seven days
+    pub this_week: Vec<&'a Task>,
+    pub later: Vec<&'a Task>,
+    /// No due date, oldest first
+    pub no_date: Vec<&'a Task>,
+}
+
+/// Sorts open tasks into agenda buckets by their due date as seen from `tz`,
+/// each bucket ordered by due time. Days are compared rather than times, so
+/// a task due earlier today is still in `today`. Resolved and deleted tasks,
+/// templates and recurring tasks are left out.
+pub fn agenda_buckets(tasks: &[Task], now: DateTime<Utc>, tz: Tz) -> AgendaBuckets<'_> {
+    let today = now.with_timezone(&tz).date_naive();
+    let tomorrow = today + Days::new(1);
+    let week_end = today + Days::new(6);
+
+    let mut buckets = AgendaBuckets::default();
+    for task in tasks {
+        if task.deleted || HIDDEN_STATUSES.contains(&task.status.as_str()) {
+            continue;
+        }
+
+        let Some(due) = task.due else {
+            buckets.no_date.push(task);
+            continue;
+        };
+
+        let day = due.with_timezone(&tz).date_naive();
+        let bucket = if day < today {
+            &mut buckets.overdue
+        } else if day == today {
+            &mut buckets.today
+        } else if day == tomorrow {
+            &mut buckets.tomorrow
+        } else if day <= week_end {
+            &mut buckets.this_week
+        } else {
+            &mut buckets.later
+        };
+        bucket.push(task);
+    }
+
+    for bucket in [
+        &mut buckets.overdue,
+        &mut buckets.today,
+        &mut buckets.tomorrow,
+        &mut buckets.this_week,
+        &mut buckets.later,
+    ] {
+        bucket.sort_by_key(|task| task.due);
+    }
+    buckets.no_date.sort_by_key(|task| task.created);
+
+    buckets
+}
+
+/// Sums the estimated effort of open tasks per due date as seen from `tz`.
+/// Tasks without an estimate count for [`DEFAULT_SCHEDULE_DURATION`], as
+/// when scheduling. A day's total stops at `Duration::MAX` rather than
+/// overflowing.
+pub fn workload_by_day(tasks: &[Task], tz: Tz) -> BTreeMap<NaiveDate, Duration> {
+    workload_by_day_with(tasks, tz, DEFAULT_SCHEDULE_DURATION)
+}
+
+/// Same as [`workload_by_day`], with the effort assumed for tasks without an
+/// estimate given explicitly
+pub fn workload_by_day_with(
+    tasks: &[Task],
+    tz: Tz,
+    default_estimate: Duration,
+) -> BTreeMap<NaiveDate, Duration> {
+    let mut workload: BTreeMap<NaiveDate, Duration> = BTreeMap::new();
+
+    for task in tasks {
+        if task.deleted || HIDDEN_STATUSES.contains(&task.status.as_str()) {
+            continue;
+        }
+
+        let Some(due) = task.due else {
+            continue;
+        };
+
+        let day = due.with_timezone(&tz).date_naive();
+        let effort = task.estimate.unwrap_or(default_estimate);
+        let total = workload.entry(day).or_insert_with(Duration::zero);
+        *total = total.checked_add(&effort).unwrap_or(Duration::MAX);
+    }
+
+    workload
+}
+
+/// The day among the `days` days starting today, as seen from `tz`, with
+/// the most open tasks due, along with how many. Ties go to the earliest
+/// day. None if nothing is due in that window.
+pub fn busiest_day(
+    tasks: &[Task],
+    now: DateTime<Utc>,
+    days: u32,
+    tz: Tz,
+) -> Option<(NaiveDate, usize)> {
+    let today = now.with_timezone(&tz).date_naive();
+    let end = today.checked_add_days(Days::new(days.into()))?;
+
+    let mut counts: BTreeMap<NaiveDate, usize> = BTreeMap::new();
+    for task in tasks {
+        if task.deleted || HIDDEN_STATUSES.contains(&task.status.as_str()) {
+            continue;
+        }
+        if let Some(due) = task.due {
+            let day = due.with_timezone(&tz).date_naive();
+            if day >= today && day < end {
+                *counts.entry(day).or_default() += 1;
+            }
+        }
+    }
+
+    counts
+        .into_iter()
+        .fold(None, |busiest, (day, count)| match busiest {
+            Some((_, most)) if most >= count => busiest,
+            _ => Some((day, count)),
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn due_at(summary: &str, hour: u32) -> Task {
+        let mut task = Task::new(summary.to_string());
+        task.due = Some(Utc.with_ymd_and_hms(2024, 6, 1, hour, 30, 0).unwrap());
+        task
+    }
+
+    fn summaries(tasks: &[&Task]) -> Vec<String> {
+        tasks.iter().map(|t| t.summary.clone()).collect()
+    }
+
+    #[test]
+    fn test_tasks_due_on_across_midnight() {
+        // 23:30 UTC is already the 2nd in Tokyo, 02:30 UTC still the 31st in Los Angeles
+        let tasks = vec![
+            due_at("morning", 2),
+            due_at("afternoon", 14),
+            due_at("late", 23),
+            Task::new("undated".to_string()),
+        ];
+
+        let june_1 = NaiveDate::from_ymd_opt(2024, 6, 1).unwrap();
+        let june_2 = NaiveDate::from_ymd_opt(2024, 6, 2).unwrap();
+
+        assert_eq!(
+            summaries(&tasks_due_on(&tasks, june_1, Tz::UTC)),
+            vec!["morning", "afternoon", "late"]
+        );
+        assert_eq!(
+            summaries(&tasks_due_on(&tasks, june_1, Tz::Asia__Tokyo)),
+            vec!["morning", "afternoon"]
+        );
+        assert_eq!(
+            summaries(&tasks_due_on(&tasks, june_2, Tz::Asia__Tokyo)),
+            vec!["late"]
+        );
+        assert_eq!(
+            summaries(&tasks_due_on(&tasks, june_1, Tz::America__Los_Angeles)),
+            vec!["afternoon", "late"]
+        );
+    }
+
+    fn due(summary: &str, due: DateTime<Utc>) -> Task {
+        let mut task = Task::new(summary.to_string());
+        task.due = Some(due);
+        task
+    }
+
+    #[test]
+    fn test_agenda_buckets() {
+        let tz = Tz::Asia__Tokyo;
+        // 16:00 UTC on the 9th is 01:00 on the 10th in Tokyo
+        let now = Utc.with_ymd_and_hms(2024, 6, 9, 16, 0, 0).unwrap();
+        let at = |d, h| Utc.with_ymd_and_hms(2024, 6, d, h, 0, 0).unwrap();
+
+        let mut resolved = due("resolved", at(9, 0));
+        resolved.status = STATUS_RESOLVED.to_string();
+        let mut deleted = due("deleted", at(10, 0));
+        deleted.deleted = true;
+
+        let tasks = vec![
+            due("overdue", at(9, 14)),
+            due("earlier today", at(9, 15)),
+            due("today", at(10, 2)),
+            due("tomorrow", at(10, 16)),
+            due("this week", at(15, 0)),
+            due("next week", at(16, 16)),
+            Task::new("no date".to_string()),
+            resolved,
+            deleted,
+        ];
+
+        let buckets = agenda_buckets(&tasks, now, tz);
+        assert_eq!(summaries(&buckets.overdue), vec!["overdue"]);
+        assert_eq!(summaries(&buckets.today), vec!["earlier today", "today"]);
+        assert_eq!(summaries(&buckets.tomorrow), vec!["tomorrow"]);
+        assert_eq!(summaries(&buckets.this_week), vec!["this week"]);
+        assert_eq!(summaries(&buckets.later), vec!["next week"]);
+        assert_eq!(summaries(&buckets.no_date), vec!["no date"]);
+
+        // Seen from UTC it's still the 9th
+        let buckets = agenda_buckets(&tasks, now, Tz::UTC);
+        assert_eq!(summaries(&buckets.today), vec!["overdue", "earlier today"]);
+        assert_eq!(summaries(&buckets.tomorrow), vec!["today", "tomorrow"]);
+    }
+
+    #[test]
+    fn test_busiest_day() {
+        let now = Utc.with_ymd_and_hms(2024, 6, 3, 12, 0, 0).unwrap();
+        let at = |d| Utc.with_ymd_and_hms(2024, 6, d, 9, 0, 0).unwrap();
+        let june = |day| NaiveDate::from_ymd_opt(2024, 6, day).unwrap();
+
+        let mut resolved = due("resolved", at(5));
+        resolved.status = STATUS_RESOLVED.to_string();
+        let mut tasks = vec![
+            due("overdue", at(1)),
+            due("overdue", at(1)),
+            due("overdue", at(1)),
+            due("tie", at(4)),
+            due("tie", at(4)),
+            due("peak", at(10)),
+            due("peak", at(10)),
+            due("peak", at(10)),
+            due("too late", at(17)),
+            due("too late", at(17)),
+            due("too late", at(17)),
+            due("too late", at(17)),
+            resolved.clone(),
+            resolved.clone(),
+            resolved,
+            Task::new("undated".to_string()),
+        ];
+
+        assert_eq!(busiest_day(&tasks, now, 14, Tz::UTC), Some((june(10), 3)));
+
+        // Two days on two each: the earlier wins
+        tasks.push(due("tie", at(12)));
+        tasks.push(due("tie", at(12)));
+        tasks.retain(|task| task.summary != "peak");
+        assert_eq!(busiest_day(&tasks, now, 14, Tz::UTC), Some((june(4), 2)));
+
+        assert_eq!(busiest_day(&tasks, now, 0, Tz::UTC), None);
+    }
+
+    #[test]
+    fn test_workload_by_day() {
+        let with_estimate = |day, hour, estimate: Option<Duration>| {
+            let mut task = due(
+                "work",
+                Utc.with_ymd_and_hms(2024, 6, day, hour, 0, 0).unwrap(),
+            );
+            task.estimate = estimate;
+            task
+        };
+
+        let mut resolved = with_estimate(1, 9, Some(Duration::hours(8)));
+        resolved.status = STATUS_RESOLVED.to_string();
+
+        let tasks = vec![
+            with_estimate(1, 9, Some(Duration::hours(2))),
+            with_estimate(1, 16, Some(Duration::minutes(30))),
+            with_estimate(2, 9, Some(Duration::hours(3))),
+            with_estimate(2, 10, None),
+            resolved,
+            Task::new("undated".to_string()),
+        ];
+
+        let june = |day| NaiveDate::from_ymd_opt(2024, 6, day).unwrap();
+        let workload = workload_by_day(&tasks, Tz::UTC);
+        assert_eq!(workload.len(), 2);
+        assert_eq!(workload[&june(1)], Duration::minutes(150));
+        assert_eq!(
+            workload[&june(2)],
+            Duration::hours(3) + DEFAULT_SCHEDULE_DURATION
+        );
+
+        let workload = workload_by_day_with(&tasks, Tz::UTC, Duration::zero());
+        assert_eq!(workload[&june(2)], Duration::hours(3));
+
+        // 16:00 UTC on the 1st is already the 2nd in Tokyo
+        let workload = workload_by_day_with(&tasks, Tz::Asia__Tokyo, Duration::zero());
+        assert_eq!(workload[&june(1)], Duration::hours(2));
+        assert_eq!(workload[&june(2)], Duration::minutes(210));
+
+        let huge = [
+            with_estimate(3, 9, Some(Duration::MAX)),
+            with_estimate(3, 10, Some(Duration::hours(1))),
+        ];
+        assert_eq!(workload_by_day(&huge, Tz::UTC)[&june(3)], Duration::MAX);
+    }
+}
@@ -0,0 +1,149 @@
+// Archive - many tasks stored in a single markdown file
+//
+// Each task is written as a regular task document preceded by a header line
+// carrying its UUID and status, which are normally encoded in the file path:
+//
+//     <!-- rstask:task 0f8e...-1c2d resolved -->
+//     ---
+//     summary: ...
+//     ---
+//
+//     notes
+use crate::frontmatter::{task_from_markdown, task_to_markdown};
+use crate::task::Task;
+use crate::util::is_valid_uuid4_string;
+use crate::{Result, RstaskError};
+use std::io::Write;
+use std::path::Path;
+
+const ENTRY_PREFIX: &str = "<!-- rstask:task ";
+const ENTRY_SUFFIX: &str = " -->";
+
+/// Appends a task to the archive file, creating it if needed
+pub fn append_to_archive(archive_path: &Path, task: &Task) -> Result<()> {
+    let existing = std::fs::read_to_string(archive_path).unwrap_or_default();
+
+    let mut entry = String::new();
+    if !existing.is_empty() {
+        if !existing.ends_with('\n') {
+            entry.push('\n');
+        }
+        entry.push('\n');
+    }
+    entry.push_str(&format!(
+        "{}{} {}{}\n",
+        ENTRY_PREFIX, task.uuid, task.status, ENTRY_SUFFIX
+    ));
+    entry.push_str(&task_to_markdown(task)?);
+
+    if let Some(parent) = archive_path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+
+    let mut file = std::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(archive_path)?;
+    file.write_all(entry.as_bytes())?;
+    Ok(())
+}
+
+/// Reads every task from an archive file. A missing file is an empty archive.
+pub fn read_archive(archive_path: &Path) -> Result<Vec<Task>> {
+    match std::fs::read_to_string(archive_path) {
+        Ok(content) => parse_archive(&content),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Vec::new()),
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// Parses archive content into its tasks
+pub fn parse_archive(content: &str) -> Result<Vec<Task>> {
+    let mut tasks = Vec::new();
+    let mut header: Option<(String, String)> = None;
+    let mut body: Vec<&str> = Vec::new();
+
+    for line in content.lines() {
+        if let Some(rest) = line
+            .strip_prefix(ENTRY_PREFIX)
+            .and_then(|rest| rest.strip_suffix(ENTRY_SUFFIX))
+        {
+            if let Some((uuid, status)) = header.take() {
+                tasks.push(parse_entry(&uuid, &status, &body)?);
+            }
+            body.clear();
+
+            let (uuid, status) = rest.split_once(' ').ok_or_else(|| {
+                RstaskError::Parse(format!("invalid archive entry header: {}", line))
+            })?;
+            if !is_valid_uuid4_string(uuid) {
+                return Err(RstaskError::InvalidUuid(uuid.to_string()));
+            }
+            header = Some((uuid.to_string(), status.to_string()));
+        } else if header.is_some() {
+            body.push(line);
+        } else if !line.trim().is_empty() {
+            return Err(RstaskError::Parse(
+                "archive content found before the first task header".to_string(),
+            ));
+        }
+    }
+
+    if let Some((uuid, status)) = header {
+        tasks.push(parse_entry(&uuid, &status, &body)?);
+    }
+
+    Ok(tasks)
+}
+
+fn parse_entry(uuid: &str, status: &str, body: &[&str]) -> Result<Task> {
+    // Drop the blank line separating this entry from the next one
+    let document = body.join("\n");
+    let mut task = task_from_markdown(document.trim_end_matches('\n'), uuid, status, 0)?;
+    task.notes = task.notes.trim_end_matches('\n').to_string();
+    Ok(task)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::constants::STATUS_RESOLVED;
+    use chrono::Utc;
+
+    fn resolved_task(summary: &str, notes: &str) -> Task {
+        let mut task = Task::new(summary.to_string());
+        task.status = STATUS_RESOLVED.to_string();
+        task.resolved = Some(Utc::now());
+        task.notes = notes.to_string();
+        task
+    }
+
+    #[test]
+    fn test_append_and_read_archive() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let path = dir.path().join("completed.md");
+
+        let first = resolved_task("first", "some notes\n\n---\n\nafter a rule");
+        let second = resolved_task("second", "");
+        append_to_archive(&path, &first).unwrap();
+        append_to_archive(&path, &second).unwrap();
+
+        let tasks = read_archive(&path).unwrap();
+        assert_eq!(tasks.len(), 2);
+        assert!(tasks[0].equals(&first));
+        assert!(tasks[1].equals(&second));
+        assert_eq!(tasks[0].status, STATUS_RESOLVED);
+    }
+
+    #[test]
+    fn test_read_missing_archive() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let tasks = read_archive(&dir.path().join("none.md")).unwrap();
+        assert!(tasks.is_empty());
+    }
+
+    #[test]
+    fn test_parse_archive_rejects_headerless_content() {
+        assert!(parse_archive("---\nsummary: x\n---\n").is_err());
+    }
+}
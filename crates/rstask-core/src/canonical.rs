@@ -0,0 +1,137 @@
+// Canonical form of a whole task store, e.g. before committing
+use crate::constants::*;
+use crate::preferences::Preferences;
+use crate::task::Task;
+use std::collections::HashSet;
+use std::fmt;
+
+/// A problem found while canonicalizing, tied to the task it concerns
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ValidationIssue {
+    pub uuid: String,
+    pub message: String,
+}
+
+impl fmt::Display for ValidationIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.uuid, self.message)
+    }
+}
+
+/// Brings every task into canonical form and reports what couldn't be fixed.
+///
+/// In order: each task is normalised (tags lowercased, sorted and
+/// deduplicated), dependencies are deduplicated and sorted when
+/// `sort_tags_on_write` is set, tasks are ordered by creation time then UUID,
+/// and IDs are renumbered so open tasks hold unique IDs (the first task keeps
+/// a contested ID, the others get the lowest free ones). Finally tasks are
+/// validated and dependencies on unknown tasks are reported.
+///
+/// Running it again on its own output changes nothing.
+pub fn canonicalize_store(tasks: &mut [Task], prefs: &Preferences) -> Vec<ValidationIssue> {
+    for task in tasks.iter_mut() {
+        task.normalise();
+
+        if prefs.sort_tags_on_write {
+            task.dependencies.sort();
+            task.dependencies.dedup();
+        }
+    }
+
+    tasks.sort_by(|a, b| a.created.cmp(&b.created).then_with(|| a.uuid.cmp(&b.uuid)));
+
+    renumber(tasks);
+
+    let mut issues = Vec::new();
+    let known: HashSet<&str> = tasks.iter().map(|t| t.uuid.as_str()).collect();
+    for task in tasks.iter() {
+        if let Err(e) = task.validate() {
+            issues.push(ValidationIssue {
+                uuid: task.uuid.clone(),
+                message: e.to_string(),
+            });
+        }
+
+        for dep in &task.dependencies {
+            if !known.contains(dep.as_str()) {
+                issues.push(ValidationIssue {
+                    uuid: task.uuid.clone(),
+                    message: format!("depends on unknown task {}", dep),
+                });
+            }
+        }
+    }
+
+    issues
+}
+
+fn holds_id(task: &Task) -> bool {
+    task.status != STATUS_RESOLVED && !task.deleted
+}
+
+fn renumber(tasks: &mut [Task]) {
+    let mut taken = HashSet::new();
+    for task in tasks.iter_mut() {
+        if !holds_id(task) || !taken.insert(task.id) {
+            task.id = 0;
+        }
+    }
+
+    let mut next_id = 1;
+    for task in tasks.iter_mut().filter(|t| holds_id(t) && t.id <= 0) {
+        while taken.contains(&next_id) {
+            next_id += 1;
+        }
+        task.id = next_id;
+        taken.insert(next_id);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{Duration, TimeZone, Utc};
+
+    fn task(summary: &str, id: i32, minutes: i64) -> Task {
+        let created = Utc.with_ymd_and_hms(2024, 6, 1, 0, 0, 0).unwrap();
+        let mut task = Task::new(summary.to_string());
+        task.id = id;
+        task.created = created + Duration::minutes(minutes);
+        task
+    }
+
+    #[test]
+    fn test_canonicalize_store_is_idempotent() {
+        let mut first = task("first", 2, 0);
+        first.tags = vec!["Work".into(), "home".into(), "work".into()];
+        let second = task("second", 2, 1);
+        let mut resolved = task("resolved", 3, 2);
+        resolved.status = STATUS_RESOLVED.to_string();
+        let mut broken = task("broken", 0, 3);
+        broken.dependencies.push("not-a-uuid".to_string());
+
+        let mut tasks = vec![broken, resolved, second, first];
+        let prefs = Preferences {
+            sort_tags_on_write: true,
+            ..Default::default()
+        };
+
+        let issues = canonicalize_store(&mut tasks, &prefs);
+        let summaries: Vec<&str> = tasks.iter().map(|t| t.summary.as_str()).collect();
+        assert_eq!(summaries, vec!["first", "second", "resolved", "broken"]);
+        assert_eq!(tasks[0].tags, vec!["home", "work"]);
+        let ids: Vec<i32> = tasks.iter().map(|t| t.id).collect();
+        assert_eq!(ids, vec![2, 1, 0, 3]);
+
+        assert_eq!(issues.len(), 2);
+        assert!(issues.iter().all(|i| i.uuid == tasks[3].uuid));
+
+        let snapshot = tasks.clone();
+        let again = canonicalize_store(&mut tasks, &prefs);
+        assert_eq!(again, issues);
+        for (before, after) in snapshot.iter().zip(&tasks) {
+            assert!(before.equals(after));
+            assert_eq!(before.id, after.id);
+        }
+    }
+}
@@ -0,0 +1,27 @@
+// Clock abstraction so time-dependent behaviour can be tested deterministically
+use chrono::{DateTime, Utc};
+
+/// Source of the current time
+pub trait Clock {
+    fn now(&self) -> DateTime<Utc>;
+}
+
+/// Clock returning the real system time
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> DateTime<Utc> {
+        Utc::now()
+    }
+}
+
+/// Clock frozen at a given instant, for tests
+#[derive(Debug, Clone, Copy)]
+pub struct FixedClock(pub DateTime<Utc>);
+
+impl Clock for FixedClock {
+    fn now(&self) -> DateTime<Utc> {
+        self.0
+    }
+}
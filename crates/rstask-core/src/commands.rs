@@ -4,15 +4,46 @@ use crate::{
     error::{Result, RstaskError},
     git::git_commit,
     local_state::LocalState,
+    lock::TaskLock,
     query::Query,
     task::Task,
     taskset::TaskSet,
     util::stdout_is_tty,
 };
-use chrono::Utc;
 use std::io::{self, Write};
 use termimad::MadSkin;
 
+/// Loads the task set for a command that only reads it, honouring the
+/// user's preferences. The store stays share-locked until the returned lock
+/// is dropped.
+fn load_task_set(conf: &Config, include_resolved: bool) -> Result<(TaskLock, TaskSet)> {
+    load_task_set_with(
+        TaskLock::acquire_shared(&conf.repo)?,
+        conf,
+        include_resolved,
+    )
+}
+
+/// Same as [`load_task_set`], locking the store exclusively for a command
+/// that changes it. Hold the lock until the changes are saved and committed.
+fn load_task_set_for_write(conf: &Config, include_resolved: bool) -> Result<(TaskLock, TaskSet)> {
+    load_task_set_with(TaskLock::acquire(&conf.repo)?, conf, include_resolved)
+}
+
+fn load_task_set_with(
+    lock: TaskLock,
+    conf: &Config,
+    include_resolved: bool,
+) -> Result<(TaskLock, TaskSet)> {
+    let ts = TaskSet::load_with_preferences(
+        &conf.repo,
+        &conf.ids_file,
+        include_resolved,
+        &conf.preferences,
+    )?;
+    Ok((lock, ts))
+}
+
 /// Add a new task to the task database
 pub fn cmd_add(conf: &Config, ctx: &Query, query: &Query) -> Result<()> {
     if query.text.is_empty() && query.template == 0 {
@@ -27,7 +58,7 @@ pub fn cmd_add(conf: &Config, ctx: &Query, query: &Query) -> Result<()> {
         ));
     }
 
-    let mut ts = TaskSet::load(&conf.repo, &conf.ids_file, false)?;
+    let (_lock, mut ts) = load_task_set_for_write(conf, false)?;
 
     if query.template > 0 {
         // Create task from template
@@ -53,6 +84,7 @@ pub fn cmd_add(conf: &Config, ctx: &Query, query: &Query) -> Result<()> {
         };
 
         task.modify(&merged_query);
+        task.apply_defaults(&conf.preferences);
         task = ts.must_load_task(task)?;
         ts.save_pending_changes()?;
         git_commit(&conf.repo, &format!("Added {}", task.summary))?;
@@ -80,6 +112,7 @@ pub fn cmd_add(conf: &Config, ctx: &Query, query: &Query) -> Result<()> {
             ..Default::default()
         };
 
+        task.apply_defaults(&conf.preferences);
         task = ts.must_load_task(task)?;
         ts.save_pending_changes()?;
 
@@ -120,7 +153,7 @@ pub fn cmd_done(conf: &Config, _ctx: &Query, query: &Query) -> Result<()> {
         ));
     }
 
-    let mut ts = TaskSet::load(&conf.repo, &conf.ids_file, false)?;
+    let (_lock, mut ts) = load_task_set_for_write(conf, false)?;
 
     // iterate over IDs instead of filtering; it's clearer and enables us to
     // test each ID exists, and ignore context/operators
@@ -135,11 +168,14 @@ pub fn cmd_done(conf: &Config, _ctx: &Query, query: &Query) -> Result<()> {
         }
 
         let mut task = task.clone();
-        task.status = STATUS_RESOLVED.to_string();
-        task.resolved = Some(Utc::now());
-        task.write_pending = true;
+        let next =
+            crate::recur::complete_recurring(&mut task, ts.clock().now(), conf.preferences.tz());
 
         ts.must_update_task(task)?;
+        if let Some(next) = next {
+            let next = ts.must_load_task(next)?;
+            println!("Next occurrence is {}: {}", next.id, next.summary);
+        }
     }
 
     ts.save_pending_changes()?;
@@ -158,6 +194,18 @@ pub fn cmd_done(conf: &Config, _ctx: &Query, query: &Query) -> Result<()> {
     Ok(())
 }
 
+/// Fails if a task loaded for editing changed on disk while the editor was
+/// open, as saving the edit would silently undo the other change
+fn ensure_unchanged(ts: &TaskSet, original: &Task) -> Result<()> {
+    match ts.get_by_uuid(&original.uuid) {
+        Some(current) if current.content_hash() == original.content_hash() => Ok(()),
+        _ => Err(RstaskError::Other(format!(
+            "{} changed while it was being edited, the edit was not saved",
+            original
+        ))),
+    }
+}
+
 /// Edit a task in $EDITOR
 pub fn cmd_edit(conf: &Config, _ctx: &Query, query: &Query) -> Result<()> {
     use crate::util::edit_string;
@@ -168,24 +216,42 @@ pub fn cmd_edit(conf: &Config, _ctx: &Query, query: &Query) -> Result<()> {
         ));
     }
 
-    let mut ts = TaskSet::load(&conf.repo, &conf.ids_file, false)?;
-    let task = ts.must_get_by_id(query.ids[0]);
+    let (lock, ts) = load_task_set(conf, false)?;
+    let task = ts.must_get_by_id(query.ids[0]).clone();
 
-    // Serialize task to markdown with frontmatter for editing
-    let markdown_str = crate::frontmatter::task_to_markdown(task)?;
+    // Serialize task to markdown with frontmatter for editing, with the
+    // notes inline and in plain text wherever they are stored
+    let markdown_str = crate::frontmatter::task_to_edit_markdown(&task, &conf.preferences)?;
+    // The store isn't kept locked while the editor is open
+    drop((lock, ts));
     let edited = edit_string(&markdown_str)?;
 
     // Parse edited markdown
-    let edited_task =
-        crate::frontmatter::task_from_markdown(&edited, &task.uuid, &task.status, task.id)?;
+    let (edited_task, warnings) = crate::frontmatter::task_from_markdown_verbose(
+        &edited,
+        &task.uuid,
+        &task.status,
+        task.id,
+        &conf.preferences,
+    )?;
+    for warning in &warnings {
+        eprintln!("Warning: {}", warning);
+    }
+
+    edited_task.validate_user_fields(&conf.preferences)?;
 
     // Validate UUID hasn't changed (should be guaranteed by task_from_markdown)
     if edited_task.uuid != task.uuid {
         return Err(RstaskError::Parse("task ID must not be edited".to_string()));
     }
 
+    let (_lock, mut ts) = load_task_set_for_write(conf, false)?;
+    ensure_unchanged(&ts, &task)?;
+
     let mut edited_task = edited_task;
-    edited_task.write_pending = true;
+    // The buffer carried the notes inline, so they replace the notes file
+    edited_task.notes_loaded = true;
+    edited_task.mark_dirty();
     ts.must_update_task(edited_task)?;
     ts.save_pending_changes()?;
     git_commit(&conf.repo, "Edited task")?;
@@ -211,7 +277,7 @@ pub fn cmd_log(conf: &Config, ctx: &Query, query: &Query) -> Result<()> {
         return Err(RstaskError::Parse("task description required".to_string()));
     }
 
-    let mut ts = TaskSet::load(&conf.repo, &conf.ids_file, false)?;
+    let (_lock, mut ts) = load_task_set_for_write(conf, false)?;
 
     ctx.print_context_description();
     let merged_query = query.merge(ctx);
@@ -224,7 +290,7 @@ pub fn cmd_log(conf: &Config, ctx: &Query, query: &Query) -> Result<()> {
         project: merged_query.project.clone(),
         priority: merged_query.priority.clone(),
         due: merged_query.due,
-        resolved: Some(Utc::now()),
+        resolved: Some(ts.clock().now()),
         ..Default::default()
     };
 
@@ -245,7 +311,7 @@ pub fn cmd_modify(conf: &Config, ctx: &Query, query: &Query) -> Result<()> {
         return Err(RstaskError::Parse("no operations specified".to_string()));
     }
 
-    let mut ts = TaskSet::load(&conf.repo, &conf.ids_file, false)?;
+    let (_lock, mut ts) = load_task_set_for_write(conf, false)?;
 
     if query.ids.is_empty() {
         // Apply to all tasks in context
@@ -264,7 +330,7 @@ pub fn cmd_modify(conf: &Config, ctx: &Query, query: &Query) -> Result<()> {
 
         for mut task in tasks_to_modify {
             task.modify(query);
-            task.write_pending = true;
+            task.mark_dirty();
             ts.must_update_task(task.clone())?;
             ts.save_pending_changes()?;
 
@@ -288,7 +354,7 @@ pub fn cmd_modify(conf: &Config, ctx: &Query, query: &Query) -> Result<()> {
             let task = ts.must_get_by_id(*id);
             let mut task = task.clone();
             task.modify(query);
-            task.write_pending = true;
+            task.mark_dirty();
             ts.must_update_task(task.clone())?;
             ts.save_pending_changes()?;
 
@@ -312,7 +378,7 @@ pub fn cmd_modify(conf: &Config, ctx: &Query, query: &Query) -> Result<()> {
 
 /// Show next/pending tasks (default view)
 pub fn cmd_next(conf: &Config, ctx: &Query, query: &Query) -> Result<()> {
-    let mut ts = TaskSet::load(&conf.repo, &conf.ids_file, false)?;
+    let (_lock, mut ts) = load_task_set(conf, false)?;
 
     let filter_query = if !query.ids.is_empty() {
         // addressing task by ID, ignores context
@@ -343,15 +409,19 @@ pub fn cmd_note(conf: &Config, _ctx: &Query, query: &Query) -> Result<()> {
         ));
     }
 
-    let mut ts = TaskSet::load(&conf.repo, &conf.ids_file, false)?;
-    let task = ts.must_get_by_id(query.ids[0]);
+    let (lock, ts) = load_task_set(conf, false)?;
+    let original = ts.must_get_by_id(query.ids[0]).clone();
+    // The store isn't kept locked while the editor is open
+    drop((lock, ts));
 
     // Edit notes (notes is already a String)
-    let edited = edit_string(&task.notes)?;
+    let edited = edit_string(&original.notes)?;
 
-    let mut task = task.clone();
+    let (_lock, mut ts) = load_task_set_for_write(conf, false)?;
+    ensure_unchanged(&ts, &original)?;
+    let mut task = original;
     task.notes = edited;
-    task.write_pending = true;
+    task.mark_dirty();
 
     ts.must_update_task(task)?;
     ts.save_pending_changes()?;
@@ -375,7 +445,7 @@ pub fn cmd_open(conf: &Config, _ctx: &Query, query: &Query) -> Result<()> {
         ));
     }
 
-    let ts = TaskSet::load(&conf.repo, &conf.ids_file, false)?;
+    let (_lock, ts) = load_task_set(conf, false)?;
 
     for id in &query.ids {
         let task = ts.must_get_by_id(*id);
@@ -407,7 +477,7 @@ pub fn cmd_remove(conf: &Config, _ctx: &Query, query: &Query) -> Result<()> {
         ));
     }
 
-    let mut ts = TaskSet::load(&conf.repo, &conf.ids_file, false)?;
+    let (_lock, mut ts) = load_task_set_for_write(conf, false)?;
 
     // Print tasks that will be removed (like Go version)
     for id in &query.ids {
@@ -454,7 +524,7 @@ pub fn cmd_remove(conf: &Config, _ctx: &Query, query: &Query) -> Result<()> {
 
 /// Show active tasks
 pub fn cmd_show_active(conf: &Config, ctx: &Query, query: &Query) -> Result<()> {
-    let mut ts = TaskSet::load(&conf.repo, &conf.ids_file, true)?;
+    let (_lock, mut ts) = load_task_set(conf, true)?;
     let merged_query = query.merge(ctx);
 
     ts.filter(&merged_query);
@@ -466,7 +536,7 @@ pub fn cmd_show_active(conf: &Config, ctx: &Query, query: &Query) -> Result<()>
 
 /// Show tasks grouped by project
 pub fn cmd_show_projects(conf: &Config, ctx: &Query, query: &Query) -> Result<()> {
-    let mut ts = TaskSet::load(&conf.repo, &conf.ids_file, true)?;
+    let (_lock, mut ts) = load_task_set(conf, true)?;
     let merged_query = query.merge(ctx);
 
     ts.filter(&merged_query);
@@ -477,7 +547,7 @@ pub fn cmd_show_projects(conf: &Config, ctx: &Query, query: &Query) -> Result<()
 
 /// Show open tasks (pending + active + paused)
 pub fn cmd_show_open(conf: &Config, ctx: &Query, query: &Query) -> Result<()> {
-    let mut ts = TaskSet::load(&conf.repo, &conf.ids_file, false)?;
+    let (_lock, mut ts) = load_task_set(conf, false)?;
     let merged_query = query.merge(ctx);
 
     ts.filter(&merged_query);
@@ -489,7 +559,7 @@ pub fn cmd_show_open(conf: &Config, ctx: &Query, query: &Query) -> Result<()> {
 
 /// Show a single task with rendered markdown notes
 pub fn cmd_show(conf: &Config, _ctx: &Query, query: &Query) -> Result<()> {
-    let ts = TaskSet::load(&conf.repo, &conf.ids_file, true)?;
+    let (_lock, ts) = load_task_set(conf, true)?;
 
     // Get the task ID from the query
     if query.ids.is_empty() {
@@ -522,7 +592,7 @@ pub fn cmd_show(conf: &Config, _ctx: &Query, query: &Query) -> Result<()> {
 
 /// Show paused tasks
 pub fn cmd_show_paused(conf: &Config, ctx: &Query, query: &Query) -> Result<()> {
-    let mut ts = TaskSet::load(&conf.repo, &conf.ids_file, true)?;
+    let (_lock, mut ts) = load_task_set(conf, true)?;
     let merged_query = query.merge(ctx);
 
     ts.filter(&merged_query);
@@ -534,7 +604,7 @@ pub fn cmd_show_paused(conf: &Config, ctx: &Query, query: &Query) -> Result<()>
 
 /// Show resolved tasks
 pub fn cmd_show_resolved(conf: &Config, ctx: &Query, query: &Query) -> Result<()> {
-    let mut ts = TaskSet::load(&conf.repo, &conf.ids_file, true)?;
+    let (_lock, mut ts) = load_task_set(conf, true)?;
     let merged_query = query.merge(ctx);
 
     ts.unhide();
@@ -547,7 +617,7 @@ pub fn cmd_show_resolved(conf: &Config, ctx: &Query, query: &Query) -> Result<()
 
 /// Show all tags in use
 pub fn cmd_show_tags(conf: &Config, ctx: &Query, query: &Query) -> Result<()> {
-    let mut ts = TaskSet::load(&conf.repo, &conf.ids_file, true)?;
+    let (_lock, mut ts) = load_task_set(conf, true)?;
     let merged_query = query.merge(ctx);
 
     ts.filter(&merged_query);
@@ -574,7 +644,7 @@ pub fn cmd_show_tags(conf: &Config, ctx: &Query, query: &Query) -> Result<()> {
 
 /// Show template tasks
 pub fn cmd_show_templates(conf: &Config, ctx: &Query, query: &Query) -> Result<()> {
-    let mut ts = TaskSet::load(&conf.repo, &conf.ids_file, false)?;
+    let (_lock, mut ts) = load_task_set(conf, false)?;
 
     ts.unhide();
     ts.filter_by_status(STATUS_TEMPLATE);
@@ -595,7 +665,7 @@ pub fn cmd_show_unorganised(conf: &Config, ctx: &Query, query: &Query) -> Result
         ));
     }
 
-    let mut ts = TaskSet::load(&conf.repo, &conf.ids_file, false)?;
+    let (_lock, mut ts) = load_task_set(conf, false)?;
 
     // Don't filter by query or context - show ALL unorganised tasks
     ts.filter_unorganised();
@@ -612,7 +682,7 @@ pub fn cmd_start(conf: &Config, _ctx: &Query, query: &Query) -> Result<()> {
         ));
     }
 
-    let mut ts = TaskSet::load(&conf.repo, &conf.ids_file, false)?;
+    let (_lock, mut ts) = load_task_set_for_write(conf, false)?;
 
     for id in &query.ids {
         let task = ts.must_get_by_id(*id);
@@ -626,7 +696,7 @@ pub fn cmd_start(conf: &Config, _ctx: &Query, query: &Query) -> Result<()> {
 
         let mut task = task.clone();
         task.status = STATUS_ACTIVE.to_string();
-        task.write_pending = true;
+        task.mark_dirty();
 
         ts.must_update_task(task)?;
     }
@@ -655,7 +725,7 @@ pub fn cmd_stop(conf: &Config, _ctx: &Query, query: &Query) -> Result<()> {
         ));
     }
 
-    let mut ts = TaskSet::load(&conf.repo, &conf.ids_file, false)?;
+    let (_lock, mut ts) = load_task_set_for_write(conf, false)?;
 
     for id in &query.ids {
         let task = ts.must_get_by_id(*id);
@@ -669,7 +739,7 @@ pub fn cmd_stop(conf: &Config, _ctx: &Query, query: &Query) -> Result<()> {
 
         let mut task = task.clone();
         task.status = STATUS_PAUSED.to_string();
-        task.write_pending = true;
+        task.mark_dirty();
 
         ts.must_update_task(task)?;
     }
@@ -692,6 +762,13 @@ pub fn cmd_stop(conf: &Config, _ctx: &Query, query: &Query) -> Result<()> {
 
 /// Sync repository with git remote
 pub fn cmd_sync(repo_path: &str) -> Result<()> {
+    // Pulling rewrites task files, so nothing else may touch the store
+    let _lock = TaskLock::acquire(std::path::Path::new(repo_path))?;
+    sync_repo(repo_path)
+}
+
+/// Pulls and pushes the repository, for callers already holding the lock
+fn sync_repo(repo_path: &str) -> Result<()> {
     use crate::git::{git_pull, git_push};
 
     // Pull with fast-forward, creating merge commits if needed
@@ -703,12 +780,13 @@ pub fn cmd_sync(repo_path: &str) -> Result<()> {
     Ok(())
 }
 
-/// Automatically sync if configured to do so
+/// Automatically sync if configured to do so. Callers change the store and
+/// so already hold its lock.
 fn auto_sync_if_enabled(conf: &Config) -> Result<()> {
     use crate::preferences::SyncFrequency;
 
     if conf.preferences.sync_frequency == SyncFrequency::AfterEveryModification {
-        cmd_sync(conf.repo.to_str().unwrap())?;
+        sync_repo(conf.repo.to_str().unwrap())?;
     }
 
     Ok(())
@@ -718,7 +796,7 @@ fn auto_sync_if_enabled(conf: &Config) -> Result<()> {
 pub fn cmd_template(conf: &Config, ctx: &Query, query: &Query) -> Result<()> {
     use crate::preferences::BulkCommitStrategy;
 
-    let mut ts = TaskSet::load(&conf.repo, &conf.ids_file, false)?;
+    let (_lock, mut ts) = load_task_set_for_write(conf, false)?;
 
     if !query.ids.is_empty() {
         // Convert existing task(s) to template(s)
@@ -728,7 +806,7 @@ pub fn cmd_template(conf: &Config, ctx: &Query, query: &Query) -> Result<()> {
             let task = ts.must_get_by_id(*id);
             let mut task = task.clone();
             task.status = STATUS_TEMPLATE.to_string();
-            task.write_pending = true;
+            task.mark_dirty();
             ts.must_update_task(task.clone())?;
 
             if conf.preferences.bulk_commit_strategy == BulkCommitStrategy::PerTask {
@@ -760,6 +838,7 @@ pub fn cmd_template(conf: &Config, ctx: &Query, query: &Query) -> Result<()> {
             ..Default::default()
         };
 
+        task.apply_defaults(&conf.preferences);
         task = ts.must_load_task(task)?;
         ts.save_pending_changes()?;
         git_commit(&conf.repo, &format!("Created template: {}", task.summary))?;
@@ -777,6 +856,8 @@ pub fn cmd_template(conf: &Config, ctx: &Query, query: &Query) -> Result<()> {
 pub fn cmd_undo(conf: &Config, args: &[String]) -> Result<()> {
     use crate::git::git_reset;
 
+    let _lock = TaskLock::acquire(&conf.repo)?;
+
     // Default to 1 commit
     let count = if args.len() > 2 {
         args[2].parse::<usize>().unwrap_or(1)
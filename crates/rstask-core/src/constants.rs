@@ -25,6 +25,8 @@ pub const STATUS_DEFERRED: &str = "deferred";
 pub const STATUS_PAUSED: &str = "paused";
 pub const STATUS_RECURRING: &str = "recurring";
 pub const STATUS_TEMPLATE: &str = "template";
+/// Parked for some day, maybe; never listed among open tasks
+pub const STATUS_SOMEDAY: &str = "someday";
 
 // Command constants
 pub const CMD_NEXT: &str = "next";
@@ -107,9 +109,15 @@ pub const ALL_STATUSES: &[&str] = &[
     STATUS_RECURRING,
     STATUS_RESOLVED,
     STATUS_TEMPLATE,
+    STATUS_SOMEDAY,
 ];
 
-pub const HIDDEN_STATUSES: &[&str] = &[STATUS_RECURRING, STATUS_RESOLVED, STATUS_TEMPLATE];
+pub const HIDDEN_STATUSES: &[&str] = &[
+    STATUS_RECURRING,
+    STATUS_RESOLVED,
+    STATUS_TEMPLATE,
+    STATUS_SOMEDAY,
+];
 
 pub const NON_RESOLVED_STATUSES: &[&str] = &[
     STATUS_ACTIVE,
@@ -119,6 +127,7 @@ pub const NON_RESOLVED_STATUSES: &[&str] = &[
     STATUS_PAUSED,
     STATUS_RECURRING,
     STATUS_TEMPLATE,
+    STATUS_SOMEDAY,
 ];
 
 // Valid status transitions
@@ -130,6 +139,10 @@ pub const VALID_STATUS_TRANSITIONS: &[(&str, &str)] = &[
     (STATUS_PAUSED, STATUS_RESOLVED),
     (STATUS_ACTIVE, STATUS_RESOLVED),
     (STATUS_PENDING, STATUS_TEMPLATE),
+    (STATUS_RESOLVED, STATUS_PENDING),
+    (STATUS_PENDING, STATUS_SOMEDAY),
+    (STATUS_PAUSED, STATUS_SOMEDAY),
+    (STATUS_SOMEDAY, STATUS_PENDING),
 ];
 
 pub const ALL_CMDS: &[&str] = &[
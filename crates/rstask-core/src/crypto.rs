@@ -0,0 +1,216 @@
+// Note encryption - keeps task notes out of plaintext in the repository
+//
+// Encrypted notes are stored as a marker line followed by base64 of the
+// Argon2 salt, nonce and XChaCha20-Poly1305 ciphertext, wrapped to 76
+// columns. The frontmatter is never encrypted.
+use crate::task::Task;
+use crate::{Result, RstaskError};
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD as BASE64;
+use chacha20poly1305::aead::rand_core::RngCore;
+use chacha20poly1305::aead::{Aead, AeadCore, KeyInit, OsRng};
+use chacha20poly1305::{XChaCha20Poly1305, XNonce};
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::{Arc, Mutex};
+
+/// First line of an encrypted note
+pub const ENCRYPTED_NOTE_MARKER: &str = "<!-- rstask:encrypted-notes -->";
+
+/// Environment variable holding the passphrase notes are encrypted with
+pub const NOTES_PASSPHRASE_ENV: &str = "RSTASK_NOTES_PASSPHRASE";
+
+const SALT_LEN: usize = 16;
+const NONCE_LEN: usize = 24;
+const LINE_WIDTH: usize = 76;
+
+type Salt = [u8; SALT_LEN];
+
+#[derive(Clone)]
+enum Secret {
+    /// Used as is, whatever the salt
+    Key([u8; 32]),
+    Passphrase(String),
+}
+
+/// Symmetric key for note encryption. Keys from a passphrase encrypt with a
+/// salt chosen at random when they are created, and derive the key for any
+/// other salt met when decrypting, remembering it for the next note.
+#[derive(Clone)]
+pub struct NoteKey {
+    secret: Secret,
+    salt: Salt,
+    key: [u8; 32],
+    derived: Arc<Mutex<HashMap<Vec<u8>, [u8; 32]>>>,
+}
+
+fn derive_key(passphrase: &str, salt: &[u8]) -> Result<[u8; 32]> {
+    let mut key = [0u8; 32];
+    argon2::Argon2::default()
+        .hash_password_into(passphrase.as_bytes(), salt, &mut key)
+        .map_err(|e| RstaskError::Encryption(e.to_string()))?;
+    Ok(key)
+}
+
+impl NoteKey {
+    pub fn from_bytes(bytes: [u8; 32]) -> Self {
+        NoteKey {
+            secret: Secret::Key(bytes),
+            salt: [0; SALT_LEN],
+            key: bytes,
+            derived: Default::default(),
+        }
+    }
+
+    /// Derives a key from a passphrase with Argon2id and a random salt
+    pub fn from_passphrase(passphrase: &str) -> Result<Self> {
+        let mut salt = [0u8; SALT_LEN];
+        OsRng.fill_bytes(&mut salt);
+        Ok(NoteKey {
+            key: derive_key(passphrase, &salt)?,
+            secret: Secret::Passphrase(passphrase.to_string()),
+            salt,
+            derived: Default::default(),
+        })
+    }
+
+    /// Derives the key from the passphrase in the environment, None if it
+    /// is unset or empty
+    pub fn from_env() -> Result<Option<Self>> {
+        match std::env::var(NOTES_PASSPHRASE_ENV) {
+            Ok(passphrase) if !passphrase.is_empty() => {
+                Self::from_passphrase(&passphrase).map(Some)
+            }
+            _ => Ok(None),
+        }
+    }
+
+    /// The key for notes encrypted with `salt`
+    fn key_for(&self, salt: &[u8]) -> Result<[u8; 32]> {
+        let passphrase = match &self.secret {
+            Secret::Key(key) => return Ok(*key),
+            Secret::Passphrase(_) if salt == self.salt => return Ok(self.key),
+            Secret::Passphrase(passphrase) => passphrase,
+        };
+
+        let mut derived = self.derived.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some(key) = derived.get(salt) {
+            return Ok(*key);
+        }
+        let key = derive_key(passphrase, salt)?;
+        derived.insert(salt.to_vec(), key);
+        Ok(key)
+    }
+}
+
+impl fmt::Debug for NoteKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("NoteKey(..)")
+    }
+}
+
+/// Whether the notes are an encrypted block
+pub fn is_encrypted_note(notes: &str) -> bool {
+    notes.lines().next() == Some(ENCRYPTED_NOTE_MARKER)
+}
+
+/// Encrypts notes into a marked base64 block
+pub fn encrypt_note(plaintext: &str, key: &NoteKey) -> Result<String> {
+    let cipher = XChaCha20Poly1305::new((&key.key).into());
+    let nonce = XChaCha20Poly1305::generate_nonce(&mut OsRng);
+    let ciphertext = cipher
+        .encrypt(&nonce, plaintext.as_bytes())
+        .map_err(|_| RstaskError::Encryption("failed to encrypt notes".to_string()))?;
+
+    let mut payload = key.salt.to_vec();
+    payload.extend_from_slice(&nonce);
+    payload.extend_from_slice(&ciphertext);
+    let encoded = BASE64.encode(payload);
+
+    let mut block = String::from(ENCRYPTED_NOTE_MARKER);
+    for chunk in encoded.as_bytes().chunks(LINE_WIDTH) {
+        block.push('\n');
+        // Base64 output is ASCII, so chunks are valid UTF-8
+        block.push_str(std::str::from_utf8(chunk).unwrap());
+    }
+    Ok(block)
+}
+
+/// Decrypts a block produced by [`encrypt_note`]
+pub fn decrypt_note(block: &str, key: &NoteKey) -> Result<String> {
+    let mut lines = block.lines();
+    if lines.next() != Some(ENCRYPTED_NOTE_MARKER) {
+        return Err(RstaskError::Encryption(
+            "notes are not encrypted".to_string(),
+        ));
+    }
+
+    let encoded: String = lines.map(str::trim).collect();
+    let payload = BASE64
+        .decode(encoded)
+        .map_err(|e| RstaskError::Encryption(format!("invalid encrypted notes: {}", e)))?;
+    if payload.len() < SALT_LEN + NONCE_LEN {
+        return Err(RstaskError::Encryption(
+            "encrypted notes are truncated".to_string(),
+        ));
+    }
+
+    let (salt, payload) = payload.split_at(SALT_LEN);
+    let (nonce, ciphertext) = payload.split_at(NONCE_LEN);
+    let cipher = XChaCha20Poly1305::new((&key.key_for(salt)?).into());
+    let plaintext = cipher
+        .decrypt(XNonce::from_slice(nonce), ciphertext)
+        .map_err(|_| {
+            RstaskError::Encryption("wrong key or corrupted encrypted notes".to_string())
+        })?;
+
+    String::from_utf8(plaintext)
+        .map_err(|_| RstaskError::Encryption("decrypted notes are not UTF-8".to_string()))
+}
+
+/// Decrypts the task's notes in place if they are encrypted. Without a key
+/// the encrypted block is left as is, and written back unchanged.
+pub fn decrypt_task_notes(task: &mut Task, key: Option<&NoteKey>) -> Result<()> {
+    if let Some(key) = key
+        && is_encrypted_note(&task.notes)
+    {
+        task.notes = decrypt_note(&task.notes, key)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_encrypt_decrypt_roundtrip() {
+        let key = NoteKey::from_bytes([7; 32]);
+        let notes = "account number 1234\n\n- [ ] call the bank";
+
+        let block = encrypt_note(notes, &key).unwrap();
+        assert!(is_encrypted_note(&block));
+        assert!(!block.contains("1234"));
+        assert!(block.lines().all(|line| line.len() <= LINE_WIDTH));
+        assert_eq!(decrypt_note(&block, &key).unwrap(), notes);
+
+        let other = NoteKey::from_bytes([8; 32]);
+        assert!(decrypt_note(&block, &other).is_err());
+        assert!(decrypt_note("plain notes", &key).is_err());
+    }
+
+    #[test]
+    fn test_passphrase_keys_use_random_salts() {
+        let a = NoteKey::from_passphrase("correct horse").unwrap();
+        let b = NoteKey::from_passphrase("correct horse").unwrap();
+        let c = NoteKey::from_passphrase("battery staple").unwrap();
+        assert_ne!(a.salt, b.salt);
+        assert_ne!(a.key, b.key);
+
+        // The salt travels with the note, so any key from the passphrase
+        // can decrypt it
+        let block = encrypt_note("the vault code is 0000", &a).unwrap();
+        assert_eq!(decrypt_note(&block, &b).unwrap(), "the vault code is 0000");
+        assert!(decrypt_note(&block, &c).is_err());
+    }
+}
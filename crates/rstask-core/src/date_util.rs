@@ -1,5 +1,7 @@
 use crate::Result;
-use chrono::{Datelike, Days, Local, NaiveDate, TimeZone, Weekday};
+use chrono::{
+    DateTime, Datelike, Days, Duration, Local, Months, NaiveDate, TimeZone, Utc, Weekday,
+};
 
 /// Returns the start of day (midnight) for a given time
 pub fn start_of_day(t: chrono::DateTime<Local>) -> chrono::DateTime<Local> {
@@ -8,8 +10,22 @@ pub fn start_of_day(t: chrono::DateTime<Local>) -> chrono::DateTime<Local> {
         .unwrap()
 }
 
+/// Parses an ISO week such as "2024-W23" to the Monday it starts on
+pub fn parse_iso_week(s: &str) -> Option<NaiveDate> {
+    let (year, week) = s.split_once(['W', 'w'])?;
+    let year = year.strip_suffix('-')?;
+    if year.len() != 4 || week.len() != 2 {
+        return None;
+    }
+    NaiveDate::from_isoywd_opt(year.parse().ok()?, week.parse().ok()?, Weekday::Mon)
+}
+
 /// Parses weekday strings (full names and abbreviations)
-fn weekday_str_to_time(date_str: &str, selector: &str) -> Option<chrono::DateTime<Local>> {
+fn weekday_str_to_time(
+    date_str: &str,
+    selector: &str,
+    now: chrono::DateTime<Local>,
+) -> Option<chrono::DateTime<Local>> {
     let weekday = match date_str.to_lowercase().as_str() {
         "sun" | "sunday" => Weekday::Sun,
         "mon" | "monday" => Weekday::Mon,
@@ -21,7 +37,6 @@ fn weekday_str_to_time(date_str: &str, selector: &str) -> Option<chrono::DateTim
         _ => return None,
     };
 
-    let now = Local::now();
     let now_weekday = now.weekday();
     let days_difference =
         weekday.num_days_from_monday() as i64 - now_weekday.num_days_from_monday() as i64;
@@ -41,11 +56,70 @@ fn weekday_str_to_time(date_str: &str, selector: &str) -> Option<chrono::DateTim
     Some(target_date)
 }
 
+/// Parses offsets like "+3d" or "+1w" into the start of that day
+fn offset_str_to_time(
+    date_str: &str,
+    now: chrono::DateTime<Local>,
+) -> Option<chrono::DateTime<Local>> {
+    let rest = date_str.strip_prefix('+')?;
+    let (count, unit) = rest.split_at(rest.len().checked_sub(1)?);
+    let count: u64 = count.parse().ok()?;
+
+    let days = match unit {
+        "d" => count,
+        "w" => count.checked_mul(7)?,
+        _ => return None,
+    };
+
+    now.checked_add_days(Days::new(days)).map(start_of_day)
+}
+
+/// Parses period phrases. "next-week", "next-month" and "next-year" give the
+/// first day of that period (weeks start on Monday); "end-of-week",
+/// "end-of-month" and "end-of-year" give the last day of the current one.
+/// Like other dates, these are the start of the day in local time.
+fn period_str_to_time(
+    date_str: &str,
+    now: chrono::DateTime<Local>,
+) -> Option<chrono::DateTime<Local>> {
+    let today = now.date_naive();
+    let week_start = today - Days::new(today.weekday().num_days_from_monday() as u64);
+    let month_start = today.with_day(1)?;
+
+    let date = match date_str {
+        "next-week" => week_start + Days::new(7),
+        "next-month" => month_start + Months::new(1),
+        "next-year" => NaiveDate::from_ymd_opt(today.year() + 1, 1, 1)?,
+        "end-of-week" => week_start + Days::new(6),
+        "end-of-month" => month_start + Months::new(1) - Days::new(1),
+        "end-of-year" => NaiveDate::from_ymd_opt(today.year(), 12, 31)?,
+        _ => return None,
+    };
+
+    Local
+        .from_local_datetime(&date.and_hms_opt(0, 0, 0)?)
+        .earliest()
+}
+
 /// Parses a date string into a DateTime
-/// Supports: "today", "tomorrow", "yesterday", "[next-]monday", "YYYY-MM-DD", "MM-DD", "DD"
+/// Supports: "today", "tomorrow", "yesterday", "[next-]monday", "+3d", "+1w",
+/// "next-week", "next-month", "end-of-month" and the like, "YYYY-MM-DD", "YYYY-Www",
+/// "MM-DD", "DD". Words may be separated by spaces instead of dashes, as in
+/// "next friday" or "end of month".
 pub fn parse_str_to_date(date_str: &str) -> Result<chrono::DateTime<Local>> {
-    let now = Local::now();
-    let lower = date_str.trim().to_lowercase();
+    parse_str_to_date_at(date_str, Local::now())
+}
+
+/// Same as [`parse_str_to_date`], with relative dates counted from `now`
+pub fn parse_str_to_date_at(
+    date_str: &str,
+    now: chrono::DateTime<Local>,
+) -> Result<chrono::DateTime<Local>> {
+    let lower = date_str
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join("-")
+        .to_lowercase();
 
     match lower.as_str() {
         "today" => return Ok(start_of_day(now)),
@@ -54,18 +128,35 @@ pub fn parse_str_to_date(date_str: &str) -> Result<chrono::DateTime<Local>> {
         _ => {}
     }
 
+    // Check for +[n]d, +[n]w
+    if let Some(date) = offset_str_to_time(&lower, now) {
+        return Ok(date);
+    }
+
+    // Check for next-week, end-of-month, ...
+    if let Some(date) = period_str_to_time(&lower, now) {
+        return Ok(date);
+    }
+
     // Check for next-[weekday], this-[weekday]
     if let Some((selector, rest)) = lower.split_once('-')
-        && let Some(date) = weekday_str_to_time(rest, selector)
+        && let Some(date) = weekday_str_to_time(rest, selector, now)
     {
         return Ok(date);
     }
 
     // Check for [weekday]
-    if let Some(date) = weekday_str_to_time(&lower, "") {
+    if let Some(date) = weekday_str_to_time(&lower, "", now) {
         return Ok(date);
     }
 
+    // Try YYYY-Www, the Monday of an ISO week
+    if let Some(naive_date) = parse_iso_week(date_str) {
+        return Ok(Local
+            .from_local_datetime(&naive_date.and_hms_opt(0, 0, 0).unwrap())
+            .unwrap());
+    }
+
     // Try YYYY-MM-DD
     if let Ok(naive_date) = NaiveDate::parse_from_str(date_str, "%Y-%m-%d") {
         return Ok(Local
@@ -77,11 +168,12 @@ pub fn parse_str_to_date(date_str: &str) -> Result<chrono::DateTime<Local>> {
     if date_str.contains('-') && date_str.split('-').count() == 2 {
         let parts: Vec<&str> = date_str.split('-').collect();
         if let (Ok(month), Ok(day)) = (parts[0].parse::<u32>(), parts[1].parse::<u32>())
-            && let Some(naive_date) = NaiveDate::from_ymd_opt(now.year(), month, day) {
-                return Ok(Local
-                    .from_local_datetime(&naive_date.and_hms_opt(0, 0, 0).unwrap())
-                    .unwrap());
-            }
+            && let Some(naive_date) = NaiveDate::from_ymd_opt(now.year(), month, day)
+        {
+            return Ok(Local
+                .from_local_datetime(&naive_date.and_hms_opt(0, 0, 0).unwrap())
+                .unwrap());
+        }
     }
 
     // Try DD (day of month)
@@ -95,7 +187,7 @@ pub fn parse_str_to_date(date_str: &str) -> Result<chrono::DateTime<Local>> {
     }
 
     Err(crate::RstaskError::Parse(format!(
-        "Invalid due date format: {}\nExpected format: YYYY-MM-DD, MM-DD or DD, relative date like 'next-monday', 'today', etc.",
+        "Invalid due date format: {}\nExpected format: YYYY-MM-DD, YYYY-Www, MM-DD or DD, relative date like 'next-monday', 'today', '+1w', etc.",
         date_str
     )))
 }
@@ -140,17 +232,26 @@ pub fn parse_due_date_arg(due_str: &str) -> Result<(String, chrono::DateTime<Loc
 
 /// Formats a due date for display
 pub fn format_due_date(due: chrono::DateTime<Local>) -> String {
-    let now = Local::now();
+    format_relative_due(due.with_timezone(&Utc), Utc::now(), &Local)
+}
+
+/// Formats a due date for display relative to `now`, as seen from the given timezone
+pub fn format_relative_due<Tz: TimeZone>(due: DateTime<Utc>, now: DateTime<Utc>, tz: &Tz) -> String
+where
+    Tz::Offset: std::fmt::Display,
+{
+    let due = due.with_timezone(tz);
+    let now = now.with_timezone(tz);
 
     if due.date_naive() == now.date_naive() {
         return "today".to_string();
     }
 
-    if due.date_naive() == (now + Days::new(1)).date_naive() {
+    if due.date_naive() == (now.date_naive() + Days::new(1)) {
         return "tomorrow".to_string();
     }
 
-    if due.date_naive() == (now - Days::new(1)).date_naive() {
+    if due.date_naive() == (now.date_naive() - Days::new(1)) {
         return "yesterday".to_string();
     }
 
@@ -172,6 +273,180 @@ pub fn format_due_date(due: chrono::DateTime<Local>) -> String {
     }
 }
 
+/// Parses an ISO-8601 duration such as "PT2H30M", "P1DT4H" or "P2W".
+/// Years and months are rejected as they don't have a fixed length.
+pub fn parse_iso8601_duration(duration_str: &str) -> Result<Duration> {
+    let invalid = || {
+        crate::RstaskError::Parse(format!(
+            "Invalid duration: {}\nExpected an ISO-8601 duration like PT2H30M, P1D or P2W",
+            duration_str
+        ))
+    };
+
+    let rest = duration_str
+        .trim()
+        .strip_prefix(['P', 'p'])
+        .ok_or_else(invalid)?;
+    if rest.is_empty() {
+        return Err(invalid());
+    }
+
+    let mut seconds = 0.0;
+    let mut number = String::new();
+    let mut in_time = false;
+    let mut seen_component = false;
+
+    for c in rest.chars() {
+        let c = c.to_ascii_uppercase();
+        if c.is_ascii_digit() || c == '.' || c == ',' {
+            number.push(if c == ',' { '.' } else { c });
+            continue;
+        }
+
+        if c == 'T' {
+            if in_time || !number.is_empty() {
+                return Err(invalid());
+            }
+            in_time = true;
+            continue;
+        }
+
+        let value: f64 = number.parse().map_err(|_| invalid())?;
+        number.clear();
+
+        let unit = match (in_time, c) {
+            (false, 'W') => 7.0 * 86400.0,
+            (false, 'D') => 86400.0,
+            (true, 'H') => 3600.0,
+            (true, 'M') => 60.0,
+            (true, 'S') => 1.0,
+            _ => return Err(invalid()),
+        };
+
+        seconds += value * unit;
+        seen_component = true;
+    }
+
+    if !number.is_empty() || !seen_component {
+        return Err(invalid());
+    }
+
+    seconds_to_duration(seconds).ok_or_else(invalid)
+}
+
+/// Rounds to a whole number of seconds, None when out of range for a
+/// Duration
+fn seconds_to_duration(seconds: f64) -> Option<Duration> {
+    if !seconds.is_finite() {
+        return None;
+    }
+    // Out of range values saturate, which try_seconds then rejects
+    Duration::try_seconds(seconds.round() as i64)
+}
+
+/// Parses a shorthand duration like "30m", "2h", "1.5h", "1d" or "1h30m".
+/// Units are w, d, h, m and s; a day is 24 hours.
+pub fn parse_duration_shorthand(duration_str: &str) -> Result<Duration> {
+    let invalid = || {
+        crate::RstaskError::Parse(format!(
+            "Invalid duration: {}\nExpected a duration like 30m, 2h, 1.5h or 1d",
+            duration_str
+        ))
+    };
+
+    let mut seconds = 0.0;
+    let mut number = String::new();
+    let mut seen_component = false;
+
+    for c in duration_str.trim().chars() {
+        if c.is_ascii_digit() || c == '.' {
+            number.push(c);
+            continue;
+        }
+
+        let value: f64 = number.parse().map_err(|_| invalid())?;
+        number.clear();
+
+        let unit = match c.to_ascii_lowercase() {
+            'w' => 7.0 * 86400.0,
+            'd' => 86400.0,
+            'h' => 3600.0,
+            'm' => 60.0,
+            's' => 1.0,
+            _ => return Err(invalid()),
+        };
+
+        seconds += value * unit;
+        seen_component = true;
+    }
+
+    if !number.is_empty() || !seen_component {
+        return Err(invalid());
+    }
+
+    seconds_to_duration(seconds).ok_or_else(invalid)
+}
+
+/// Parses a duration written either as ISO-8601 ("PT2H") or as shorthand ("2h")
+pub fn parse_duration(duration_str: &str) -> Result<Duration> {
+    if duration_str.trim_start().starts_with(['P', 'p']) {
+        parse_iso8601_duration(duration_str)
+    } else {
+        parse_duration_shorthand(duration_str)
+    }
+}
+
+/// Formats a duration as shorthand, e.g. "1d2h30m". Zero is "0m".
+pub fn format_duration_shorthand(duration: Duration) -> String {
+    let total = duration.num_seconds().max(0);
+    let parts = [
+        (total / 86400, 'd'),
+        ((total % 86400) / 3600, 'h'),
+        ((total % 3600) / 60, 'm'),
+        (total % 60, 's'),
+    ];
+
+    let out: String = parts
+        .iter()
+        .filter(|(value, _)| *value > 0)
+        .map(|(value, unit)| format!("{}{}", value, unit))
+        .collect();
+    if out.is_empty() {
+        "0m".to_string()
+    } else {
+        out
+    }
+}
+
+/// Formats a duration as ISO-8601, e.g. "P1DT2H30M". Zero is "PT0S".
+pub fn format_iso8601_duration(duration: Duration) -> String {
+    let total = duration.num_seconds().max(0);
+    let days = total / 86400;
+    let hours = (total % 86400) / 3600;
+    let minutes = (total % 3600) / 60;
+    let seconds = total % 60;
+
+    let mut out = String::from("P");
+    if days > 0 {
+        out.push_str(&format!("{}D", days));
+    }
+
+    if hours > 0 || minutes > 0 || seconds > 0 || days == 0 {
+        out.push('T');
+        if hours > 0 {
+            out.push_str(&format!("{}H", hours));
+        }
+        if minutes > 0 {
+            out.push_str(&format!("{}M", minutes));
+        }
+        if seconds > 0 || (hours == 0 && minutes == 0) {
+            out.push_str(&format!("{}S", seconds));
+        }
+    }
+
+    out
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -195,6 +470,27 @@ mod tests {
         assert_eq!(date.day(), 25);
     }
 
+    #[test]
+    fn test_parse_iso_week() {
+        assert_eq!(
+            parse_iso_week("2024-W23"),
+            NaiveDate::from_ymd_opt(2024, 6, 3)
+        );
+        // Week 1 of 2025 starts in 2024
+        assert_eq!(
+            parse_iso_week("2025-w01"),
+            NaiveDate::from_ymd_opt(2024, 12, 30)
+        );
+        assert_eq!(parse_iso_week("2024-W54"), None);
+        assert_eq!(parse_iso_week("2024W23"), None);
+
+        let date = parse_str_to_date("2024-W23").unwrap();
+        assert_eq!(
+            date.date_naive(),
+            NaiveDate::from_ymd_opt(2024, 6, 3).unwrap()
+        );
+    }
+
     #[test]
     fn test_parse_weekdays() {
         // These tests will pass as long as the weekday parsing works
@@ -203,6 +499,74 @@ mod tests {
         assert!(parse_str_to_date("this-wed").is_ok());
     }
 
+    #[test]
+    fn test_parse_offsets() {
+        let now = Local.with_ymd_and_hms(2024, 6, 5, 15, 30, 0).unwrap();
+        let day = |d| Local.with_ymd_and_hms(2024, 6, d, 0, 0, 0).unwrap();
+
+        assert_eq!(parse_str_to_date_at("+3d", now).unwrap(), day(8));
+        assert_eq!(parse_str_to_date_at("+1w", now).unwrap(), day(12));
+        assert_eq!(parse_str_to_date_at("+0d", now).unwrap(), day(5));
+        assert!(parse_str_to_date_at("+w", now).is_err());
+        assert!(parse_str_to_date_at("+2y", now).is_err());
+    }
+
+    #[test]
+    fn test_parse_periods() {
+        // A Wednesday
+        let now = Local.with_ymd_and_hms(2024, 1, 31, 15, 30, 0).unwrap();
+        let day = |y, m, d| Local.with_ymd_and_hms(y, m, d, 0, 0, 0).unwrap();
+
+        assert_eq!(
+            parse_str_to_date_at("next week", now).unwrap(),
+            day(2024, 2, 5)
+        );
+        assert_eq!(
+            parse_str_to_date_at("next-month", now).unwrap(),
+            day(2024, 2, 1)
+        );
+        assert_eq!(
+            parse_str_to_date_at("next year", now).unwrap(),
+            day(2025, 1, 1)
+        );
+        assert_eq!(
+            parse_str_to_date_at("end of week", now).unwrap(),
+            day(2024, 2, 4)
+        );
+        assert_eq!(
+            parse_str_to_date_at("end of month", now).unwrap(),
+            day(2024, 1, 31)
+        );
+        assert_eq!(
+            parse_str_to_date_at("End-Of-Year", now).unwrap(),
+            day(2024, 12, 31)
+        );
+        assert_eq!(
+            parse_str_to_date_at("next friday", now).unwrap(),
+            day(2024, 2, 9)
+        );
+
+        // February in a leap year
+        let now = Local.with_ymd_and_hms(2024, 2, 10, 9, 0, 0).unwrap();
+        assert_eq!(
+            parse_str_to_date_at("end of month", now).unwrap(),
+            day(2024, 2, 29)
+        );
+
+        // On a Sunday the week ends today
+        let now = Local.with_ymd_and_hms(2024, 2, 4, 9, 0, 0).unwrap();
+        assert_eq!(
+            parse_str_to_date_at("end of week", now).unwrap(),
+            day(2024, 2, 4)
+        );
+        assert_eq!(
+            parse_str_to_date_at("next week", now).unwrap(),
+            day(2024, 2, 5)
+        );
+
+        assert!(parse_str_to_date_at("end of days", now).is_err());
+    }
+
     #[test]
     fn test_parse_due_date_arg() {
         let (filter, _date) = parse_due_date_arg("due:today").unwrap();
@@ -214,4 +578,86 @@ mod tests {
         let (filter, _date) = parse_due_date_arg("due:overdue").unwrap();
         assert_eq!(filter, "before");
     }
+
+    #[test]
+    fn test_format_relative_due_in_timezone() {
+        let now = Utc.with_ymd_and_hms(2024, 6, 1, 12, 0, 0).unwrap();
+        let due = Utc.with_ymd_and_hms(2024, 6, 1, 23, 30, 0).unwrap();
+
+        // Still the 1st in UTC, but already the 2nd in Tokyo
+        assert_eq!(format_relative_due(due, now, &Utc), "today");
+        assert_eq!(
+            format_relative_due(due, now, &chrono_tz::Asia::Tokyo),
+            "tomorrow"
+        );
+
+        let later = Utc.with_ymd_and_hms(2024, 8, 20, 3, 0, 0).unwrap();
+        assert_eq!(
+            format_relative_due(later, now, &chrono_tz::America::Los_Angeles),
+            "19 Aug"
+        );
+    }
+
+    #[test]
+    fn test_iso8601_duration_roundtrip() {
+        assert_eq!(
+            parse_iso8601_duration("PT2H30M").unwrap(),
+            Duration::minutes(150)
+        );
+        assert_eq!(parse_iso8601_duration("P2W").unwrap(), Duration::days(14));
+        assert_eq!(
+            parse_iso8601_duration("P1DT1.5H").unwrap(),
+            Duration::minutes(24 * 60 + 90)
+        );
+        assert!(parse_iso8601_duration("P1Y").is_err());
+        assert!(parse_iso8601_duration("2h").is_err());
+        assert!(parse_iso8601_duration("PT").is_err());
+        assert!(matches!(
+            parse_iso8601_duration("P99999999999999999D"),
+            Err(crate::RstaskError::Parse(_))
+        ));
+
+        assert_eq!(format_iso8601_duration(Duration::minutes(150)), "PT2H30M");
+        assert_eq!(format_iso8601_duration(Duration::days(1)), "P1D");
+        assert_eq!(format_iso8601_duration(Duration::zero()), "PT0S");
+        assert_eq!(
+            format_iso8601_duration(Duration::seconds(90061)),
+            "P1DT1H1M1S"
+        );
+    }
+
+    #[test]
+    fn test_duration_shorthand() {
+        assert_eq!(
+            parse_duration_shorthand("30m").unwrap(),
+            Duration::minutes(30)
+        );
+        assert_eq!(parse_duration_shorthand("2h").unwrap(), Duration::hours(2));
+        assert_eq!(
+            parse_duration_shorthand("1.5h").unwrap(),
+            Duration::minutes(90)
+        );
+        assert_eq!(parse_duration_shorthand("1d").unwrap(), Duration::days(1));
+        assert_eq!(
+            parse_duration_shorthand("1h30m").unwrap(),
+            Duration::minutes(90)
+        );
+        assert!(parse_duration_shorthand("2").is_err());
+        assert!(matches!(
+            parse_duration("99999999999999999d"),
+            Err(crate::RstaskError::Parse(_))
+        ));
+        assert!(parse_duration_shorthand("2y").is_err());
+        assert!(parse_duration_shorthand("").is_err());
+
+        assert_eq!(parse_duration("PT2H").unwrap(), Duration::hours(2));
+        assert_eq!(parse_duration("2h").unwrap(), Duration::hours(2));
+
+        assert_eq!(format_duration_shorthand(Duration::minutes(150)), "2h30m");
+        assert_eq!(
+            format_duration_shorthand(Duration::seconds(90061)),
+            "1d1h1m1s"
+        );
+        assert_eq!(format_duration_shorthand(Duration::zero()), "0m");
+    }
 }
@@ -0,0 +1,402 @@
+// Field-level diffs between two versions of a task, for reviewing changes
+use crate::date_util::format_iso8601_duration;
+use crate::task::Task;
+use chrono::{DateTime, Utc};
+use std::collections::BTreeMap;
+
+/// How a single field changed
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Change {
+    /// The value was replaced; empty strings stand for unset values
+    Value { old: String, new: String },
+    /// List fields compared as sets, e.g. tags
+    Set {
+        added: Vec<String>,
+        removed: Vec<String>,
+    },
+}
+
+/// A changed field, named as in the frontmatter
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FieldChange {
+    pub field: String,
+    pub change: Change,
+}
+
+fn date(date: Option<DateTime<Utc>>) -> String {
+    date.map(|d| d.to_rfc3339()).unwrap_or_default()
+}
+
+fn value(changes: &mut Vec<FieldChange>, field: &str, old: String, new: String) {
+    if old != new {
+        changes.push(FieldChange {
+            field: field.to_string(),
+            change: Change::Value { old, new },
+        });
+    }
+}
+
+fn set(changes: &mut Vec<FieldChange>, field: &str, old: &[String], new: &[String]) {
+    let added: Vec<String> = new.iter().filter(|v| !old.contains(v)).cloned().collect();
+    let removed: Vec<String> = old.iter().filter(|v| !new.contains(v)).cloned().collect();
+    if !added.is_empty() || !removed.is_empty() {
+        changes.push(FieldChange {
+            field: field.to_string(),
+            change: Change::Set { added, removed },
+        });
+    }
+}
+
+/// Lists the fields that differ between two versions of a task. Tags,
+/// dependencies, subtasks and attachments are compared as sets, so
+/// reordering them isn't a change. Unknown frontmatter keys are compared one
+/// by one.
+pub(crate) fn diff_tasks(old: &Task, new: &Task) -> Vec<FieldChange> {
+    let mut changes = Vec::new();
+
+    value(
+        &mut changes,
+        "status",
+        old.status.clone(),
+        new.status.clone(),
+    );
+    value(
+        &mut changes,
+        "summary",
+        old.summary.clone(),
+        new.summary.clone(),
+    );
+    set(&mut changes, "tags", &old.tags, &new.tags);
+    value(
+        &mut changes,
+        "project",
+        old.project.clone(),
+        new.project.clone(),
+    );
+    value(
+        &mut changes,
+        "priority",
+        old.priority.clone(),
+        new.priority.clone(),
+    );
+    set(
+        &mut changes,
+        "delegatedto",
+        &old.delegated_to,
+        &new.delegated_to,
+    );
+
+    let waiting_for = |task: &Task| task.waiting_for.clone().unwrap_or_default();
+    value(
+        &mut changes,
+        "waiting_for",
+        waiting_for(old),
+        waiting_for(new),
+    );
+
+    let subtasks = |task: &Task| -> Vec<String> {
+        task.subtasks
+            .iter()
+            .map(|s| format!("[{}] {}", if s.resolved { "x" } else { " " }, s.summary))
+            .collect()
+    };
+    set(&mut changes, "subtasks", &subtasks(old), &subtasks(new));
+    set(
+        &mut changes,
+        "dependencies",
+        &old.dependencies,
+        &new.dependencies,
+    );
+
+    let attachments =
+        |task: &Task| -> Vec<String> { task.attachments.iter().map(|a| a.describe()).collect() };
+    set(
+        &mut changes,
+        "attachments",
+        &attachments(old),
+        &attachments(new),
+    );
+
+    value(
+        &mut changes,
+        "created",
+        old.created.to_rfc3339(),
+        new.created.to_rfc3339(),
+    );
+    value(
+        &mut changes,
+        "resolved",
+        date(old.resolved),
+        date(new.resolved),
+    );
+    value(&mut changes, "due", date(old.due), date(new.due));
+    value(
+        &mut changes,
+        "modified",
+        date(old.modified),
+        date(new.modified),
+    );
+
+    let estimate = |task: &Task| {
+        task.estimate
+            .map(format_iso8601_duration)
+            .unwrap_or_default()
+    };
+    value(&mut changes, "estimate", estimate(old), estimate(new));
+
+    let time_spent = |task: &Task| {
+        task.time_spent
+            .map(format_iso8601_duration)
+            .unwrap_or_default()
+    };
+    value(&mut changes, "time_spent", time_spent(old), time_spent(new));
+
+    let reminders = |task: &Task| -> Vec<String> {
+        task.reminders
+            .iter()
+            .map(|r| format_iso8601_duration(*r))
+            .collect()
+    };
+    set(&mut changes, "reminders", &reminders(old), &reminders(new));
+
+    value(
+        &mut changes,
+        "deleted_at",
+        date(old.deleted_at),
+        date(new.deleted_at),
+    );
+
+    let recur = |task: &Task| {
+        task.recur
+            .map(|r| r.as_str().to_string())
+            .unwrap_or_default()
+    };
+    value(&mut changes, "recur", recur(old), recur(new));
+
+    let extra = |task: &Task, key: &str| {
+        task.extra
+            .get(key)
+            .map(|v| {
+                serde_yaml::to_string(v)
+                    .unwrap_or_default()
+                    .trim()
+                    .to_string()
+            })
+            .unwrap_or_default()
+    };
+    let mut keys: Vec<&String> = old.extra.keys().chain(new.extra.keys()).collect();
+    keys.sort();
+    keys.dedup();
+    for key in keys {
+        value(&mut changes, key, extra(old, key), extra(new, key));
+    }
+
+    let notes_file = |task: &Task| task.notes_file.clone().unwrap_or_default();
+    value(&mut changes, "notes_file", notes_file(old), notes_file(new));
+    value(&mut changes, "notes", old.notes.clone(), new.notes.clone());
+
+    changes
+}
+
+/// What changed between two versions of a whole store, keyed by UUID
+#[derive(Debug, Clone, Default)]
+pub struct StoreDiff<'a> {
+    /// Tasks only in the newer store
+    pub added: BTreeMap<String, &'a Task>,
+    /// Tasks only in the older store
+    pub removed: BTreeMap<String, &'a Task>,
+    /// Tasks in both whose fields differ, with the changes going from the
+    /// older version to the newer one
+    pub modified: BTreeMap<String, Vec<FieldChange>>,
+}
+
+impl StoreDiff<'_> {
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.modified.is_empty()
+    }
+}
+
+/// Compares two versions of a store, e.g. before and after a sync pull
+pub fn store_diff<'a>(before: &'a [Task], after: &'a [Task]) -> StoreDiff<'a> {
+    let by_uuid = |tasks: &'a [Task]| -> BTreeMap<String, &'a Task> {
+        tasks.iter().map(|task| (task.uuid.clone(), task)).collect()
+    };
+    let before = by_uuid(before);
+    let mut diff = StoreDiff {
+        added: by_uuid(after),
+        ..Default::default()
+    };
+
+    for (uuid, old) in before {
+        match diff.added.remove(&uuid) {
+            Some(new) => {
+                let changes = old.diff(new);
+                if !changes.is_empty() {
+                    diff.modified.insert(uuid, changes);
+                }
+            }
+            None => {
+                diff.removed.insert(uuid, old);
+            }
+        }
+    }
+
+    diff
+}
+
+/// Lists `(uuid, old_status, new_status)` for tasks in both versions of a
+/// store whose status changed, in the order of `after`
+pub fn status_transitions(before: &[Task], after: &[Task]) -> Vec<(String, String, String)> {
+    let before: BTreeMap<&str, &str> = before
+        .iter()
+        .map(|task| (task.uuid.as_str(), task.status.as_str()))
+        .collect();
+
+    after
+        .iter()
+        .filter_map(|task| {
+            let old = *before.get(task.uuid.as_str())?;
+            (old != task.status).then(|| (task.uuid.clone(), old.to_string(), task.status.clone()))
+        })
+        .collect()
+}
+
+const RED: &str = "\x1b[31m";
+const GREEN: &str = "\x1b[32m";
+const RESET: &str = "\x1b[0m";
+
+/// Renders changes as `- old` / `+ new` lines under each field name. Set
+/// changes list the removed and added members; multi-line values such as
+/// notes get one line each.
+pub fn render_diff(changes: &[FieldChange]) -> String {
+    render_diff_with(changes, false)
+}
+
+/// Same as [`render_diff`], colouring removals red and additions green
+/// when `color` is set
+pub fn render_diff_with(changes: &[FieldChange], color: bool) -> String {
+    let (red, green, reset) = if color {
+        (RED, GREEN, RESET)
+    } else {
+        ("", "", "")
+    };
+
+    let mut out = String::new();
+    for change in changes {
+        out.push_str(&format!("{}:\n", change.field));
+
+        let (removed, added): (Vec<&str>, Vec<&str>) = match &change.change {
+            Change::Value { old, new } => (old.lines().collect(), new.lines().collect()),
+            Change::Set { added, removed } => (
+                removed.iter().map(String::as_str).collect(),
+                added.iter().map(String::as_str).collect(),
+            ),
+        };
+        for line in removed {
+            out.push_str(&format!("{}- {}{}\n", red, line, reset));
+        }
+        for line in added {
+            out.push_str(&format!("{}+ {}{}\n", green, line, reset));
+        }
+    }
+
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::constants::{STATUS_PENDING, STATUS_RESOLVED};
+
+    #[test]
+    fn test_store_diff() {
+        let kept = Task::new("unchanged".to_string());
+        let edited = Task::new("edited".to_string());
+        let dropped = Task::new("dropped".to_string());
+        let created = Task::new("created".to_string());
+
+        let mut edited_after = edited.clone();
+        edited_after.priority = "P1".to_string();
+
+        let before = vec![kept.clone(), edited.clone(), dropped.clone()];
+        let after = vec![created.clone(), edited_after, kept];
+
+        let diff = store_diff(&before, &after);
+        assert_eq!(diff.added.keys().collect::<Vec<_>>(), vec![&created.uuid]);
+        assert_eq!(diff.removed.keys().collect::<Vec<_>>(), vec![&dropped.uuid]);
+        assert_eq!(diff.modified.len(), 1);
+        assert_eq!(
+            diff.modified[&edited.uuid],
+            vec![FieldChange {
+                field: "priority".to_string(),
+                change: Change::Value {
+                    old: "P2".to_string(),
+                    new: "P1".to_string(),
+                },
+            }]
+        );
+
+        assert!(store_diff(&before, &before).is_empty());
+    }
+
+    #[test]
+    fn test_status_transitions() {
+        let kept = Task::new("unchanged".to_string());
+        let finished = Task::new("finished".to_string());
+        let created = Task::new("created".to_string());
+
+        let mut finished_after = finished.clone();
+        finished_after.status = STATUS_RESOLVED.to_string();
+
+        let before = vec![kept.clone(), finished.clone()];
+        let after = vec![kept, finished_after, created];
+
+        assert_eq!(
+            status_transitions(&before, &after),
+            vec![(
+                finished.uuid.clone(),
+                STATUS_PENDING.to_string(),
+                STATUS_RESOLVED.to_string()
+            )]
+        );
+        assert!(status_transitions(&before, &before).is_empty());
+    }
+
+    #[test]
+    fn test_render_diff() {
+        let old = Task {
+            tags: vec!["work".to_string(), "urgent".to_string()],
+            notes: "first line".to_string(),
+            ..Task::new("Write report".to_string())
+        };
+        let mut new = old.clone();
+        new.summary = "Write the report".to_string();
+        new.tags = vec!["urgent".to_string(), "home".to_string()];
+        new.notes = "first line\nsecond line".to_string();
+
+        let changes = old.diff(&new);
+        let fields: Vec<&str> = changes.iter().map(|c| c.field.as_str()).collect();
+        assert_eq!(fields, vec!["summary", "tags", "notes"]);
+
+        let rendered = render_diff(&changes);
+        assert_eq!(
+            rendered,
+            "summary:\n\
+             - Write report\n\
+             + Write the report\n\
+             tags:\n\
+             - work\n\
+             + home\n\
+             notes:\n\
+             - first line\n\
+             + first line\n\
+             + second line\n"
+        );
+
+        let colored = render_diff_with(&changes, true);
+        assert!(colored.contains("\x1b[31m- work\x1b[0m"));
+        assert!(colored.contains("\x1b[32m+ home\x1b[0m"));
+
+        assert!(old.diff(&old.clone()).is_empty());
+    }
+}
@@ -0,0 +1,173 @@
+// Digest - summary of recent and upcoming activity, e.g. for a standup
+use crate::constants::*;
+use crate::task::Task;
+use chrono::{DateTime, Duration, Utc};
+
+/// Tasks grouped by what happened to them around a point in time
+#[derive(Debug, Clone)]
+pub struct Digest<'a> {
+    pub start: DateTime<Utc>,
+    pub end: DateTime<Utc>,
+    /// Tasks resolved within the window
+    pub completed: Vec<&'a Task>,
+    /// Tasks created within the window
+    pub created: Vec<&'a Task>,
+    /// Open tasks due between now and the end of the next window
+    pub due: Vec<&'a Task>,
+    /// Open tasks whose due date has passed
+    pub overdue: Vec<&'a Task>,
+}
+
+/// Builds a digest of the `window` leading up to `now`. Upcoming due dates are
+/// looked up over the same length of time after `now`.
+pub fn generate_digest(tasks: &[Task], now: DateTime<Utc>, window: Duration) -> Digest<'_> {
+    let start = now - window;
+    let horizon = now + window;
+
+    let mut digest = Digest {
+        start,
+        end: now,
+        completed: Vec::new(),
+        created: Vec::new(),
+        due: Vec::new(),
+        overdue: Vec::new(),
+    };
+
+    for task in tasks {
+        if task.deleted {
+            continue;
+        }
+
+        if task.status == STATUS_RESOLVED {
+            if let Some(resolved) = task.resolved
+                && resolved >= start
+                && resolved <= now
+            {
+                digest.completed.push(task);
+            }
+            continue;
+        }
+
+        // Templates and recurring tasks aren't real work items
+        if HIDDEN_STATUSES.contains(&task.status.as_str()) {
+            continue;
+        }
+
+        if task.created >= start && task.created <= now {
+            digest.created.push(task);
+        }
+
+        if let Some(due) = task.due {
+            if due < now {
+                digest.overdue.push(task);
+            } else if due <= horizon {
+                digest.due.push(task);
+            }
+        }
+    }
+
+    digest.completed.sort_by_key(|t| t.resolved);
+    digest.created.sort_by_key(|t| t.created);
+    digest.due.sort_by_key(|t| t.due);
+    digest.overdue.sort_by_key(|t| t.due);
+
+    digest
+}
+
+impl Digest<'_> {
+    /// Renders the digest as markdown, omitting empty sections
+    pub fn to_markdown(&self) -> String {
+        let mut out = format!(
+            "# Digest {} to {}\n",
+            self.start.format("%Y-%m-%d"),
+            self.end.format("%Y-%m-%d")
+        );
+
+        let sections = [
+            ("Completed", &self.completed),
+            ("Created", &self.created),
+            ("Overdue", &self.overdue),
+            ("Due soon", &self.due),
+        ];
+
+        for (title, tasks) in sections {
+            if tasks.is_empty() {
+                continue;
+            }
+
+            out.push_str(&format!("\n## {} ({})\n\n", title, tasks.len()));
+            for task in tasks {
+                out.push_str(&format!("- {}", task));
+                if !task.project.is_empty() {
+                    out.push_str(&format!(" (project:{})", task.project));
+                }
+                if let Some(due) = task.due
+                    && task.status != STATUS_RESOLVED
+                {
+                    out.push_str(&format!(" due {}", due.format("%Y-%m-%d")));
+                }
+                out.push('\n');
+            }
+        }
+
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn now() -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2024, 6, 10, 12, 0, 0).unwrap()
+    }
+
+    #[test]
+    fn test_digest_buckets() {
+        let mut done = Task::new("done yesterday".to_string());
+        done.status = STATUS_RESOLVED.to_string();
+        done.created = now() - Duration::days(30);
+        done.resolved = Some(now() - Duration::days(1));
+
+        let mut old_done = Task::new("done long ago".to_string());
+        old_done.status = STATUS_RESOLVED.to_string();
+        old_done.created = now() - Duration::days(60);
+        old_done.resolved = Some(now() - Duration::days(20));
+
+        let mut new_task = Task::new("created recently".to_string());
+        new_task.created = now() - Duration::days(2);
+        new_task.due = Some(now() + Duration::days(3));
+
+        let mut late = Task::new("late".to_string());
+        late.created = now() - Duration::days(40);
+        late.due = Some(now() - Duration::days(5));
+
+        let tasks = vec![done, old_done, new_task, late];
+        let digest = generate_digest(&tasks, now(), Duration::days(7));
+
+        assert_eq!(digest.completed.len(), 1);
+        assert_eq!(digest.completed[0].summary, "done yesterday");
+        assert_eq!(digest.created.len(), 1);
+        assert_eq!(digest.created[0].summary, "created recently");
+        assert_eq!(digest.due.len(), 1);
+        assert_eq!(digest.due[0].summary, "created recently");
+        assert_eq!(digest.overdue.len(), 1);
+        assert_eq!(digest.overdue[0].summary, "late");
+    }
+
+    #[test]
+    fn test_digest_to_markdown() {
+        let mut done = Task::new("done yesterday".to_string());
+        done.status = STATUS_RESOLVED.to_string();
+        done.resolved = Some(now() - Duration::days(1));
+        done.project = "work".to_string();
+
+        let tasks = vec![done];
+        let md = generate_digest(&tasks, now(), Duration::days(7)).to_markdown();
+
+        assert!(md.starts_with("# Digest 2024-06-03 to 2024-06-10\n"));
+        assert!(md.contains("## Completed (1)\n\n- done yesterday (project:work)\n"));
+        assert!(!md.contains("## Overdue"));
+    }
+}
@@ -1,11 +1,41 @@
 use crate::Result;
 use crate::constants::*;
+use crate::date_util::format_relative_due;
 use crate::query::Query;
 use crate::table::{RowStyle, Table};
 use crate::task::Task;
 use crate::taskset::TaskSet;
 use crate::util::{get_term_size, stdout_is_tty};
-use chrono::{Datelike, Utc};
+use chrono::{DateTime, Datelike, Local, Utc};
+use chrono_tz::Tz;
+use serde::Serialize;
+
+const DISPLAY_DATE_FORMAT: &str = "%a %-d %b %Y, %H:%M";
+
+/// A task prepared for templates, with dates already formatted and derived
+/// values such as urgency worked out
+#[derive(Debug, Clone, Serialize)]
+pub struct TaskDisplay {
+    pub id: i32,
+    pub uuid: String,
+    pub status: String,
+    pub summary: String,
+    pub notes: String,
+    pub project: String,
+    pub priority: String,
+    pub tags: Vec<String>,
+    pub created: String,
+    pub resolved: Option<String>,
+    pub due: Option<String>,
+    /// Due date relative to now, e.g. "tomorrow"
+    pub due_relative: Option<String>,
+    pub overdue: bool,
+    pub subtasks_done: usize,
+    pub subtasks_total: usize,
+    /// Resolved subtasks out of all, e.g. "2/3", None without subtasks
+    pub subtask_progress: Option<String>,
+    pub urgency: f64,
+}
 
 impl Task {
     /// Returns the row style for this task
@@ -98,9 +128,80 @@ impl Task {
 
         table.render();
     }
+
+    /// Scores how pressing the task is at `now`, higher being more urgent.
+    /// Priority contributes up to 6, a due date up to 12 (reached when it is
+    /// due or overdue, falling to nothing two weeks out) and being active 4.
+    /// Resolved tasks score 0.
+    pub fn urgency(&self, now: DateTime<Utc>) -> f64 {
+        if self.status == STATUS_RESOLVED {
+            return 0.0;
+        }
+
+        let priority = match self.priority.as_str() {
+            PRIORITY_CRITICAL => 6.0,
+            PRIORITY_HIGH => 3.9,
+            PRIORITY_NORMAL => 1.8,
+            _ => 0.0,
+        };
+        let due = self.due.map_or(0.0, |due| {
+            let days_left = (due - now).num_seconds() as f64 / 86_400.0;
+            12.0 * (1.0 - days_left / 14.0).clamp(0.0, 1.0)
+        });
+        let active = if self.status == STATUS_ACTIVE {
+            4.0
+        } else {
+            0.0
+        };
+
+        priority + due + active
+    }
+
+    /// Prepares the task for display at `now`, with dates shown in `tz`
+    pub fn to_display(&self, now: DateTime<Utc>, tz: Tz) -> TaskDisplay {
+        let format = |date: DateTime<Utc>| {
+            date.with_timezone(&tz)
+                .format(DISPLAY_DATE_FORMAT)
+                .to_string()
+        };
+        let subtasks_done = self.subtasks.iter().filter(|s| s.resolved).count();
+        let subtasks_total = self.subtasks.len();
+
+        TaskDisplay {
+            id: self.id,
+            uuid: self.uuid.clone(),
+            status: self.status.clone(),
+            summary: self.summary.clone(),
+            notes: self.notes.clone(),
+            project: self.project.clone(),
+            priority: self.priority.clone(),
+            tags: self.tags.clone(),
+            created: format(self.created),
+            resolved: self.resolved.map(format),
+            due: self.due.map(format),
+            due_relative: self.due.map(|due| format_relative_due(due, now, &tz)),
+            overdue: self.status != STATUS_RESOLVED && self.due.is_some_and(|due| due < now),
+            subtasks_done,
+            subtasks_total,
+            subtask_progress: (subtasks_total > 0)
+                .then(|| format!("{}/{}", subtasks_done, subtasks_total)),
+            urgency: self.urgency(now),
+        }
+    }
 }
 
 impl TaskSet {
+    /// Formats a task's due date in the configured timezone, falling back to the
+    /// system's local time when no timezone is configured
+    fn due_date_str(&self, task: &Task) -> String {
+        match task.due {
+            Some(due) if self.preferences().timezone.is_some() => {
+                format_relative_due(due, Utc::now(), &self.preferences().tz())
+            }
+            _ => task.parse_due_date_to_str(),
+        }
+    }
+
     /// Displays tasks in "next" view (by priority and creation date)
     pub fn display_by_next(&mut self, ctx: &Query, truncate: bool) -> Result<()> {
         self.sort_by_created_ascending();
@@ -200,7 +301,7 @@ impl TaskSet {
                     format!("{:<2}", task.id),
                     task.priority.clone(),
                     task.tags.join(" "),
-                    task.parse_due_date_to_str(),
+                    self.due_date_str(task),
                     task.project.clone(),
                     task.long_summary(),
                 ],
@@ -266,7 +367,7 @@ impl TaskSet {
                                 resolved.format("%a %-d").to_string(),
                                 task.priority.clone(),
                                 task.tags.join(" "),
-                                task.parse_due_date_to_str(),
+                                self.due_date_str(task),
                                 task.project.clone(),
                                 task.long_summary(),
                             ],
@@ -334,3 +435,95 @@ impl TaskSet {
         Ok(())
     }
 }
+
+/// Formats a task as a single line for compact lists, e.g.
+/// `3  P1  [web] Fix login bug  (due tomorrow) +urgent`. The ID, priority,
+/// project, due date and tags are each left out when unset.
+pub fn format_task_line(task: &Task, now: DateTime<Utc>) -> String {
+    let mut columns = Vec::new();
+    if task.id > 0 {
+        columns.push(task.id.to_string());
+    }
+    if !task.priority.is_empty() {
+        columns.push(task.priority.clone());
+    }
+    if task.project.is_empty() {
+        columns.push(task.summary.clone());
+    } else {
+        columns.push(format!("[{}] {}", task.project, task.summary));
+    }
+    if let Some(due) = task.due {
+        columns.push(format!("(due {})", format_relative_due(due, now, &Local)));
+    }
+
+    let mut line = columns.join("  ");
+    for tag in &task.tags {
+        line.push_str(&format!(" +{}", tag));
+    }
+    line
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::task::SubTask;
+    use chrono::{Duration, TimeZone};
+
+    #[test]
+    fn test_to_display() {
+        let now = Utc.with_ymd_and_hms(2024, 6, 5, 12, 0, 0).unwrap();
+
+        let mut task = Task::new("Ship release".to_string());
+        task.created = Utc.with_ymd_and_hms(2024, 6, 1, 9, 30, 0).unwrap();
+        task.priority = PRIORITY_HIGH.to_string();
+        task.status = STATUS_ACTIVE.to_string();
+        task.due = Some(now - Duration::days(1));
+        task.subtasks = vec![
+            SubTask {
+                summary: "tag".to_string(),
+                resolved: true,
+            },
+            SubTask {
+                summary: "announce".to_string(),
+                resolved: false,
+            },
+        ];
+
+        let display = task.to_display(now, Tz::Asia__Tokyo);
+        assert_eq!(display.created, "Sat 1 Jun 2024, 18:30");
+        assert_eq!(display.due.as_deref(), Some("Tue 4 Jun 2024, 21:00"));
+        assert_eq!(display.due_relative.as_deref(), Some("yesterday"));
+        assert!(display.overdue);
+        assert_eq!(display.resolved, None);
+        assert_eq!((display.subtasks_done, display.subtasks_total), (1, 2));
+        assert_eq!(display.subtask_progress.as_deref(), Some("1/2"));
+        // High priority, overdue and active
+        assert!((display.urgency - 19.9).abs() < 1e-9);
+
+        let plain = Task::new("Someday".to_string()).to_display(now, Tz::UTC);
+        assert_eq!(plain.due_relative, None);
+        assert!(!plain.overdue);
+        assert_eq!(plain.subtask_progress, None);
+        assert!((plain.urgency - 1.8).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_format_task_line() {
+        let now = Utc.with_ymd_and_hms(2024, 6, 5, 12, 0, 0).unwrap();
+
+        let mut task = Task::new("Fix login bug".to_string());
+        task.id = 3;
+        task.priority = PRIORITY_HIGH.to_string();
+        task.project = "web".to_string();
+        task.due = Some(now + Duration::days(1));
+        task.tags = vec!["urgent".to_string(), "backend".to_string()];
+        assert_eq!(
+            format_task_line(&task, now),
+            "3  P1  [web] Fix login bug  (due tomorrow) +urgent +backend"
+        );
+
+        let mut minimal = Task::new("Just a thought".to_string());
+        minimal.priority = String::new();
+        assert_eq!(format_task_line(&minimal, now), "Just a thought");
+    }
+}
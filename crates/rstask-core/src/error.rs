@@ -32,9 +32,24 @@ pub enum RstaskError {
     #[error("Invalid status transition from {0} to {1}")]
     InvalidStatusTransition(String, String),
 
+    #[error("Duplicate task ID {0} shared by {1}")]
+    DuplicateId(i32, String),
+
+    #[error("UUID prefix {0} is ambiguous, it matches {1}")]
+    AmbiguousUuidPrefix(String, String),
+
+    #[error("Dependency cycle: {0}")]
+    DependencyCycle(String),
+
+    #[error("Note encryption error: {0}")]
+    Encryption(String),
+
     #[error("Task not found: {0}")]
     TaskNotFound(String),
 
+    #[error("Task store {0} is locked by another process")]
+    StoreLocked(String),
+
     #[error("Repository not found: {0}")]
     RepoNotFound(String),
 
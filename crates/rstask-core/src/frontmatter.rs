@@ -1,19 +1,63 @@
 use crate::Result;
 use crate::RstaskError;
+use crate::crypto::{NOTES_PASSPHRASE_ENV, encrypt_note, is_encrypted_note};
+use crate::date_util::{format_duration_shorthand, format_iso8601_duration};
+use crate::preferences::{DEFAULT_FRONTMATTER_DELIMITER, EstimateFormat, Preferences};
 use crate::task::Task;
 use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::fmt;
+use std::path::{Component, Path, PathBuf};
 
 /// Serialize a task to markdown with YAML frontmatter
 /// The notes field becomes the markdown content, everything else goes in frontmatter
 pub fn task_to_markdown(task: &Task) -> Result<String> {
-    // Create a copy without notes for frontmatter
-    let frontmatter_task = TaskFrontmatter {
+    task_to_markdown_with(task, &Preferences::default())
+}
+
+/// Serialize a task to markdown, honouring the given preferences.
+/// The task itself is never modified, e.g. tags are only sorted in the output.
+pub fn task_to_markdown_with(task: &Task, preferences: &Preferences) -> Result<String> {
+    // Notes kept in a file of their own are written there when saving
+    let notes = if task.notes_file.is_some() {
+        String::new()
+    } else {
+        notes_to_write(task, preferences)?
+    };
+
+    render_markdown(
+        task,
+        &to_frontmatter(task, preferences),
+        &notes,
+        preferences,
+    )
+}
+
+/// Serializes a task for editing by hand: as [`task_to_markdown_with`], but
+/// with the notes in the body as plain text, even when they are kept in a
+/// notes file or encrypted on disk. Parsing the edited buffer keeps
+/// `notes_file`, so the notes are written back where they came from.
+pub fn task_to_edit_markdown(task: &Task, preferences: &Preferences) -> Result<String> {
+    render_markdown(
+        task,
+        &to_frontmatter(task, preferences),
+        &task.notes,
+        preferences,
+    )
+}
+
+/// The frontmatter written for a task
+fn to_frontmatter(task: &Task, preferences: &Preferences) -> TaskFrontmatter {
+    let mut tags = task.tags.clone();
+    let mut dependencies = task.dependencies.clone();
+    if preferences.sort_tags_on_write {
+        tags.sort();
+        dependencies.sort();
+    }
+
+    TaskFrontmatter {
         summary: task.summary.clone(),
-        tags: if task.tags.is_empty() {
-            None
-        } else {
-            Some(task.tags.clone())
-        },
+        tags: if tags.is_empty() { None } else { Some(tags) },
         project: if task.project.is_empty() {
             None
         } else {
@@ -29,53 +73,142 @@ pub fn task_to_markdown(task: &Task) -> Result<String> {
         } else {
             Some(task.delegated_to.clone())
         },
+        waiting_for: task.waiting_for.clone(),
         subtasks: if task.subtasks.is_empty() {
             None
         } else {
             Some(task.subtasks.clone())
         },
-        dependencies: if task.dependencies.is_empty() {
+        dependencies: if dependencies.is_empty() {
             None
         } else {
-            Some(task.dependencies.clone())
+            Some(dependencies)
         },
+        attachments: task.attachments.clone(),
         created: task.created,
         resolved: task.resolved,
         due: task.due,
-    };
+        modified: task.modified,
+        estimate: task.estimate.map(|duration| Estimate {
+            duration,
+            format: preferences.estimate_format,
+        }),
+        time_spent: task.time_spent,
+        reminders: task.reminders.clone(),
+        deleted_at: task.deleted_at,
+        recur: task.recur,
+        notes_file: task.notes_file.clone(),
+        extra: task.extra.clone(),
+    }
+}
 
-    let yaml_frontmatter = serde_yaml::to_string(&frontmatter_task).map_err(RstaskError::Yaml)?;
+fn render_markdown(
+    task: &Task,
+    frontmatter_task: &TaskFrontmatter,
+    notes: &str,
+    preferences: &Preferences,
+) -> Result<String> {
+    let yaml_frontmatter = serde_yaml::to_string(frontmatter_task).map_err(RstaskError::Yaml)?;
 
-    let mut result = String::from("---\n");
+    let delimiter = preferences.frontmatter_delimiter();
+    let mut result = format!("{}\n", delimiter);
     result.push_str(&yaml_frontmatter);
-    result.push_str("---\n");
+    result.push_str(&format!("{}\n", delimiter));
 
-    if !task.notes.is_empty() {
+    if !notes.is_empty() {
         result.push('\n');
-        result.push_str(&task.notes);
-        if !task.notes.ends_with('\n') {
+        result.push_str(notes);
+        if !notes.ends_with('\n') {
             result.push('\n');
         }
     }
 
+    if preferences.emit_footer {
+        result.push('\n');
+        result.push_str(&format!(
+            "{}uuid={}{}\n",
+            FOOTER_PREFIX, task.uuid, FOOTER_SUFFIX
+        ));
+    }
+
     Ok(result)
 }
 
-/// Deserialize a task from markdown with YAML frontmatter
-pub fn task_from_markdown(content: &str, uuid: &str, status: &str, id: i32) -> Result<Task> {
+/// The notes as written to disk, encrypted if `encrypt_notes` is set
+pub(crate) fn notes_to_write(task: &Task, preferences: &Preferences) -> Result<String> {
+    if preferences.encrypt_notes && !task.notes.is_empty() && !is_encrypted_note(&task.notes) {
+        let key = preferences.notes_key.as_ref().ok_or_else(|| {
+            RstaskError::Encryption(format!(
+                "encrypt_notes is enabled but {} is not set",
+                NOTES_PASSPHRASE_ENV
+            ))
+        })?;
+        encrypt_note(&task.notes, key)
+    } else {
+        Ok(task.notes.clone())
+    }
+}
+
+/// Resolves a `notes_file` reference against `base_path`. Only relative
+/// paths that stay inside it are accepted, as the file is written on save.
+pub(crate) fn notes_file_path(base_path: &Path, notes_file: &str) -> Result<PathBuf> {
+    let relative = Path::new(notes_file);
+    let inside = relative
+        .components()
+        .all(|component| matches!(component, Component::Normal(_)));
+    if notes_file.is_empty() || !inside {
+        return Err(RstaskError::Parse(format!(
+            "notes_file must be a relative path inside the repository: {}",
+            notes_file
+        )));
+    }
+    Ok(base_path.join(relative))
+}
+
+const FOOTER_PREFIX: &str = "<!-- rstask: ";
+const FOOTER_SUFFIX: &str = " -->";
+
+/// Removes a trailing metadata footer, as written with `emit_footer`, from
+/// notes. Only a last line matching the footer exactly is removed, so other
+/// comments in the notes are left alone.
+fn strip_footer(notes: &str) -> &str {
+    let trimmed = notes.trim_end();
+    let (rest, last_line) = match trimmed.rsplit_once('\n') {
+        Some((rest, last_line)) => (rest, last_line),
+        None => ("", trimmed),
+    };
+
+    let is_footer = last_line
+        .strip_prefix(FOOTER_PREFIX)
+        .and_then(|footer| footer.strip_suffix(FOOTER_SUFFIX))
+        .and_then(|footer| footer.strip_prefix("uuid="))
+        .is_some_and(|uuid| uuid::Uuid::try_parse(uuid).is_ok());
+    if is_footer {
+        rest.trim_end_matches('\n')
+    } else {
+        notes
+    }
+}
+
+/// Splits markdown content into its raw frontmatter and notes. The
+/// frontmatter opens with one of the `delimiters` and closes with the same one.
+fn split_frontmatter(content: &str, delimiters: &[&str]) -> Result<(String, String)> {
     // Find the frontmatter boundaries
     let lines: Vec<&str> = content.lines().collect();
 
-    if lines.is_empty() || lines[0] != "---" {
+    let Some(&delimiter) = lines
+        .first()
+        .and_then(|first| delimiters.iter().find(|d| *d == first))
+    else {
         return Err(RstaskError::Parse(
             "missing frontmatter delimiter".to_string(),
         ));
-    }
+    };
 
-    // Find the closing ---
+    // Find the closing delimiter
     let closing_idx = lines[1..]
         .iter()
-        .position(|&line| line == "---")
+        .position(|&line| line == delimiter)
         .ok_or_else(|| RstaskError::Parse("missing closing frontmatter delimiter".to_string()))?;
 
     // Extract frontmatter (lines between the two ---)
@@ -93,9 +226,202 @@ pub fn task_from_markdown(content: &str, uuid: &str, status: &str, id: i32) -> R
         String::new()
     };
 
-    // Deserialize frontmatter
-    let frontmatter: TaskFrontmatter =
-        serde_yaml::from_str(&frontmatter_str).map_err(RstaskError::Yaml)?;
+    Ok((frontmatter_str, strip_footer(&notes).to_string()))
+}
+
+/// Finds the first anchor (`&name`) or alias (`*name`) in a YAML document,
+/// returning it with its 1-based line number. Only node positions are
+/// checked, so text like "Tom & Jerry" or "*important*" inside a value
+/// isn't mistaken for one; quoted strings, comments, block scalars and the
+/// continuation lines of plain scalars are skipped. A tag (`!!str &a`)
+/// leaves the node position open, as do the `-`, `?` and `:` indicators.
+fn find_yaml_anchor(yaml: &str) -> Option<(usize, String)> {
+    let mut block_indent: Option<usize> = None;
+    // Column of the key or `-` owning a plain scalar that may continue on
+    // lines indented past it
+    let mut plain_owner: Option<usize> = None;
+    // Column of the key or `-` of a line ending before its value, which
+    // then starts on the next line
+    let mut value_owner: Option<usize> = None;
+
+    for (idx, line) in yaml.lines().enumerate() {
+        let indent = line.len() - line.trim_start().len();
+        if let Some(block) = block_indent {
+            if line.trim().is_empty() || indent > block {
+                continue;
+            }
+            block_indent = None;
+        }
+        if let Some(owner) = plain_owner {
+            if line.trim().is_empty() || indent > owner {
+                continue;
+            }
+            plain_owner = None;
+        }
+        if line.trim().is_empty() || line.trim_start().starts_with('#') {
+            continue;
+        }
+
+        let chars: Vec<char> = line.chars().collect();
+        let mut at_node = true;
+        let mut flow_depth = 0;
+        let mut i = 0;
+        let mut last_token_start = 0;
+        let mut owner = match value_owner.take() {
+            Some(value_owner) if indent > value_owner => value_owner,
+            _ => indent,
+        };
+        let mut key_start = indent;
+        let mut plain = false;
+
+        while i < chars.len() {
+            let c = chars[i];
+            if c == ' ' || c == '\t' {
+                i += 1;
+                continue;
+            }
+            if c == '#' && (i == 0 || chars[i - 1] == ' ' || chars[i - 1] == '\t') {
+                break;
+            }
+
+            if at_node {
+                last_token_start = i;
+                match c {
+                    '&' | '*' if chars.get(i + 1).is_some_and(|n| !n.is_whitespace()) => {
+                        let token: String = chars[i..]
+                            .iter()
+                            .take_while(|c| !c.is_whitespace() && !",]}".contains(**c))
+                            .collect();
+                        return Some((idx + 1, token));
+                    }
+                    '-' | '?' if chars.get(i + 1).is_none_or(|n| *n == ' ') => {
+                        owner = i;
+                        i += 1;
+                        continue;
+                    }
+                    '!' => {
+                        while i < chars.len() && !chars[i].is_whitespace() {
+                            i += 1;
+                        }
+                        continue;
+                    }
+                    '[' | '{' => {
+                        flow_depth += 1;
+                        i += 1;
+                        continue;
+                    }
+                    '"' | '\'' => {
+                        // Skip to the closing quote; '' and \" are escapes
+                        i += 1;
+                        while i < chars.len() {
+                            if c == '"' && chars[i] == '\\' {
+                                i += 2;
+                                continue;
+                            }
+                            if chars[i] == c {
+                                if c == '\'' && chars.get(i + 1) == Some(&'\'') {
+                                    i += 2;
+                                    continue;
+                                }
+                                break;
+                            }
+                            i += 1;
+                        }
+                        key_start = last_token_start;
+                        at_node = false;
+                        plain = false;
+                        i += 1;
+                        continue;
+                    }
+                    _ => {
+                        key_start = i;
+                        at_node = false;
+                        plain = true;
+                    }
+                }
+            }
+
+            match c {
+                ':' if chars.get(i + 1).is_none_or(|n| n.is_whitespace()) => {
+                    owner = key_start;
+                    at_node = true;
+                }
+                ',' if flow_depth > 0 => at_node = true,
+                ']' | '}' if flow_depth > 0 => flow_depth -= 1,
+                _ => {}
+            }
+            i += 1;
+        }
+
+        // A value of | or > starts a block scalar on the following lines
+        let last: String = chars[last_token_start..]
+            .iter()
+            .take_while(|c| !c.is_whitespace())
+            .collect();
+        if last.starts_with(['|', '>'])
+            && last[1..]
+                .chars()
+                .all(|c| c.is_ascii_digit() || c == '-' || c == '+')
+        {
+            block_indent = Some(indent);
+        } else if plain && !at_node && flow_depth == 0 {
+            plain_owner = Some(owner);
+        } else if at_node && flow_depth == 0 {
+            value_owner = Some(owner);
+        }
+    }
+
+    None
+}
+
+/// Rejects YAML using anchors or aliases unless `allow_yaml_anchors` is set,
+/// as aliases can expand a small file into a very large document
+pub(crate) fn check_yaml_anchors(yaml: &str, preferences: &Preferences) -> Result<()> {
+    if preferences.allow_yaml_anchors {
+        return Ok(());
+    }
+
+    match find_yaml_anchor(yaml) {
+        Some((line, token)) => Err(RstaskError::Parse(format!(
+            "YAML anchors and aliases are not supported in task files (found {} on line {}); \
+             set allow_yaml_anchors to accept them",
+            token, line
+        ))),
+        None => Ok(()),
+    }
+}
+
+/// Returns only the notes of a task file, without deserializing the frontmatter
+pub fn notes_only(content: &str) -> Result<String> {
+    let (_, notes) = split_frontmatter(content, &[DEFAULT_FRONTMATTER_DELIMITER])?;
+    Ok(notes)
+}
+
+/// Deserialize a task from markdown with YAML frontmatter
+pub fn task_from_markdown(content: &str, uuid: &str, status: &str, id: i32) -> Result<Task> {
+    task_from_markdown_with(content, uuid, status, id, &Preferences::default())
+}
+
+/// Delimiters accepted when reading: the configured one and the default
+fn frontmatter_delimiters(preferences: &Preferences) -> [&str; 2] {
+    [
+        preferences.frontmatter_delimiter(),
+        DEFAULT_FRONTMATTER_DELIMITER,
+    ]
+}
+
+/// Deserialize a task from markdown, honouring the given preferences. Files
+/// using the default `---` delimiter are read whatever the configured one is.
+pub fn task_from_markdown_with(
+    content: &str,
+    uuid: &str,
+    status: &str,
+    id: i32,
+    preferences: &Preferences,
+) -> Result<Task> {
+    let (frontmatter_str, notes) =
+        split_frontmatter(content, &frontmatter_delimiters(preferences))?;
+    let frontmatter = parse_frontmatter(&frontmatter_str, preferences)?;
 
     // Construct the task
     let task = Task {
@@ -110,23 +436,237 @@ pub fn task_from_markdown(content: &str, uuid: &str, status: &str, id: i32) -> R
         project: frontmatter.project.unwrap_or_default(),
         priority: frontmatter.priority.unwrap_or_default(),
         delegated_to: frontmatter.delegatedto.unwrap_or_default(),
+        waiting_for: frontmatter.waiting_for,
         subtasks: frontmatter.subtasks.unwrap_or_default(),
         dependencies: frontmatter.dependencies.unwrap_or_default(),
+        attachments: frontmatter.attachments,
         created: frontmatter.created,
         resolved: frontmatter.resolved,
         due: frontmatter.due,
+        modified: frontmatter.modified,
+        estimate: frontmatter.estimate.map(|estimate| estimate.duration),
+        time_spent: frontmatter.time_spent,
+        reminders: frontmatter.reminders,
+        deleted_at: frontmatter.deleted_at,
+        recur: frontmatter.recur,
+        notes_file: frontmatter.notes_file,
+        notes_loaded: false,
+        extra: frontmatter.extra,
         filtered: false,
     };
 
     Ok(task)
 }
 
+/// Deserializes the frontmatter, which is YAML or, when it opens with `{`,
+/// JSON with the same keys. Frontmatter over `max_frontmatter_bytes` is
+/// rejected before either parser sees it. With `strict_frontmatter` set,
+/// keys that are neither known nor declared in `user_fields` are rejected
+/// instead of being kept.
+fn parse_frontmatter(frontmatter_str: &str, preferences: &Preferences) -> Result<TaskFrontmatter> {
+    check_frontmatter_size(frontmatter_str, preferences)?;
+
+    // A YAML flow mapping also opens with `{`, so JSON that doesn't parse is
+    // read as YAML
+    let json = frontmatter_str
+        .trim_start()
+        .starts_with('{')
+        .then(|| serde_json::from_str::<TaskFrontmatter>(frontmatter_str).ok())
+        .flatten();
+    let frontmatter = match json {
+        Some(frontmatter) => frontmatter,
+        None => {
+            check_yaml_anchors(frontmatter_str, preferences)?;
+            serde_yaml::from_str(frontmatter_str)?
+        }
+    };
+
+    check_unknown_keys(frontmatter.extra.keys(), preferences)?;
+    Ok(frontmatter)
+}
+
+/// Rejects frontmatter over `max_frontmatter_bytes`
+pub(crate) fn check_frontmatter_size(
+    frontmatter_str: &str,
+    preferences: &Preferences,
+) -> Result<()> {
+    if frontmatter_str.len() > preferences.max_frontmatter_bytes {
+        return Err(RstaskError::Parse(format!(
+            "frontmatter is {} bytes, over the limit of {}",
+            frontmatter_str.len(),
+            preferences.max_frontmatter_bytes
+        )));
+    }
+    Ok(())
+}
+
+/// With `strict_frontmatter` set, rejects unknown keys not declared in
+/// `user_fields`
+pub(crate) fn check_unknown_keys<'a>(
+    keys: impl IntoIterator<Item = &'a String>,
+    preferences: &Preferences,
+) -> Result<()> {
+    if !preferences.strict_frontmatter {
+        return Ok(());
+    }
+
+    match keys
+        .into_iter()
+        .find(|key| !preferences.user_fields.contains_key(*key))
+    {
+        Some(key) => Err(RstaskError::Parse(format!(
+            "unexpected frontmatter key '{}'",
+            key
+        ))),
+        None => Ok(()),
+    }
+}
+
+/// Same as [`task_from_markdown_with`], loading the notes from the file
+/// named by `notes_file`, relative to `base_path`, when there is one. The
+/// body is ignored for such tasks.
+pub fn task_from_markdown_at(
+    content: &str,
+    uuid: &str,
+    status: &str,
+    id: i32,
+    preferences: &Preferences,
+    base_path: &Path,
+) -> Result<Task> {
+    let mut task = task_from_markdown_with(content, uuid, status, id, preferences)?;
+    if let Some(notes_file) = &task.notes_file {
+        task.notes = std::fs::read_to_string(notes_file_path(base_path, notes_file)?)?;
+        task.notes_loaded = true;
+    }
+    Ok(task)
+}
+
+/// Frontmatter keys that are still read but written under another name,
+/// paired with the key that replaces them. Each needs a matching serde
+/// alias on `TaskFrontmatter` and `Task`, which
+/// `test_deprecated_keys_are_aliases` checks.
+pub const DEPRECATED_KEYS: &[(&str, &str)] = &[("desc", "summary"), ("title", "summary")];
+
+/// Something the parser cleaned up while reading a task file
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ParseWarning {
+    /// A tag appeared more than once
+    DuplicateTag(String),
+    /// A key from [`DEPRECATED_KEYS`] was used
+    DeprecatedKey { key: String, replacement: String },
+}
+
+impl fmt::Display for ParseWarning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ParseWarning::DuplicateTag(tag) => write!(f, "collapsed duplicate tag '{}'", tag),
+            ParseWarning::DeprecatedKey { key, replacement } => write!(
+                f,
+                "key '{}' is deprecated and will be saved as '{}'",
+                key, replacement
+            ),
+        }
+    }
+}
+
+/// Same as [`task_from_markdown_with`], also reporting what the parser had
+/// to clean up: deprecated keys, which are still read, and duplicate tags,
+/// which are collapsed. Tags differing only in case count as duplicates, as
+/// they would after normalisation; the first spelling is kept.
+pub fn task_from_markdown_verbose(
+    content: &str,
+    uuid: &str,
+    status: &str,
+    id: i32,
+    preferences: &Preferences,
+) -> Result<(Task, Vec<ParseWarning>)> {
+    let mut task = task_from_markdown_with(content, uuid, status, id, preferences)?;
+    let mut warnings = Vec::new();
+
+    // Parsing succeeded, so the frontmatter is a valid mapping
+    let (frontmatter_str, _) = split_frontmatter(content, &frontmatter_delimiters(preferences))?;
+    let keys: serde_yaml::Mapping = serde_yaml::from_str(&frontmatter_str)?;
+    for (key, replacement) in DEPRECATED_KEYS {
+        if keys.contains_key(*key) {
+            warnings.push(ParseWarning::DeprecatedKey {
+                key: key.to_string(),
+                replacement: replacement.to_string(),
+            });
+        }
+    }
+
+    let mut seen = HashSet::new();
+    task.tags.retain(|tag| {
+        if seen.insert(tag.to_lowercase()) {
+            return true;
+        }
+        warnings.push(ParseWarning::DuplicateTag(tag.clone()));
+        false
+    });
+
+    Ok((task, warnings))
+}
+
+/// Checks that the task survives being written and read back, listing the
+/// fields that would change otherwise
+pub fn verify_roundtrip(task: &Task) -> Result<()> {
+    let markdown = task_to_markdown(task)?;
+    let parsed = task_from_markdown(&markdown, &task.uuid, &task.status, task.id)?;
+
+    let mut changed = Vec::new();
+    let mut check = |field: &str, same: bool| {
+        if !same {
+            changed.push(field.to_string());
+        }
+    };
+    check("summary", parsed.summary == task.summary);
+    // Notes kept in a file never make it into the markdown
+    check(
+        "notes",
+        task.notes_file.is_some() || parsed.notes == task.notes,
+    );
+    check("notes_file", parsed.notes_file == task.notes_file);
+    check("tags", parsed.tags == task.tags);
+    check("project", parsed.project == task.project);
+    check("priority", parsed.priority == task.priority);
+    check("delegatedto", parsed.delegated_to == task.delegated_to);
+    check("waiting_for", parsed.waiting_for == task.waiting_for);
+    check("subtasks", parsed.subtasks == task.subtasks);
+    check("dependencies", parsed.dependencies == task.dependencies);
+    check("attachments", parsed.attachments == task.attachments);
+    check("created", parsed.created == task.created);
+    check("resolved", parsed.resolved == task.resolved);
+    check("due", parsed.due == task.due);
+    check("modified", parsed.modified == task.modified);
+    check("estimate", parsed.estimate == task.estimate);
+    check("time_spent", parsed.time_spent == task.time_spent);
+    check("reminders", parsed.reminders == task.reminders);
+    check("deleted_at", parsed.deleted_at == task.deleted_at);
+    check("recur", parsed.recur == task.recur);
+    check("extra fields", parsed.extra == task.extra);
+
+    if changed.is_empty() {
+        Ok(())
+    } else {
+        Err(RstaskError::Other(format!(
+            "Task {} would not be saved faithfully, changed fields: {}",
+            task.uuid,
+            changed.join(", ")
+        )))
+    }
+}
+
 /// Task frontmatter structure (task without notes)
 #[derive(Debug, Serialize, Deserialize, Clone)]
 struct TaskFrontmatter {
+    #[serde(alias = "desc", alias = "title")]
     summary: String,
 
-    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(
+        deserialize_with = "crate::task::tags_list_or_string::deserialize_option",
+        skip_serializing_if = "Option::is_none",
+        default
+    )]
     tags: Option<Vec<String>>,
 
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -135,15 +675,30 @@ struct TaskFrontmatter {
     #[serde(skip_serializing_if = "Option::is_none")]
     priority: Option<String>,
 
-    #[serde(skip_serializing_if = "Option::is_none")]
-    delegatedto: Option<String>,
+    #[serde(
+        serialize_with = "crate::task::delegates_string_or_list::serialize_option",
+        deserialize_with = "crate::task::delegates_string_or_list::deserialize_option",
+        skip_serializing_if = "Option::is_none",
+        default
+    )]
+    delegatedto: Option<Vec<String>>,
+
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    waiting_for: Option<String>,
 
     #[serde(skip_serializing_if = "Option::is_none")]
     subtasks: Option<Vec<crate::task::SubTask>>,
 
-    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(
+        default,
+        skip_serializing_if = "Option::is_none",
+        deserialize_with = "crate::task::string_or_list::deserialize_option"
+    )]
     dependencies: Option<Vec<String>>,
 
+    #[serde(skip_serializing_if = "Vec::is_empty", default)]
+    attachments: Vec<crate::task::Attachment>,
+
     #[serde(with = "crate::task::datetime_rfc3339")]
     created: chrono::DateTime<chrono::Utc>,
 
@@ -160,6 +715,86 @@ struct TaskFrontmatter {
         default
     )]
     due: Option<chrono::DateTime<chrono::Utc>>,
+
+    #[serde(
+        with = "crate::task::optional_datetime_rfc3339",
+        skip_serializing_if = "Option::is_none",
+        default
+    )]
+    modified: Option<chrono::DateTime<chrono::Utc>>,
+
+    #[serde(
+        deserialize_with = "deserialize_estimate",
+        skip_serializing_if = "Option::is_none",
+        default
+    )]
+    estimate: Option<Estimate>,
+
+    #[serde(
+        with = "crate::task::optional_duration_iso8601",
+        skip_serializing_if = "Option::is_none",
+        default
+    )]
+    time_spent: Option<chrono::Duration>,
+
+    #[serde(
+        with = "crate::task::duration_list_iso8601",
+        skip_serializing_if = "Vec::is_empty",
+        default
+    )]
+    reminders: Vec<chrono::Duration>,
+
+    #[serde(
+        with = "crate::task::optional_datetime_rfc3339",
+        skip_serializing_if = "Option::is_none",
+        default
+    )]
+    deleted_at: Option<chrono::DateTime<chrono::Utc>>,
+
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    recur: Option<crate::recur::Recurrence>,
+
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    notes_file: Option<String>,
+
+    /// Unknown keys, written back after the known ones
+    #[serde(flatten)]
+    extra: std::collections::BTreeMap<String, serde_yaml::Value>,
+}
+
+/// An estimate along with the format it's written in
+#[derive(Debug, Clone, Copy)]
+struct Estimate {
+    duration: chrono::Duration,
+    format: EstimateFormat,
+}
+
+impl Serialize for Estimate {
+    fn serialize<S: serde::Serializer>(
+        &self,
+        serializer: S,
+    ) -> std::result::Result<S::Ok, S::Error> {
+        serializer.serialize_str(&match self.format {
+            EstimateFormat::Iso8601 => format_iso8601_duration(self.duration),
+            EstimateFormat::Shorthand => format_duration_shorthand(self.duration),
+        })
+    }
+}
+
+/// Reads an estimate in either format, an empty one being no estimate
+fn deserialize_estimate<'de, D>(deserializer: D) -> std::result::Result<Option<Estimate>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    Ok(
+        crate::task::optional_duration_iso8601::deserialize(deserializer)?.map(|duration| {
+            Estimate {
+                duration,
+                // Only used when writing, where the configured format applies
+                format: EstimateFormat::default(),
+            }
+        }),
+    )
 }
 
 #[cfg(test)]
@@ -177,15 +812,26 @@ mod tests {
             deleted: false,
             summary: "Test task".to_string(),
             notes: "This is a note\nWith multiple lines".to_string(),
+            notes_file: None,
+            notes_loaded: false,
             tags: vec!["tag1".to_string(), "tag2".to_string()],
             project: "myproject".to_string(),
             priority: "H".to_string(),
-            delegated_to: String::new(),
+            delegated_to: Vec::new(),
+            waiting_for: None,
             subtasks: vec![],
             dependencies: vec![],
+            attachments: Vec::new(),
             created: Utc::now(),
             resolved: None,
             due: None,
+            modified: None,
+            estimate: None,
+            time_spent: None,
+            reminders: Vec::new(),
+            deleted_at: None,
+            recur: None,
+            extra: Default::default(),
             filtered: false,
         };
 
@@ -230,15 +876,26 @@ With multiple lines"#;
             deleted: false,
             summary: "Test task".to_string(),
             notes: "Note content".to_string(),
+            notes_file: None,
+            notes_loaded: false,
             tags: vec!["tag1".to_string()],
             project: "project1".to_string(),
             priority: "M".to_string(),
-            delegated_to: String::new(),
+            delegated_to: Vec::new(),
+            waiting_for: None,
             subtasks: vec![],
             dependencies: vec![],
+            attachments: Vec::new(),
             created: Utc::now(),
             resolved: None,
             due: None,
+            modified: None,
+            estimate: None,
+            time_spent: None,
+            reminders: Vec::new(),
+            deleted_at: None,
+            recur: None,
+            extra: Default::default(),
             filtered: false,
         };
 
@@ -251,4 +908,614 @@ With multiple lines"#;
         assert_eq!(original.project, restored.project);
         assert_eq!(original.priority, restored.priority);
     }
+
+    #[test]
+    fn test_attachments_roundtrip() {
+        let mut task = Task::new("Test task".to_string());
+        assert!(!task_to_markdown(&task).unwrap().contains("attachments:"));
+
+        task.add_attachment(
+            crate::task::AttachmentKind::File,
+            "docs/spec.pdf".to_string(),
+            None,
+        );
+        task.add_attachment(
+            crate::task::AttachmentKind::Url,
+            "https://example.com/issue/42".to_string(),
+            Some("Bug report".to_string()),
+        );
+
+        let md = task_to_markdown(&task).unwrap();
+        assert!(md.contains("attachments:"));
+        assert!(md.contains("kind: url"));
+
+        let restored = task_from_markdown(&md, &task.uuid, &task.status, 1).unwrap();
+        assert_eq!(restored.attachments, task.attachments);
+        assert!(verify_roundtrip(&task).is_ok());
+    }
+
+    #[test]
+    fn test_estimate_roundtrip() {
+        let mut task = Task::new("Test task".to_string());
+        task.estimate = Some(chrono::Duration::minutes(90));
+
+        let md = task_to_markdown(&task).unwrap();
+        assert!(md.contains("estimate: PT1H30M"));
+
+        let restored = task_from_markdown(&md, &task.uuid, "pending", 1).unwrap();
+        assert_eq!(restored.estimate, task.estimate);
+
+        task.estimate = None;
+        assert!(!task_to_markdown(&task).unwrap().contains("estimate"));
+    }
+
+    #[test]
+    fn test_estimate_out_of_range() {
+        let content = "---\nsummary: Test\ncreated: 2024-01-01T00:00:00Z\nestimate: P99999999999999999D\n---\n";
+        assert!(task_from_markdown(content, "uuid", "pending", 1).is_err());
+    }
+
+    #[test]
+    fn test_estimate_shorthand() {
+        let content = "---\nsummary: Test\ncreated: 2024-01-01T00:00:00Z\nestimate: 1.5h\n---\n";
+        let task = task_from_markdown(content, "uuid", "pending", 1).unwrap();
+        assert_eq!(task.estimate, Some(chrono::Duration::minutes(90)));
+
+        let content = "---\nsummary: Test\ncreated: 2024-01-01T00:00:00Z\nestimate: PT30M\n---\n";
+        let mut task = task_from_markdown(content, "uuid", "pending", 1).unwrap();
+        assert_eq!(task.estimate, Some(chrono::Duration::minutes(30)));
+
+        // The configured format is used when writing, whatever was read
+        let preferences = Preferences {
+            estimate_format: EstimateFormat::Shorthand,
+            ..Default::default()
+        };
+        task.estimate = Some(chrono::Duration::minutes(150));
+        let md = task_to_markdown_with(&task, &preferences).unwrap();
+        assert!(md.contains("estimate: 2h30m"), "{}", md);
+        assert!(
+            task_to_markdown(&task)
+                .unwrap()
+                .contains("estimate: PT2H30M")
+        );
+
+        let restored = task_from_markdown(&md, "uuid", "pending", 1).unwrap();
+        assert_eq!(restored.estimate, task.estimate);
+    }
+
+    #[test]
+    fn test_delegates_roundtrip() {
+        let single =
+            "---\nsummary: Test\ndelegatedto: Jane Doe\ncreated: 2024-01-01T00:00:00Z\n---\n";
+        let task = task_from_markdown(single, "uuid", "pending", 1).unwrap();
+        assert_eq!(task.delegates(), ["Jane Doe"]);
+        let md = task_to_markdown(&task).unwrap();
+        assert!(md.contains("delegatedto: Jane Doe\n"), "{}", md);
+        assert!(verify_roundtrip(&task).is_ok());
+
+        let list =
+            "---\nsummary: Test\ndelegatedto: [jane, bob]\ncreated: 2024-01-01T00:00:00Z\n---\n";
+        let task = task_from_markdown(list, "uuid", "pending", 1).unwrap();
+        assert_eq!(task.delegates(), ["jane", "bob"]);
+        let md = task_to_markdown(&task).unwrap();
+        assert!(md.contains("delegatedto:\n- jane\n- bob\n"), "{}", md);
+        let restored = task_from_markdown(&md, "uuid", "pending", 1).unwrap();
+        assert_eq!(restored.delegates(), task.delegates());
+
+        let empty = "---\nsummary: Test\ndelegatedto: ''\ncreated: 2024-01-01T00:00:00Z\n---\n";
+        let task = task_from_markdown(empty, "uuid", "pending", 1).unwrap();
+        assert!(task.delegates().is_empty());
+        assert!(!task_to_markdown(&task).unwrap().contains("delegatedto"));
+    }
+
+    #[test]
+    fn test_notes_file() {
+        let dir = tempfile::TempDir::new().unwrap();
+        std::fs::create_dir_all(dir.path().join("notes")).unwrap();
+        std::fs::write(dir.path().join("notes/long.md"), "A very long note\n").unwrap();
+
+        let content =
+            "---\nsummary: Test\nnotes_file: notes/long.md\ncreated: 2024-01-01T00:00:00Z\n---\n";
+        let prefs = Preferences::default();
+        let task =
+            task_from_markdown_at(content, "uuid", "pending", 1, &prefs, dir.path()).unwrap();
+        assert_eq!(task.notes, "A very long note\n");
+        assert_eq!(task.notes_file.as_deref(), Some("notes/long.md"));
+
+        // The reference is written back and the notes stay out of the body
+        let md = task_to_markdown(&task).unwrap();
+        assert!(md.contains("notes_file: notes/long.md\n"), "{}", md);
+        assert!(!md.contains("A very long note"));
+        assert!(verify_roundtrip(&task).is_ok());
+
+        let escaping = content.replace("notes/long.md", "../outside.md");
+        assert!(
+            task_from_markdown_at(&escaping, "uuid", "pending", 1, &prefs, dir.path()).is_err()
+        );
+    }
+
+    #[test]
+    fn test_oversized_frontmatter_rejected() {
+        let padding = format!("padding: {}\n", "x".repeat(2000));
+        let content = format!(
+            "---\nsummary: Test\n{}created: 2024-01-01T00:00:00Z\n---\n",
+            padding
+        );
+        assert!(task_from_markdown(&content, "uuid", "pending", 1).is_ok());
+
+        let prefs = Preferences {
+            max_frontmatter_bytes: 1024,
+            ..Default::default()
+        };
+        let result = task_from_markdown_with(&content, "uuid", "pending", 1, &prefs);
+        assert!(matches!(result, Err(RstaskError::Parse(_))));
+    }
+
+    #[test]
+    fn test_strict_frontmatter() {
+        let content =
+            "---\nsummary: Test\nsumary: typo\nsprint: 12\ncreated: 2024-01-01T00:00:00Z\n---\n";
+
+        let task = task_from_markdown(content, "uuid", "pending", 1).unwrap();
+        assert_eq!(task.extra["sumary"], serde_yaml::Value::from("typo"));
+
+        let mut prefs = Preferences {
+            strict_frontmatter: true,
+            ..Default::default()
+        };
+        prefs
+            .user_fields
+            .insert("sprint".to_string(), crate::uda::UdaType::Number);
+        match task_from_markdown_with(content, "uuid", "pending", 1, &prefs) {
+            Err(RstaskError::Parse(message)) => {
+                assert!(message.contains("'sumary'"), "{}", message)
+            }
+            other => panic!("expected a parse error, got {:?}", other),
+        }
+
+        // Declared user fields are expected
+        let content = "---\nsummary: Test\nsprint: 12\ncreated: 2024-01-01T00:00:00Z\n---\n";
+        assert!(task_from_markdown_with(content, "uuid", "pending", 1, &prefs).is_ok());
+    }
+
+    #[test]
+    fn test_json_frontmatter() {
+        let yaml = "---\nsummary: Generated\ntags: [work, report]\npriority: P1\ndependencies: abc-123\ncreated: 2024-01-01T00:00:00Z\ndue: 2024-02-01T09:00:00Z\nsource: generator\n---\n\nSome notes\n";
+        let json = r#"---
+{
+  "summary": "Generated",
+  "tags": ["work", "report"],
+  "priority": "P1",
+  "dependencies": "abc-123",
+  "created": "2024-01-01T00:00:00Z",
+  "due": "2024-02-01T09:00:00Z",
+  "source": "generator"
+}
+---
+
+Some notes
+"#;
+
+        let from_yaml = task_from_markdown(yaml, "uuid", "pending", 1).unwrap();
+        let from_json = task_from_markdown(json, "uuid", "pending", 1).unwrap();
+        assert!(
+            from_json.equals(&from_yaml),
+            "{:?}",
+            from_json.diff(&from_yaml)
+        );
+
+        // Written back as YAML
+        let md = task_to_markdown(&from_json).unwrap();
+        assert!(md.contains("summary: Generated\n"), "{}", md);
+
+        let broken = "---\n{\"summary\": \"Generated\"\n---\n";
+        assert!(task_from_markdown(broken, "uuid", "pending", 1).is_err());
+
+        // A YAML flow mapping isn't JSON but is still read
+        let flow =
+            "---\n{summary: Generated, tags: [work, report], created: 2024-01-01T00:00:00Z}\n---\n";
+        let task = task_from_markdown(flow, "uuid", "pending", 1).unwrap();
+        assert_eq!(task.summary, "Generated");
+        assert_eq!(task.tags, vec!["work", "report"]);
+    }
+
+    #[test]
+    fn test_dependencies_scalar_or_list() {
+        let scalar =
+            "---\nsummary: Test\ndependencies: abc-123\ncreated: 2024-01-01T00:00:00Z\n---\n";
+        let task = task_from_markdown(scalar, "uuid", "pending", 1).unwrap();
+        assert_eq!(task.dependencies, vec!["abc-123"]);
+        // Always written back as a list
+        let md = task_to_markdown(&task).unwrap();
+        assert!(md.contains("dependencies:\n- abc-123\n"), "{}", md);
+
+        let list = "---\nsummary: Test\ndependencies: [abc-123, def-456]\ncreated: 2024-01-01T00:00:00Z\n---\n";
+        let task = task_from_markdown(list, "uuid", "pending", 1).unwrap();
+        assert_eq!(task.dependencies, vec!["abc-123", "def-456"]);
+        assert!(verify_roundtrip(&task).is_ok());
+    }
+
+    #[test]
+    fn test_waiting_for_roundtrip() {
+        let mut task = Task::new("Test task".to_string());
+        assert!(!task_to_markdown(&task).unwrap().contains("waiting_for"));
+
+        task.waiting_for = Some("email from vendor".to_string());
+        let md = task_to_markdown(&task).unwrap();
+        assert!(md.contains("waiting_for: email from vendor\n"), "{}", md);
+        let restored = task_from_markdown(&md, &task.uuid, "pending", 1).unwrap();
+        assert_eq!(restored.waiting_for, task.waiting_for);
+        assert!(verify_roundtrip(&task).is_ok());
+    }
+
+    #[test]
+    fn test_reminders_roundtrip() {
+        let mut task = Task::new("Test task".to_string());
+        task.reminders = vec![chrono::Duration::days(1), chrono::Duration::minutes(30)];
+
+        let md = task_to_markdown(&task).unwrap();
+        assert!(md.contains("reminders:\n- P1D\n- PT30M\n"));
+
+        let restored = task_from_markdown(&md, &task.uuid, "pending", 1).unwrap();
+        assert_eq!(restored.reminders, task.reminders);
+
+        task.reminders.clear();
+        assert!(!task_to_markdown(&task).unwrap().contains("reminders"));
+
+        let content = "---\nsummary: x\ncreated: 2024-01-01T00:00:00Z\nreminders: [soon]\n---\n";
+        assert!(task_from_markdown(content, "uuid", "pending", 1).is_err());
+    }
+
+    #[test]
+    fn test_custom_delimiter_roundtrip() {
+        let mut task = Task::new("Fenced task".to_string());
+        task.notes = "--- not a delimiter ---\n---\nnor this".to_string();
+
+        let md = task_to_markdown(&task).unwrap();
+        assert!(md.starts_with("---\n"));
+        let restored = task_from_markdown(&md, &task.uuid, "pending", 1).unwrap();
+        assert!(restored.equals(&task));
+
+        let preferences = Preferences {
+            frontmatter_delimiter: "===".to_string(),
+            ..Default::default()
+        };
+        let md = task_to_markdown_with(&task, &preferences).unwrap();
+        assert!(md.starts_with("===\nsummary: Fenced task\n"));
+        let restored =
+            task_from_markdown_with(&md, &task.uuid, "pending", 1, &preferences).unwrap();
+        assert!(restored.equals(&task));
+
+        // Existing files keep loading after switching delimiters
+        let old = task_to_markdown(&task).unwrap();
+        assert!(task_from_markdown_with(&old, &task.uuid, "pending", 1, &preferences).is_ok());
+
+        // The closing delimiter has to match the opening one
+        let mismatched = "===\nsummary: x\ncreated: 2024-01-01T00:00:00Z\n---\n";
+        assert!(task_from_markdown_with(mismatched, "uuid", "pending", 1, &preferences).is_err());
+    }
+
+    #[test]
+    fn test_sort_tags_on_write() {
+        let mut task = Task::new("Test task".to_string());
+        task.tags = vec!["zeta".to_string(), "alpha".to_string()];
+
+        let md = task_to_markdown(&task).unwrap();
+        let restored = task_from_markdown(&md, &task.uuid, "pending", 1).unwrap();
+        assert_eq!(restored.tags, vec!["zeta", "alpha"]);
+
+        let preferences = Preferences {
+            sort_tags_on_write: true,
+            ..Default::default()
+        };
+        let md = task_to_markdown_with(&task, &preferences).unwrap();
+        let restored = task_from_markdown(&md, &task.uuid, "pending", 1).unwrap();
+        assert_eq!(restored.tags, vec!["alpha", "zeta"]);
+        assert_eq!(task.tags, vec!["zeta", "alpha"]);
+    }
+
+    #[test]
+    fn test_encrypted_notes_roundtrip() {
+        use crate::crypto::{NoteKey, decrypt_task_notes};
+
+        let key = NoteKey::from_bytes([42; 32]);
+        let preferences = Preferences {
+            encrypt_notes: true,
+            notes_key: Some(key.clone()),
+            ..Default::default()
+        };
+
+        let mut task = Task::new("Secret task".to_string());
+        task.notes = "the vault code is 0000".to_string();
+
+        let md = task_to_markdown_with(&task, &preferences).unwrap();
+        assert!(md.contains("summary: Secret task"));
+        assert!(!md.contains("vault"));
+
+        let mut restored = task_from_markdown(&md, &task.uuid, "pending", 1).unwrap();
+        assert!(is_encrypted_note(&restored.notes));
+        // Already encrypted notes are written back untouched
+        assert_eq!(task_to_markdown_with(&restored, &preferences).unwrap(), md);
+
+        decrypt_task_notes(&mut restored, Some(&key)).unwrap();
+        assert!(restored.equals(&task));
+
+        // Edited by hand in plain text, encrypted again when saved
+        let buffer = task_to_edit_markdown(&restored, &preferences).unwrap();
+        assert!(buffer.contains("the vault code is 0000"));
+        assert!(!buffer.contains(crate::crypto::ENCRYPTED_NOTE_MARKER));
+
+        let no_key = Preferences {
+            encrypt_notes: true,
+            ..Default::default()
+        };
+        assert!(task_to_markdown_with(&task, &no_key).is_err());
+    }
+
+    #[test]
+    fn test_verify_roundtrip() {
+        let mut task = Task::new("Plain task".to_string());
+        task.tags = vec!["work".to_string()];
+        task.notes = "Some notes".to_string();
+        assert!(verify_roundtrip(&task).is_ok());
+
+        // YAML-significant characters in the summary are quoted on write
+        task.summary = "- [ ] yes: no # not a comment, 'quoted' \"too\"".to_string();
+        assert!(verify_roundtrip(&task).is_ok());
+
+        task.notes = "\nStarts with a blank line\n".to_string();
+        let err = verify_roundtrip(&task).unwrap_err().to_string();
+        assert!(err.contains("changed fields: notes"), "{}", err);
+    }
+
+    #[test]
+    fn test_summary_aliases() {
+        for key in ["summary", "desc", "title"] {
+            let content = format!(
+                "---\n{}: Aliased\ncreated: 2024-01-01T00:00:00Z\n---\n",
+                key
+            );
+            let task = task_from_markdown(&content, "uuid", "pending", 1).unwrap();
+            assert_eq!(task.summary, "Aliased");
+
+            let md = task_to_markdown(&task).unwrap();
+            assert!(md.contains("summary: Aliased"));
+            assert!(!md.contains("desc:") && !md.contains("title:"));
+        }
+    }
+
+    #[test]
+    fn test_tags_as_string() {
+        let content = "---\nsummary: Test\ntags: \"work, urgent  home,\"\ncreated: 2024-01-01T00:00:00Z\n---\n";
+        let task = task_from_markdown(content, "uuid", "pending", 1).unwrap();
+        assert_eq!(task.tags, vec!["work", "urgent", "home"]);
+        assert!(
+            task_to_markdown(&task)
+                .unwrap()
+                .contains("tags:\n- work\n- urgent\n- home\n")
+        );
+
+        let content = "---\nsummary: Test\ntags: [a, b]\ncreated: 2024-01-01T00:00:00Z\n---\n";
+        let task = task_from_markdown(content, "uuid", "pending", 1).unwrap();
+        assert_eq!(task.tags, vec!["a", "b"]);
+
+        let content = "---\nsummary: Test\ncreated: 2024-01-01T00:00:00Z\n---\n";
+        let task = task_from_markdown(content, "uuid", "pending", 1).unwrap();
+        assert!(task.tags.is_empty());
+    }
+
+    #[test]
+    fn test_footer_roundtrip() {
+        let preferences = Preferences {
+            emit_footer: true,
+            ..Default::default()
+        };
+
+        let mut task = Task::new("Test task".to_string());
+        task.id = 7;
+        let md = task_to_markdown_with(&task, &preferences).unwrap();
+        assert!(md.ends_with(&format!("\n\n<!-- rstask: uuid={} -->\n", task.uuid)));
+        let restored = task_from_markdown(&md, &task.uuid, "pending", 7).unwrap();
+        assert!(restored.equals(&task));
+        assert_eq!(restored.notes, "");
+
+        task.notes = "Some notes\n\n<!-- a comment of the user's own -->".to_string();
+        let md = task_to_markdown_with(&task, &preferences).unwrap();
+        let restored = task_from_markdown(&md, &task.uuid, "pending", 7).unwrap();
+        assert_eq!(restored.notes, task.notes);
+        assert_eq!(notes_only(&md).unwrap(), task.notes);
+
+        assert!(!task_to_markdown(&task).unwrap().contains("<!-- rstask:"));
+
+        // A comment that only looks like a footer is the user's
+        for notes in [
+            "Some notes\n<!-- rstask: remember the milk -->",
+            "Some notes\n<!-- rstask: uuid=not-a-uuid -->",
+            "Some notes\n<!-- rstask: uuid=7e2f2bb4-4e0c-4c4c-9a55-3b8c2e9f0d61 id=7 -->",
+        ] {
+            task.notes = notes.to_string();
+            let md = task_to_markdown(&task).unwrap();
+            let restored = task_from_markdown(&md, &task.uuid, "pending", 7).unwrap();
+            assert_eq!(restored.notes, notes);
+        }
+    }
+
+    #[test]
+    fn test_unknown_keys_preserved() {
+        let content =
+            "---\nsummary: Test\ncreated: 2024-01-01T00:00:00Z\nzeta: [1, 2]\nalpha: hello\n---\n";
+        let task = task_from_markdown(content, "uuid", "pending", 1).unwrap();
+        assert_eq!(task.extra.len(), 2);
+        assert_eq!(task.extra["alpha"], serde_yaml::Value::from("hello"));
+
+        let md = task_to_markdown(&task).unwrap();
+        assert!(
+            md.ends_with("alpha: hello\nzeta:\n- 1\n- 2\n---\n"),
+            "{}",
+            md
+        );
+        assert!(verify_roundtrip(&task).is_ok());
+    }
+
+    #[test]
+    fn test_verbose_parse_reports_duplicate_tags() {
+        let content = "---\nsummary: Test\ntags: [work, home, work, Home]\ncreated: 2024-01-01T00:00:00Z\n---\n";
+        let preferences = Preferences::default();
+
+        let (task, warnings) =
+            task_from_markdown_verbose(content, "uuid", "pending", 1, &preferences).unwrap();
+        assert_eq!(task.tags, vec!["work", "home"]);
+        assert_eq!(
+            warnings.iter().map(ToString::to_string).collect::<Vec<_>>(),
+            vec![
+                "collapsed duplicate tag 'work'",
+                "collapsed duplicate tag 'Home'"
+            ]
+        );
+
+        let clean = "---\nsummary: Test\ntags: [work]\ncreated: 2024-01-01T00:00:00Z\n---\n";
+        let (_, warnings) =
+            task_from_markdown_verbose(clean, "uuid", "pending", 1, &preferences).unwrap();
+        assert!(warnings.is_empty());
+    }
+
+    #[test]
+    fn test_yaml_anchors_rejected() {
+        let content = "---\nsummary: &name Test\ntags: [a]\ncreated: 2024-01-01T00:00:00Z\nproject: *name\n---\n";
+
+        let err = task_from_markdown(content, "uuid", "pending", 1).unwrap_err();
+        assert!(matches!(err, RstaskError::Parse(_)));
+        assert!(err.to_string().contains("&name on line 1"), "{}", err);
+
+        let preferences = Preferences {
+            allow_yaml_anchors: true,
+            ..Default::default()
+        };
+        let task = task_from_markdown_with(content, "uuid", "pending", 1, &preferences).unwrap();
+        assert_eq!(task.project, "Test");
+    }
+
+    #[test]
+    fn test_find_yaml_anchor() {
+        assert_eq!(
+            find_yaml_anchor("tags:\n- a\n- *alias"),
+            Some((3, "*alias".to_string()))
+        );
+        assert_eq!(
+            find_yaml_anchor("tags: [a, &t b]"),
+            Some((1, "&t".to_string()))
+        );
+        assert_eq!(
+            find_yaml_anchor("summary: !!str &a x\nproject: *a"),
+            Some((1, "&a".to_string()))
+        );
+        assert_eq!(
+            find_yaml_anchor("subtasks:\n- summary: a\n  resolved: *yes"),
+            Some((3, "*yes".to_string()))
+        );
+        assert_eq!(
+            find_yaml_anchor("summary: long\n  text\nproject: *p"),
+            Some((3, "*p".to_string()))
+        );
+        assert_eq!(
+            find_yaml_anchor("summary: !local\n  &a value"),
+            Some((2, "&a".to_string()))
+        );
+        assert_eq!(
+            find_yaml_anchor("tags: !!seq &t\n- a"),
+            Some((1, "&t".to_string()))
+        );
+        assert_eq!(
+            find_yaml_anchor("? *key\n: value"),
+            Some((1, "*key".to_string()))
+        );
+        assert_eq!(
+            find_yaml_anchor("? complex\n: &v value"),
+            Some((2, "&v".to_string()))
+        );
+
+        // Not anchors: text inside values, quotes, comments and block scalars
+        for yaml in [
+            "summary: Tom & Jerry",
+            "summary: Read *this* & that",
+            "summary: \"&quoted\"",
+            "summary: 'it''s *fine*'",
+            "summary: fine # &comment",
+            "summary: a, &b",
+            "notes: |\n  &not-an-anchor\n  *nor-an-alias\nproject: x",
+            "summary: a plain scalar\n  *continued* on the next line\n  & another",
+            "tags:\n- one long\n  *tag*",
+            "summary:\n  starts on the next line\n  *and continues*",
+            "summary:\n  # a comment\n  text\n  & more",
+            "? a complex key\n  *continued*\n: value",
+        ] {
+            assert_eq!(find_yaml_anchor(yaml), None, "{}", yaml);
+        }
+    }
+
+    #[test]
+    fn test_deprecated_keys_are_aliases() {
+        for (key, replacement) in DEPRECATED_KEYS {
+            let frontmatter = format!("{}: via alias\ncreated: 2024-01-01T00:00:00Z\n", key);
+            let expected = format!("{}: via alias", replacement);
+
+            let task =
+                task_from_markdown(&format!("---\n{}---\n", frontmatter), "uuid", "pending", 1)
+                    .unwrap();
+            assert!(task.extra.is_empty(), "{} is not an alias", key);
+            assert!(
+                task_to_markdown(&task).unwrap().contains(&expected),
+                "{}",
+                key
+            );
+
+            let legacy: Task = serde_yaml::from_str(&frontmatter).unwrap();
+            assert!(
+                serde_yaml::to_string(&legacy).unwrap().contains(&expected),
+                "{} is not an alias of Task",
+                key
+            );
+        }
+    }
+
+    #[test]
+    fn test_verbose_parse_reports_deprecated_keys() {
+        let content = "---\ndesc: Old style\ncreated: 2024-01-01T00:00:00Z\n---\n";
+        let preferences = Preferences::default();
+
+        let (task, warnings) =
+            task_from_markdown_verbose(content, "uuid", "pending", 1, &preferences).unwrap();
+        assert_eq!(task.summary, "Old style");
+        assert_eq!(
+            warnings,
+            vec![ParseWarning::DeprecatedKey {
+                key: "desc".to_string(),
+                replacement: "summary".to_string(),
+            }]
+        );
+        assert_eq!(
+            warnings[0].to_string(),
+            "key 'desc' is deprecated and will be saved as 'summary'"
+        );
+
+        let content = "---\nsummary: New style\ncreated: 2024-01-01T00:00:00Z\n---\n";
+        let (_, warnings) =
+            task_from_markdown_verbose(content, "uuid", "pending", 1, &preferences).unwrap();
+        assert!(warnings.is_empty());
+    }
+
+    #[test]
+    fn test_notes_only_ignores_unknown_frontmatter() {
+        let content = r#"---
+summary: Test task
+some_other_tool: [1, 2, 3]
+created: not even a date
+---
+
+Just the notes
+Second line"#;
+
+        assert_eq!(notes_only(content).unwrap(), "Just the notes\nSecond line");
+        assert!(notes_only("no frontmatter here").is_err());
+    }
 }
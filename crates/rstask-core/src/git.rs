@@ -1,5 +1,6 @@
 use crate::Result;
-use git2::Repository;
+use chrono::{DateTime, TimeZone, Utc};
+use git2::{ErrorCode, Oid, Repository, Sort, Tree};
 use std::io::{self, Write};
 use std::path::Path;
 
@@ -278,3 +279,123 @@ pub fn git_reset(repo_path: &Path) -> Result<()> {
     repo.reset(parent.as_object(), git2::ResetType::Hard, None)?;
     Ok(())
 }
+
+/// When the most recent commit touching `path` was made, read from the
+/// repository the file is in. Files outside a repository, or never
+/// committed, give None.
+pub fn last_commit_time(path: &Path) -> Result<Option<DateTime<Utc>>> {
+    let dir = match path.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => dir,
+        _ => Path::new("."),
+    };
+    let repo = match Repository::discover(dir) {
+        Ok(repo) => repo,
+        Err(e) if e.code() == ErrorCode::NotFound => return Ok(None),
+        Err(e) => return Err(e.into()),
+    };
+    let Some(workdir) = repo.workdir() else {
+        return Ok(None);
+    };
+
+    // The file itself may be gone, so resolve symlinks through its directory
+    let workdir = workdir.canonicalize()?;
+    let Some(name) = path.file_name() else {
+        return Ok(None);
+    };
+    let full = dir.canonicalize()?.join(name);
+    let Ok(relative) = full.strip_prefix(&workdir) else {
+        return Ok(None);
+    };
+
+    let head = match repo.head() {
+        Ok(head) => head.peel_to_commit()?,
+        // No commits yet
+        Err(e) if e.code() == ErrorCode::UnbornBranch || e.code() == ErrorCode::NotFound => {
+            return Ok(None);
+        }
+        Err(e) => return Err(e.into()),
+    };
+    let mut revwalk = repo.revwalk()?;
+    revwalk.push(head.id())?;
+    revwalk.set_sorting(Sort::TIME)?;
+
+    let blob_at = |tree: &Tree| -> Option<Oid> { tree.get_path(relative).ok().map(|e| e.id()) };
+    for oid in revwalk {
+        let commit = repo.find_commit(oid?)?;
+        let current = blob_at(&commit.tree()?);
+        let touched = if commit.parent_count() == 0 {
+            current.is_some()
+        } else {
+            let mut differs = false;
+            for parent in commit.parents() {
+                differs |= blob_at(&parent.tree()?) != current;
+            }
+            differs
+        };
+
+        if touched {
+            return Ok(Utc.timestamp_opt(commit.time().seconds(), 0).single());
+        }
+    }
+
+    Ok(None)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn commit_all(repo: &Repository, message: &str, seconds: i64) {
+        let mut index = repo.index().unwrap();
+        index
+            .add_all(["*"], git2::IndexAddOption::DEFAULT, None)
+            .unwrap();
+        index.write().unwrap();
+        let tree = repo.find_tree(index.write_tree().unwrap()).unwrap();
+
+        let signature =
+            git2::Signature::new("rstask", "rstask@example.com", &git2::Time::new(seconds, 0))
+                .unwrap();
+        let parent = repo.head().ok().and_then(|head| head.peel_to_commit().ok());
+        let parents: Vec<&git2::Commit> = parent.iter().collect();
+        repo.commit(
+            Some("HEAD"),
+            &signature,
+            &signature,
+            message,
+            &tree,
+            &parents,
+        )
+        .unwrap();
+    }
+
+    #[test]
+    fn test_last_commit_time() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let repo = Repository::init(dir.path()).unwrap();
+        let tracked = dir.path().join("pending").join("task.md");
+        let untracked = dir.path().join("pending").join("new.md");
+
+        std::fs::create_dir_all(tracked.parent().unwrap()).unwrap();
+        std::fs::write(&tracked, "first").unwrap();
+        assert_eq!(last_commit_time(&tracked).unwrap(), None);
+
+        commit_all(&repo, "Add task", 1_700_000_000);
+        std::fs::write(&untracked, "not committed").unwrap();
+
+        let expected = Utc.timestamp_opt(1_700_000_000, 0).single();
+        assert_eq!(last_commit_time(&tracked).unwrap(), expected);
+        assert_eq!(last_commit_time(&untracked).unwrap(), None);
+
+        // Later commits leaving the file alone don't count
+        std::fs::write(dir.path().join("other.md"), "other").unwrap();
+        commit_all(&repo, "Add other", 1_700_000_500);
+        assert_eq!(last_commit_time(&tracked).unwrap(), expected);
+
+        let outside = tempfile::TempDir::new().unwrap();
+        assert_eq!(
+            last_commit_time(&outside.path().join("task.md")).unwrap(),
+            None
+        );
+    }
+}
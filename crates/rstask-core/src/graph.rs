@@ -0,0 +1,735 @@
+// Dependency graph helpers
+use crate::constants::STATUS_RESOLVED;
+use crate::task::Task;
+use crate::{Result, RstaskError};
+use chrono::{DateTime, Duration, Utc};
+use std::collections::{HashMap, HashSet};
+
+#[derive(Clone, Copy, PartialEq)]
+enum Visit {
+    New,
+    InProgress,
+    Done,
+}
+
+/// Indexes tasks by UUID so dependencies can be followed
+fn index_by_uuid(tasks: &[Task]) -> HashMap<&str, usize> {
+    tasks
+        .iter()
+        .enumerate()
+        .map(|(idx, task)| (task.uuid.as_str(), idx))
+        .collect()
+}
+
+/// Builds the cycle error for a dependency found while it was still being visited
+fn cycle_error(tasks: &[Task], stack: &[usize], repeated: usize) -> RstaskError {
+    let start = stack.iter().position(|&idx| idx == repeated).unwrap_or(0);
+    let mut cycle: Vec<&str> = stack[start..]
+        .iter()
+        .map(|&idx| tasks[idx].uuid.as_str())
+        .collect();
+    cycle.push(tasks[repeated].uuid.as_str());
+    RstaskError::DependencyCycle(cycle.join(" -> "))
+}
+
+/// Weight of a chain: total estimate first, then number of tasks
+type Weight = (Duration, usize);
+
+struct CriticalPath<'a> {
+    tasks: &'a [Task],
+    index: HashMap<&'a str, usize>,
+    state: Vec<Visit>,
+    /// Heaviest chain ending at each task, and the dependency it came through
+    best: Vec<(Weight, Option<usize>)>,
+    stack: Vec<usize>,
+}
+
+impl CriticalPath<'_> {
+    fn visit(&mut self, idx: usize) -> Result<()> {
+        match self.state[idx] {
+            Visit::Done => return Ok(()),
+            Visit::InProgress => return Err(cycle_error(self.tasks, &self.stack, idx)),
+            Visit::New => {}
+        }
+
+        self.state[idx] = Visit::InProgress;
+        self.stack.push(idx);
+
+        let task = &self.tasks[idx];
+        let mut heaviest: Option<(Weight, usize)> = None;
+        for dep_uuid in &task.dependencies {
+            // Dependencies outside the given tasks don't contribute
+            let Some(&dep) = self.index.get(dep_uuid.as_str()) else {
+                continue;
+            };
+
+            self.visit(dep)?;
+            let weight = self.best[dep].0;
+            if heaviest.is_none_or(|(w, _)| weight > w) {
+                heaviest = Some((weight, dep));
+            }
+        }
+
+        let own = task.estimate.unwrap_or(DEFAULT_SCHEDULE_DURATION);
+        self.best[idx] = match heaviest {
+            Some(((estimate, count), dep)) => {
+                let total = estimate.checked_add(&own).ok_or_else(|| {
+                    RstaskError::Other(format!(
+                        "estimates along the chain to task {} are too large to add up",
+                        task.uuid
+                    ))
+                })?;
+                ((total, count + 1), Some(dep))
+            }
+            None => ((own, 1), None),
+        };
+
+        self.stack.pop();
+        self.state[idx] = Visit::Done;
+        Ok(())
+    }
+}
+
+/// Returns the heaviest chain of dependencies, ordered from the first task to
+/// do to the last. Chains are weighed by their total `estimate`, tasks
+/// without one counting [`DEFAULT_SCHEDULE_DURATION`] as when scheduling,
+/// with the number of tasks breaking ties, so without estimates this is the
+/// longest chain. Dependencies on tasks not in `tasks` are ignored.
+pub fn critical_path(tasks: &[Task]) -> Result<Vec<&Task>> {
+    let mut cp = CriticalPath {
+        tasks,
+        index: index_by_uuid(tasks),
+        state: vec![Visit::New; tasks.len()],
+        best: vec![((Duration::zero(), 0), None); tasks.len()],
+        stack: Vec::new(),
+    };
+
+    for idx in 0..tasks.len() {
+        cp.visit(idx)?;
+    }
+
+    let mut end: Option<usize> = None;
+    for idx in 0..tasks.len() {
+        if end.is_none_or(|e| cp.best[idx].0 > cp.best[e].0) {
+            end = Some(idx);
+        }
+    }
+
+    let mut path = Vec::new();
+    let mut current = end;
+    while let Some(idx) = current {
+        path.push(&tasks[idx]);
+        current = cp.best[idx].1;
+    }
+    path.reverse();
+
+    Ok(path)
+}
+
+struct Depths<'a> {
+    tasks: &'a [Task],
+    index: HashMap<&'a str, usize>,
+    state: Vec<Visit>,
+    depth: Vec<usize>,
+    stack: Vec<usize>,
+}
+
+impl Depths<'_> {
+    fn visit(&mut self, idx: usize) -> Result<()> {
+        match self.state[idx] {
+            Visit::Done => return Ok(()),
+            Visit::InProgress => return Err(cycle_error(self.tasks, &self.stack, idx)),
+            Visit::New => {}
+        }
+
+        self.state[idx] = Visit::InProgress;
+        self.stack.push(idx);
+
+        let mut depth = 0;
+        for dep_uuid in &self.tasks[idx].dependencies {
+            // Dependencies outside the given tasks don't add a level
+            let Some(&dep) = self.index.get(dep_uuid.as_str()) else {
+                continue;
+            };
+
+            self.visit(dep)?;
+            depth = depth.max(self.depth[dep] + 1);
+        }
+        self.depth[idx] = depth;
+
+        self.stack.pop();
+        self.state[idx] = Visit::Done;
+        Ok(())
+    }
+}
+
+/// Maps each task's UUID to its depth in the dependency graph: 0 for tasks
+/// without dependencies, otherwise one more than their deepest dependency,
+/// i.e. the longest chain down to a root. Dependencies on tasks not in
+/// `tasks` are ignored.
+pub fn dependency_depths(tasks: &[Task]) -> Result<HashMap<String, usize>> {
+    let mut depths = Depths {
+        tasks,
+        index: index_by_uuid(tasks),
+        state: vec![Visit::New; tasks.len()],
+        depth: vec![0; tasks.len()],
+        stack: Vec::new(),
+    };
+
+    for idx in 0..tasks.len() {
+        depths.visit(idx)?;
+    }
+
+    Ok(tasks
+        .iter()
+        .zip(depths.depth)
+        .map(|(task, depth)| (task.uuid.clone(), depth))
+        .collect())
+}
+
+/// Duration assumed for tasks without an estimate when scheduling
+pub const DEFAULT_SCHEDULE_DURATION: Duration = Duration::hours(1);
+
+/// A task placed on the timeline
+#[derive(Debug, Clone)]
+pub struct ScheduledTask<'a> {
+    pub task: &'a Task,
+    pub start: DateTime<Utc>,
+    pub end: DateTime<Utc>,
+}
+
+struct Scheduler<'a> {
+    tasks: &'a [Task],
+    index: HashMap<&'a str, usize>,
+    state: Vec<Visit>,
+    end: Vec<DateTime<Utc>>,
+    stack: Vec<usize>,
+    start: DateTime<Utc>,
+    scheduled: Vec<ScheduledTask<'a>>,
+}
+
+impl Scheduler<'_> {
+    fn visit(&mut self, idx: usize) -> Result<()> {
+        match self.state[idx] {
+            Visit::Done => return Ok(()),
+            Visit::InProgress => return Err(cycle_error(self.tasks, &self.stack, idx)),
+            Visit::New => {}
+        }
+
+        self.state[idx] = Visit::InProgress;
+        self.stack.push(idx);
+
+        let task = &self.tasks[idx];
+        let mut start = self.start;
+        for dep_uuid in &task.dependencies {
+            // Dependencies outside the given tasks don't hold anything up
+            let Some(&dep) = self.index.get(dep_uuid.as_str()) else {
+                continue;
+            };
+
+            self.visit(dep)?;
+            start = start.max(self.end[dep]);
+        }
+
+        let duration = task.estimate.unwrap_or(DEFAULT_SCHEDULE_DURATION);
+        let end = start.checked_add_signed(duration).ok_or_else(|| {
+            RstaskError::Other(format!(
+                "task {} can't be scheduled, its estimate runs past the last representable date",
+                task.uuid
+            ))
+        })?;
+        self.end[idx] = end;
+        self.scheduled.push(ScheduledTask { task, start, end });
+
+        self.stack.pop();
+        self.state[idx] = Visit::Done;
+        Ok(())
+    }
+}
+
+/// Lays tasks out on a timeline beginning at `start`. Each task starts once
+/// all its dependencies have finished and lasts for its `estimate`, or
+/// [`DEFAULT_SCHEDULE_DURATION`] without one. Tasks are returned with their
+/// dependencies before them. Fails if the timeline would run past the last
+/// representable date.
+pub fn schedule(tasks: &[Task], start: DateTime<Utc>) -> Result<Vec<ScheduledTask<'_>>> {
+    let mut scheduler = Scheduler {
+        tasks,
+        index: index_by_uuid(tasks),
+        state: vec![Visit::New; tasks.len()],
+        end: vec![start; tasks.len()],
+        stack: Vec::new(),
+        start,
+        scheduled: Vec::with_capacity(tasks.len()),
+    };
+
+    for idx in 0..tasks.len() {
+        scheduler.visit(idx)?;
+    }
+
+    Ok(scheduler.scheduled)
+}
+
+/// Returns pairs of scheduled tasks whose time windows intersect, in the
+/// order given. Windows are half-open, so a task starting just as another
+/// ends doesn't overlap it.
+pub fn overlaps<'s, 'a>(
+    scheduled: &'s [ScheduledTask<'a>],
+) -> Vec<(&'s ScheduledTask<'a>, &'s ScheduledTask<'a>)> {
+    let mut pairs = Vec::new();
+    for (i, first) in scheduled.iter().enumerate() {
+        for second in &scheduled[i + 1..] {
+            if first.start < second.end && second.start < first.end {
+                pairs.push((first, second));
+            }
+        }
+    }
+    pairs
+}
+
+/// A task and the tasks it depends on, as built by [`dependency_tree`]
+#[derive(Debug, Clone)]
+pub struct TreeNode<'a> {
+    pub uuid: String,
+    /// None when the UUID isn't among the given tasks
+    pub task: Option<&'a Task>,
+    pub children: Vec<TreeNode<'a>>,
+    /// The task was already expanded elsewhere in the tree, e.g. through a
+    /// diamond or a cycle, so its dependencies aren't repeated here
+    pub revisited: bool,
+}
+
+/// Builds the tree of dependencies below `root_uuid`, in the order they are
+/// listed on each task. Every task is expanded once; later appearances are
+/// leaves marked as revisited, which also stops cycles.
+pub fn dependency_tree<'a>(root_uuid: &str, tasks: &'a [Task]) -> TreeNode<'a> {
+    fn build<'a>(
+        uuid: &str,
+        tasks: &'a [Task],
+        index: &HashMap<&str, usize>,
+        expanded: &mut HashSet<usize>,
+    ) -> TreeNode<'a> {
+        let Some(&idx) = index.get(uuid) else {
+            return TreeNode {
+                uuid: uuid.to_string(),
+                task: None,
+                children: Vec::new(),
+                revisited: false,
+            };
+        };
+
+        let task = &tasks[idx];
+        let revisited = !expanded.insert(idx);
+        let children = if revisited {
+            Vec::new()
+        } else {
+            task.dependencies
+                .iter()
+                .map(|dep| build(dep, tasks, index, expanded))
+                .collect()
+        };
+
+        TreeNode {
+            uuid: uuid.to_string(),
+            task: Some(task),
+            children,
+            revisited,
+        }
+    }
+
+    let index = index_by_uuid(tasks);
+    build(root_uuid, tasks, &index, &mut HashSet::new())
+}
+
+/// The tasks left to do to finish `goal_uuid`: its unresolved dependencies,
+/// direct or not, followed by the goal itself, with every task after the
+/// ones it depends on. Resolved and deleted dependencies are pruned along
+/// with whatever is below them. An unknown goal gives nothing.
+pub fn required_for<'a>(goal_uuid: &str, tasks: &'a [Task]) -> Vec<&'a Task> {
+    fn visit<'a>(
+        idx: usize,
+        tasks: &'a [Task],
+        index: &HashMap<&str, usize>,
+        seen: &mut HashSet<usize>,
+        required: &mut Vec<&'a Task>,
+    ) {
+        if !seen.insert(idx) {
+            return;
+        }
+        for dep in &tasks[idx].dependencies {
+            if let Some(&dep_idx) = index.get(dep.as_str()) {
+                let dependency = &tasks[dep_idx];
+                if !dependency.deleted && dependency.status != STATUS_RESOLVED {
+                    visit(dep_idx, tasks, index, seen, required);
+                }
+            }
+        }
+        required.push(&tasks[idx]);
+    }
+
+    let index = index_by_uuid(tasks);
+    let mut required = Vec::new();
+    if let Some(&goal) = index.get(goal_uuid) {
+        visit(goal, tasks, &index, &mut HashSet::new(), &mut required);
+    }
+    required
+}
+
+/// Counts the tasks waiting on `uuid`, directly or through other tasks, i.e.
+/// how many would be closer to unblocked once it is done. Resolved and
+/// deleted tasks don't wait on anything, so they are neither counted nor
+/// followed. The task itself isn't counted, even within a cycle.
+pub fn unblock_impact(uuid: &str, tasks: &[Task]) -> usize {
+    let mut dependents: HashMap<&str, Vec<&str>> = HashMap::new();
+    for task in tasks
+        .iter()
+        .filter(|task| !task.deleted && task.status != STATUS_RESOLVED)
+    {
+        for dep in &task.dependencies {
+            dependents
+                .entry(dep.as_str())
+                .or_default()
+                .push(task.uuid.as_str());
+        }
+    }
+
+    let mut seen = HashSet::from([uuid]);
+    let mut queue = vec![uuid];
+    while let Some(current) = queue.pop() {
+        for &dependent in dependents.get(current).into_iter().flatten() {
+            if seen.insert(dependent) {
+                queue.push(dependent);
+            }
+        }
+    }
+
+    seen.len() - 1
+}
+
+/// Returns (task, dependency) pairs where the task is due before something
+/// it depends on, so it can't be finished on time. Only direct dependencies
+/// are compared, and resolved or deleted tasks on either side are ignored.
+pub fn scheduling_warnings(tasks: &[Task]) -> Vec<(&Task, &Task)> {
+    let index = index_by_uuid(tasks);
+    let open = |task: &Task| !task.deleted && task.status != STATUS_RESOLVED;
+
+    let mut warnings = Vec::new();
+    for task in tasks.iter().filter(|task| open(task)) {
+        let Some(due) = task.due else {
+            continue;
+        };
+
+        for dep_uuid in &task.dependencies {
+            let Some(&dep) = index.get(dep_uuid.as_str()) else {
+                continue;
+            };
+            let dependency = &tasks[dep];
+            if open(dependency) && dependency.due.is_some_and(|dep_due| due < dep_due) {
+                warnings.push((task, dependency));
+            }
+        }
+    }
+
+    warnings
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn task(summary: &str, hours: Option<i64>, deps: &[&Task]) -> Task {
+        let mut task = Task::new(summary.to_string());
+        task.estimate = hours.map(Duration::hours);
+        task.dependencies = deps.iter().map(|d| d.uuid.clone()).collect();
+        task
+    }
+
+    fn summaries(path: &[&Task]) -> Vec<String> {
+        path.iter().map(|t| t.summary.clone()).collect()
+    }
+
+    #[test]
+    fn test_critical_path_linear_chain() {
+        let a = task("a", None, &[]);
+        let b = task("b", None, &[&a]);
+        let c = task("c", None, &[&b]);
+        let lone = task("lone", None, &[]);
+
+        let tasks = vec![c, lone, a, b];
+        let path = critical_path(&tasks).unwrap();
+        assert_eq!(summaries(&path), vec!["a", "b", "c"]);
+    }
+
+    #[test]
+    fn test_critical_path_diamond_uses_estimates() {
+        let a = task("a", Some(1), &[]);
+        let short = task("short", Some(1), &[&a]);
+        let long = task("long", Some(3), &[&a]);
+        let d = task("d", Some(1), &[&short, &long]);
+
+        let tasks = vec![a, short, long, d];
+        let path = critical_path(&tasks).unwrap();
+        assert_eq!(summaries(&path), vec!["a", "long", "d"]);
+    }
+
+    #[test]
+    fn test_critical_path_unestimated_tasks_carry_weight() {
+        let quick = Task {
+            estimate: Some(Duration::minutes(1)),
+            ..task("quick", None, &[])
+        };
+        let mut chain = vec![task("step 0", None, &[])];
+        for i in 1..10 {
+            let step = task(&format!("step {}", i), None, &[chain.last().unwrap()]);
+            chain.push(step);
+        }
+
+        let mut tasks = vec![quick];
+        tasks.extend(chain);
+        let path = critical_path(&tasks).unwrap();
+        assert_eq!(path.len(), 10);
+        assert_eq!(path[9].summary, "step 9");
+    }
+
+    #[test]
+    fn test_critical_path_estimate_overflow() {
+        let huge = |summary: &str, deps: &[&Task]| Task {
+            estimate: Some(Duration::MAX),
+            ..task(summary, None, deps)
+        };
+        let a = huge("a", &[]);
+        let b = huge("b", &[&a]);
+
+        assert!(matches!(critical_path(&[a, b]), Err(RstaskError::Other(_))));
+    }
+
+    #[test]
+    fn test_critical_path_cycle() {
+        let mut a = task("a", None, &[]);
+        let b = task("b", None, &[&a]);
+        a.dependencies.push(b.uuid.clone());
+
+        let tasks = vec![a, b];
+        assert!(matches!(
+            critical_path(&tasks),
+            Err(RstaskError::DependencyCycle(_))
+        ));
+    }
+
+    #[test]
+    fn test_dependency_depths() {
+        // A three-level chain, plus a diamond whose long side is two deep
+        let a = task("a", None, &[]);
+        let b = task("b", None, &[&a]);
+        let c = task("c", None, &[&b]);
+        let d = task("d", None, &[&a, &c]);
+        let lone = task("lone", None, &[]);
+
+        let uuids: Vec<String> = [&a, &b, &c, &d, &lone]
+            .iter()
+            .map(|t| t.uuid.clone())
+            .collect();
+        let tasks = vec![d, c, lone, b, a];
+
+        let depths = dependency_depths(&tasks).unwrap();
+        let got: Vec<usize> = uuids.iter().map(|uuid| depths[uuid]).collect();
+        assert_eq!(got, vec![0, 1, 2, 3, 0]);
+
+        let mut x = task("x", None, &[]);
+        let y = task("y", None, &[&x]);
+        x.dependencies.push(y.uuid.clone());
+        assert!(matches!(
+            dependency_depths(&[x, y]),
+            Err(RstaskError::DependencyCycle(_))
+        ));
+    }
+
+    #[test]
+    fn test_schedule_dependency_chain() {
+        let start = Utc::now();
+        let design = task("design", Some(2), &[]);
+        let build = task("build", Some(3), &[&design]);
+        let unestimated = task("unestimated", None, &[]);
+
+        let tasks = vec![build, design, unestimated];
+        let scheduled = schedule(&tasks, start).unwrap();
+
+        let names: Vec<&str> = scheduled.iter().map(|s| s.task.summary.as_str()).collect();
+        assert_eq!(names, vec!["design", "build", "unestimated"]);
+
+        assert_eq!(scheduled[0].start, start);
+        assert_eq!(scheduled[0].end, start + Duration::hours(2));
+        assert_eq!(scheduled[1].start, start + Duration::hours(2));
+        assert_eq!(scheduled[1].end, start + Duration::hours(5));
+        assert_eq!(scheduled[2].start, start);
+        assert_eq!(scheduled[2].end, start + DEFAULT_SCHEDULE_DURATION);
+    }
+
+    #[test]
+    fn test_overlaps() {
+        let start = Utc::now();
+        let design = task("design", Some(2), &[]);
+        let build = task("build", Some(3), &[&design]);
+        let unestimated = task("unestimated", None, &[]);
+
+        let tasks = vec![design, build, unestimated];
+        let scheduled = schedule(&tasks, start).unwrap();
+
+        // build waits for design, so only design and unestimated share time
+        let pairs: Vec<(&str, &str)> = overlaps(&scheduled)
+            .into_iter()
+            .map(|(a, b)| (a.task.summary.as_str(), b.task.summary.as_str()))
+            .collect();
+        assert_eq!(pairs, vec![("design", "unestimated")]);
+    }
+
+    #[test]
+    fn test_required_for() {
+        let mut done = task("done", None, &[]);
+        let below_done = task("below done", None, &[]);
+        done.dependencies = vec![below_done.uuid.clone()];
+        done.status = STATUS_RESOLVED.to_string();
+        let design = task("design", None, &[]);
+        let build = task("build", None, &[&design, &done]);
+        let docs = task("docs", None, &[&design]);
+        let goal = task("release", None, &[&build, &docs]);
+        let unrelated = task("unrelated", None, &[]);
+
+        let goal_uuid = goal.uuid.clone();
+        let tasks = vec![goal, unrelated, docs, build, design, done, below_done];
+
+        let required = required_for(&goal_uuid, &tasks);
+        assert_eq!(
+            summaries(&required),
+            vec!["design", "build", "docs", "release"]
+        );
+        assert!(required_for("missing", &tasks).is_empty());
+    }
+
+    #[test]
+    fn test_unblock_impact() {
+        let a = task("a", None, &[]);
+        let b = task("b", None, &[&a]);
+        let c = task("c", None, &[&a]);
+        let d = task("d", None, &[&b, &c]);
+        let e = task("e", None, &[&d]);
+        let mut done = task("done", None, &[&a]);
+        done.status = STATUS_RESOLVED.to_string();
+        let after_done = task("after done", None, &[&done]);
+        let unrelated = task("unrelated", None, &[]);
+
+        let uuids: Vec<String> = [&a, &b, &d, &e, &unrelated]
+            .iter()
+            .map(|t| t.uuid.clone())
+            .collect();
+        let tasks = vec![a, b, c, d, e, done, after_done, unrelated];
+
+        // b, c, d and e; d is reached twice but counted once
+        assert_eq!(unblock_impact(&uuids[0], &tasks), 4);
+        assert_eq!(unblock_impact(&uuids[1], &tasks), 2);
+        assert_eq!(unblock_impact(&uuids[2], &tasks), 1);
+        assert_eq!(unblock_impact(&uuids[3], &tasks), 0);
+        assert_eq!(unblock_impact(&uuids[4], &tasks), 0);
+        assert_eq!(unblock_impact("not-a-task", &tasks), 0);
+    }
+
+    #[test]
+    fn test_schedule_estimate_overflow() {
+        let mut a = task("a", None, &[]);
+        a.estimate = Some(Duration::days(99_999_999));
+        let b = task("b", Some(1), &[&a]);
+
+        assert!(matches!(
+            schedule(&[a, b], Utc::now()),
+            Err(RstaskError::Other(_))
+        ));
+    }
+
+    #[test]
+    fn test_schedule_cycle() {
+        let mut a = task("a", None, &[]);
+        let b = task("b", None, &[&a]);
+        a.dependencies.push(b.uuid.clone());
+
+        assert!(matches!(
+            schedule(&[a, b], Utc::now()),
+            Err(RstaskError::DependencyCycle(_))
+        ));
+    }
+
+    #[test]
+    fn test_scheduling_warnings() {
+        let now = Utc::now();
+        let due = |mut task: Task, days: i64| {
+            task.due = Some(now + Duration::days(days));
+            task
+        };
+
+        let foundations = due(task("foundations", None, &[]), 5);
+        let walls = due(task("walls", None, &[&foundations]), 2);
+        let survey = due(task("survey", None, &[]), 1);
+        let permit = due(task("permit", None, &[&survey]), 3);
+        let mut done = due(task("done", None, &[]), 9);
+        done.status = STATUS_RESOLVED.to_string();
+        let painting = due(task("painting", None, &[&done]), 4);
+
+        let tasks = vec![walls, foundations, permit, survey, painting, done];
+        let warnings = scheduling_warnings(&tasks);
+        let pairs: Vec<(&str, &str)> = warnings
+            .iter()
+            .map(|(task, dep)| (task.summary.as_str(), dep.summary.as_str()))
+            .collect();
+        assert_eq!(pairs, vec![("walls", "foundations")]);
+    }
+
+    fn tree_names(node: &TreeNode) -> String {
+        let name = node.task.map_or("?", |t| t.summary.as_str());
+        let mark = if node.revisited { "*" } else { "" };
+        if node.children.is_empty() {
+            format!("{}{}", name, mark)
+        } else {
+            let children: Vec<String> = node.children.iter().map(tree_names).collect();
+            format!("{}{}({})", name, mark, children.join(" "))
+        }
+    }
+
+    #[test]
+    fn test_dependency_tree_chain() {
+        let a = task("a", None, &[]);
+        let b = task("b", None, &[&a]);
+        let c = task("c", None, &[&b]);
+        let root = c.uuid.clone();
+
+        let tasks = vec![a, b, c];
+        let tree = dependency_tree(&root, &tasks);
+        assert_eq!(tree_names(&tree), "c(b(a))");
+
+        let unknown = dependency_tree("nope", &tasks);
+        assert!(unknown.task.is_none() && unknown.children.is_empty());
+    }
+
+    #[test]
+    fn test_dependency_tree_diamond_and_cycle() {
+        let a = task("a", None, &[]);
+        let left = task("left", None, &[&a]);
+        let right = task("right", None, &[&a]);
+        let top = task("top", None, &[&left, &right]);
+        let root = top.uuid.clone();
+
+        let tasks = vec![a, left, right, top];
+        let tree = dependency_tree(&root, &tasks);
+        assert_eq!(tree_names(&tree), "top(left(a) right(a*))");
+
+        let mut x = task("x", None, &[]);
+        let y = task("y", None, &[&x]);
+        x.dependencies.push(y.uuid.clone());
+        let root = x.uuid.clone();
+        let tasks = vec![x, y];
+        assert_eq!(tree_names(&dependency_tree(&root, &tasks)), "x(y(x*))");
+    }
+
+    #[test]
+    fn test_critical_path_empty() {
+        assert!(critical_path(&[]).unwrap().is_empty());
+    }
+}
@@ -249,7 +249,8 @@ Where [task summary] is text with tags/project/priority specified. Tags are
 specified with + (or - for filtering) eg: +work. The project is specified with
 a project:g prefix eg: project:rstask -- no quotes. Priorities run from P3
 (low), P2 (default) to P1 (high) and P0 (critical). Text can also be specified
-for a substring search of description and notes.
+for a substring search of description and notes. When filtering,
+priority:P1,P2 and status:pending,active match any of the listed values.
 
 Cmd and IDs can be swapped, multiple IDs can be specified for batch
 operations.
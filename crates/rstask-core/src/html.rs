@@ -0,0 +1,216 @@
+// HTML export - a single task as a standalone page for sharing
+use crate::date_util::format_iso8601_duration;
+use crate::task::Task;
+use chrono::{DateTime, Local, Utc};
+use pulldown_cmark::{CowStr, Event, Options, Parser, Tag};
+
+const STYLE: &str = "body { font-family: sans-serif; max-width: 48em; margin: 2em auto; padding: 0 1em; }
+table.metadata { border-collapse: collapse; margin-bottom: 2em; }
+table.metadata th, table.metadata td { border: 1px solid #ccc; padding: 0.3em 0.8em; text-align: left; }
+table.metadata th { background: #f4f4f4; }
+.tag { background: #e8eef8; border-radius: 0.3em; padding: 0 0.4em; margin-right: 0.3em; }";
+
+/// Escapes text for use in HTML content and attribute values
+fn escape_html(s: &str) -> String {
+    let mut escaped = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+fn format_date(date: DateTime<Utc>) -> String {
+    date.with_timezone(&Local)
+        .format("%A %-d %B %Y, %H:%M")
+        .to_string()
+}
+
+/// Whether a link or image URL is safe to keep: http, https and mailto
+/// URLs, or relative ones. Browsers ignore whitespace and control
+/// characters in a scheme, so those are dropped before looking for one.
+fn is_safe_url(url: &str) -> bool {
+    let url: String = url
+        .chars()
+        .filter(|c| !c.is_ascii_whitespace() && !c.is_control())
+        .collect();
+    match url.find([':', '/', '?', '#']) {
+        Some(i) if url[i..].starts_with(':') => {
+            let scheme = url[..i].to_ascii_lowercase();
+            matches!(scheme.as_str(), "http" | "https" | "mailto")
+        }
+        _ => true,
+    }
+}
+
+/// Replaces a link or image URL that isn't [safe](is_safe_url)
+fn sanitize_url(url: CowStr<'_>) -> CowStr<'_> {
+    if is_safe_url(&url) {
+        url
+    } else {
+        CowStr::Borrowed("#")
+    }
+}
+
+/// Renders markdown notes. Raw HTML in the notes is shown as text rather
+/// than passed through and links or images with other than http, https,
+/// mailto or relative URLs lose them, so a shared page can't carry markup
+/// or scripts of its own.
+fn render_notes(notes: &str) -> String {
+    let events =
+        Parser::new_ext(notes, Options::ENABLE_TABLES | Options::ENABLE_TASKLISTS).map(|event| {
+            match event {
+                Event::Html(html) | Event::InlineHtml(html) => Event::Text(html),
+                Event::Start(Tag::Link {
+                    link_type,
+                    dest_url,
+                    title,
+                    id,
+                }) => Event::Start(Tag::Link {
+                    link_type,
+                    dest_url: sanitize_url(dest_url),
+                    title,
+                    id,
+                }),
+                Event::Start(Tag::Image {
+                    link_type,
+                    dest_url,
+                    title,
+                    id,
+                }) => Event::Start(Tag::Image {
+                    link_type,
+                    dest_url: sanitize_url(dest_url),
+                    title,
+                    id,
+                }),
+                event => event,
+            }
+        });
+
+    let mut html = String::new();
+    pulldown_cmark::html::push_html(&mut html, events);
+    html
+}
+
+/// Renders a task as a standalone HTML page: the summary as the title, the
+/// frontmatter fields as a metadata table and the notes as markdown
+pub fn task_to_html(task: &Task) -> String {
+    let mut rows: Vec<(&str, String)> = vec![
+        ("Status", escape_html(&task.status)),
+        ("Priority", escape_html(&task.priority)),
+    ];
+
+    if !task.project.is_empty() {
+        rows.push(("Project", escape_html(&task.project)));
+    }
+    if !task.tags.is_empty() {
+        let tags: Vec<String> = task
+            .tags
+            .iter()
+            .map(|tag| format!("<span class=\"tag\">{}</span>", escape_html(tag)))
+            .collect();
+        rows.push(("Tags", tags.join("")));
+    }
+    if let Some(due) = task.due {
+        rows.push(("Due", format_date(due)));
+    }
+    if let Some(estimate) = task.estimate {
+        rows.push(("Estimate", format_iso8601_duration(estimate)));
+    }
+    if !task.delegated_to.is_empty() {
+        rows.push(("Delegated to", escape_html(&task.delegated_to.join(", "))));
+    }
+    if let Some(waiting_for) = &task.waiting_for {
+        rows.push(("Waiting for", escape_html(waiting_for)));
+    }
+    if !task.dependencies.is_empty() {
+        rows.push(("Depends on", escape_html(&task.dependencies.join(", "))));
+    }
+    rows.push(("Created", format_date(task.created)));
+    if let Some(resolved) = task.resolved {
+        rows.push(("Resolved", format_date(resolved)));
+    }
+    rows.push(("UUID", escape_html(&task.uuid)));
+
+    let summary = escape_html(&task.summary);
+    let mut html = String::new();
+    html.push_str("<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n");
+    html.push_str(&format!("<title>{}</title>\n", summary));
+    html.push_str(&format!("<style>\n{}\n</style>\n", STYLE));
+    html.push_str("</head>\n<body>\n");
+    html.push_str(&format!("<h1>{}</h1>\n", summary));
+
+    html.push_str("<table class=\"metadata\">\n");
+    for (name, value) in rows {
+        html.push_str(&format!("<tr><th>{}</th><td>{}</td></tr>\n", name, value));
+    }
+    html.push_str("</table>\n");
+
+    if !task.subtasks.is_empty() {
+        html.push_str("<ul class=\"subtasks\">\n");
+        for subtask in &task.subtasks {
+            let checked = if subtask.resolved { " checked" } else { "" };
+            html.push_str(&format!(
+                "<li><input type=\"checkbox\" disabled{}> {}</li>\n",
+                checked,
+                escape_html(&subtask.summary)
+            ));
+        }
+        html.push_str("</ul>\n");
+    }
+
+    if !task.notes.is_empty() {
+        html.push_str("<div class=\"notes\">\n");
+        html.push_str(&render_notes(&task.notes));
+        html.push_str("</div>\n");
+    }
+
+    html.push_str("</body>\n</html>\n");
+    html
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_task_to_html() {
+        let mut task = Task::new("Fix the <blink> tag".to_string());
+        task.tags = vec!["work".to_string(), "a&b".to_string()];
+        task.project = "website".to_string();
+        task.notes = "Some **bold** notes\n\n<script>alert(1)</script>".to_string();
+
+        let html = task_to_html(&task);
+        assert!(html.contains("<h1>Fix the &lt;blink&gt; tag</h1>"));
+        assert!(html.contains("<span class=\"tag\">work</span>"));
+        assert!(html.contains("<span class=\"tag\">a&amp;b</span>"));
+        assert!(html.contains("<td>website</td>"));
+        assert!(html.contains("<strong>bold</strong>"));
+        assert!(!html.contains("<script>"));
+    }
+
+    #[test]
+    fn test_render_notes_drops_unsafe_urls() {
+        let html = render_notes(
+            "[x](javascript:alert(1)) [y](JavaScript:alert(2)) [z](java%0ascript:alert(3))\n\n\
+             ![img](data:text/html;base64,PHNjcmlwdD4=) <javascript:alert(4)>",
+        );
+        assert_eq!(html.matches("href=\"#\"").count(), 4);
+        assert!(html.contains("<img src=\"#\""));
+        assert!(!html.contains("data:"));
+
+        let html = render_notes(
+            "[a](https://example.com) [b](mailto:me@example.com) [c](../notes.md) [d](#top)",
+        );
+        assert!(html.contains("href=\"https://example.com\""));
+        assert!(html.contains("href=\"mailto:me@example.com\""));
+        assert!(html.contains("href=\"../notes.md\""));
+        assert!(html.contains("href=\"#top\""));
+    }
+}
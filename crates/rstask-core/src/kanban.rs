@@ -0,0 +1,110 @@
+// Kanban export - tasks grouped into status columns for board UIs
+use crate::constants::*;
+use crate::task::Task;
+use serde_json::{Map, Value, json};
+use std::cmp::Ordering;
+
+/// Most urgent first: by priority, then by due date with undated tasks
+/// last, then oldest first
+fn by_urgency(a: &Task, b: &Task) -> Ordering {
+    let due = match (a.due, b.due) {
+        (Some(a), Some(b)) => a.cmp(&b),
+        (Some(_), None) => Ordering::Less,
+        (None, Some(_)) => Ordering::Greater,
+        (None, None) => Ordering::Equal,
+    };
+    a.priority
+        .cmp(&b.priority)
+        .then(due)
+        .then_with(|| a.created.cmp(&b.created))
+}
+
+/// Groups tasks into columns keyed by status, each an array of
+/// `{id, uuid, summary, priority}` ordered by urgency. Statuses without
+/// tasks get no column. Deleted tasks, templates and recurring tasks aren't
+/// work to move across a board, so they are left out.
+pub fn tasks_to_kanban_json(tasks: &[Task]) -> Value {
+    let mut columns = Map::new();
+    for status in ALL_STATUSES {
+        if *status == STATUS_TEMPLATE || *status == STATUS_RECURRING {
+            continue;
+        }
+
+        let mut column: Vec<&Task> = tasks
+            .iter()
+            .filter(|task| !task.deleted && task.status == *status)
+            .collect();
+        if column.is_empty() {
+            continue;
+        }
+        column.sort_by(|a, b| by_urgency(a, b));
+
+        let cards = column
+            .into_iter()
+            .map(|task| {
+                json!({
+                    "id": task.id,
+                    "uuid": task.uuid,
+                    "summary": task.summary,
+                    "priority": task.priority,
+                })
+            })
+            .collect();
+        columns.insert(status.to_string(), Value::Array(cards));
+    }
+
+    Value::Object(columns)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{Duration, TimeZone, Utc};
+
+    #[test]
+    fn test_tasks_to_kanban_json() {
+        let now = Utc.with_ymd_and_hms(2024, 6, 10, 12, 0, 0).unwrap();
+        let card = |id: i32, summary: &str, status: &str, priority: &str| {
+            let mut task = Task::new(summary.to_string());
+            task.id = id;
+            task.status = status.to_string();
+            task.priority = priority.to_string();
+            task
+        };
+
+        let mut due_soon = card(2, "due soon", STATUS_PENDING, PRIORITY_NORMAL);
+        due_soon.due = Some(now + Duration::days(1));
+        let mut due_later = card(3, "due later", STATUS_PENDING, PRIORITY_NORMAL);
+        due_later.due = Some(now + Duration::days(5));
+        let mut deleted = card(5, "deleted", STATUS_PENDING, PRIORITY_CRITICAL);
+        deleted.deleted = true;
+
+        let tasks = vec![
+            card(1, "undated", STATUS_PENDING, PRIORITY_NORMAL),
+            due_later,
+            card(4, "urgent", STATUS_PENDING, PRIORITY_HIGH),
+            due_soon,
+            deleted,
+            card(0, "shipped", STATUS_RESOLVED, PRIORITY_LOW),
+            card(0, "template", STATUS_TEMPLATE, PRIORITY_NORMAL),
+        ];
+
+        let board = tasks_to_kanban_json(&tasks);
+        let columns: Vec<&String> = board.as_object().unwrap().keys().collect();
+        assert_eq!(columns, vec![STATUS_PENDING, STATUS_RESOLVED]);
+
+        let pending: Vec<&str> = board[STATUS_PENDING]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|card| card["summary"].as_str().unwrap())
+            .collect();
+        assert_eq!(pending, vec!["urgent", "due soon", "due later", "undated"]);
+        assert_eq!(board[STATUS_PENDING][0]["id"], 4);
+        assert_eq!(board[STATUS_PENDING][0]["priority"], PRIORITY_HIGH);
+
+        assert_eq!(board[STATUS_RESOLVED].as_array().unwrap().len(), 1);
+        assert_eq!(board[STATUS_RESOLVED][0]["summary"], "shipped");
+        assert_eq!(board[STATUS_RESOLVED][0]["uuid"], tasks[5].uuid);
+    }
+}
@@ -1,23 +1,40 @@
+pub mod agenda;
+pub mod archive;
+pub mod canonical;
+pub mod clock;
 pub mod commands;
 pub mod config;
+pub mod crypto;
 pub mod constants;
 pub mod date_util;
+pub mod diff;
+pub mod digest;
 pub mod display;
 pub mod error;
 pub mod frontmatter;
 pub mod git;
+pub mod graph;
 pub mod help;
+pub mod html;
+pub mod kanban;
 pub mod local_state;
+pub mod lock;
+pub mod merge;
+pub mod notes_import;
 pub mod preferences;
 pub mod query;
+pub mod recur;
+pub mod stats;
 pub mod table;
 pub mod task;
 pub mod taskset;
+pub mod todotxt;
+pub mod uda;
 pub mod util;
 
 pub use config::Config;
 pub use error::{Result, RstaskError};
-pub use preferences::{BulkCommitStrategy, Preferences, SyncFrequency};
+pub use preferences::{BulkCommitStrategy, DuplicateIdPolicy, Preferences, SyncFrequency};
 pub use query::Query;
 pub use task::{SubTask, Task};
 pub use taskset::TaskSet;
@@ -1,12 +1,67 @@
 // Local state management for context and ID mapping
 use crate::Result;
 use crate::error::RstaskError;
+use crate::preferences::Preferences;
 use crate::query::Query;
+use chrono::{DateTime, Utc};
+use serde::Deserialize;
 use std::collections::HashMap;
 use std::path::{Path, PathBuf};
 
 pub type IdsMap = HashMap<String, i32>;
 
+/// A context as saved before queries gained `priorities` and `statuses`.
+/// Bincode relies on field order, so contexts saved then no longer decode
+/// as a [`Query`].
+#[derive(Deserialize)]
+#[cfg_attr(test, derive(serde::Serialize))]
+struct LegacyQuery {
+    cmd: String,
+    ids: Vec<i32>,
+    tags: Vec<String>,
+    anti_tags: Vec<String>,
+    project: String,
+    anti_projects: Vec<String>,
+    #[serde(with = "chrono::serde::ts_seconds_option")]
+    due: Option<DateTime<Utc>>,
+    date_filter: String,
+    priority: String,
+    template: i32,
+    text: String,
+    ignore_context: bool,
+    note: String,
+}
+
+impl From<LegacyQuery> for Query {
+    fn from(legacy: LegacyQuery) -> Self {
+        Query {
+            cmd: legacy.cmd,
+            ids: legacy.ids,
+            tags: legacy.tags,
+            anti_tags: legacy.anti_tags,
+            project: legacy.project,
+            anti_projects: legacy.anti_projects,
+            due: legacy.due,
+            date_filter: legacy.date_filter,
+            priority: legacy.priority,
+            template: legacy.template,
+            text: legacy.text,
+            ignore_context: legacy.ignore_context,
+            note: legacy.note,
+            ..Default::default()
+        }
+    }
+}
+
+/// Decodes a saved context, including ones saved by older versions
+fn decode_context(data: &[u8]) -> Option<Query> {
+    bincode::deserialize(data).ok().or_else(|| {
+        bincode::deserialize::<LegacyQuery>(data)
+            .ok()
+            .map(Query::from)
+    })
+}
+
 /// Local state including context
 #[derive(Debug, Clone)]
 pub struct LocalState {
@@ -17,11 +72,10 @@ pub struct LocalState {
 impl LocalState {
     /// Load state from file or create default
     pub fn load(state_file: &Path) -> Self {
-        let context = if let Ok(data) = std::fs::read(state_file) {
-            bincode::deserialize(&data).unwrap_or_default()
-        } else {
-            Query::default()
-        };
+        let context = std::fs::read(state_file)
+            .ok()
+            .and_then(|data| decode_context(&data))
+            .unwrap_or_default();
 
         LocalState {
             context,
@@ -50,6 +104,16 @@ impl LocalState {
         &self.context
     }
 
+    /// The context listings are filtered by. One set with `rstask context`
+    /// wins; without one, the view named by the configured `active_context`
+    /// applies.
+    pub fn effective_context(&self, preferences: &Preferences) -> Result<Query> {
+        if self.context != Query::default() {
+            return Ok(self.context.clone());
+        }
+        Ok(preferences.active_context_filter()?.unwrap_or_default())
+    }
+
     /// Save state to file
     pub fn save(&self) -> Result<()> {
         if let Some(parent) = self.state_file.parent() {
@@ -69,21 +133,26 @@ pub fn load_ids(ids_file: &Path) -> IdsMap {
     }
 }
 
+/// Saves the ID map. Commands that only read the store still write it while
+/// holding a shared lock, so the file is replaced in one step rather than
+/// rewritten in place where another reader could see it half written.
 pub fn save_ids(ids_file: &Path, ids: &IdsMap) -> Result<()> {
-    if let Some(parent) = ids_file.parent() {
-        std::fs::create_dir_all(parent)?;
-    }
+    use std::io::Write;
+
+    let parent = match ids_file.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent,
+        _ => Path::new("."),
+    };
+    std::fs::create_dir_all(parent)?;
     let data = bincode::serialize(ids)?;
-    std::fs::write(ids_file, data)?;
+    let mut tmp = tempfile::NamedTempFile::new_in(parent)?;
+    tmp.write_all(&data)?;
+    tmp.persist(ids_file).map_err(|e| e.error)?;
     Ok(())
 }
 
 pub fn load_state(state_file: &Path) -> Option<Query> {
-    if let Ok(data) = std::fs::read(state_file) {
-        bincode::deserialize(&data).ok()
-    } else {
-        None
-    }
+    decode_context(&std::fs::read(state_file).ok()?)
 }
 
 pub fn save_state(state_file: &Path, query: &Query) -> Result<()> {
@@ -94,3 +163,72 @@ pub fn save_state(state_file: &Path, query: &Query) -> Result<()> {
     std::fs::write(state_file, data)?;
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_decode_legacy_context() {
+        let legacy = LegacyQuery {
+            cmd: String::new(),
+            ids: Vec::new(),
+            tags: vec!["work".to_string()],
+            anti_tags: Vec::new(),
+            project: "office".to_string(),
+            anti_projects: Vec::new(),
+            due: None,
+            date_filter: String::new(),
+            priority: "P1".to_string(),
+            template: 0,
+            text: String::new(),
+            ignore_context: false,
+            note: String::new(),
+        };
+        let data = bincode::serialize(&legacy).unwrap();
+        let context = decode_context(&data).unwrap();
+        assert_eq!(context.tags, vec!["work"]);
+        assert_eq!(context.project, "office");
+        assert_eq!(context.priority, "P1");
+
+        let current = Query {
+            statuses: vec!["active".to_string()],
+            ..context.clone()
+        };
+        let data = bincode::serialize(&current).unwrap();
+        assert_eq!(decode_context(&data), Some(current));
+    }
+
+    #[test]
+    fn test_effective_context() {
+        let mut preferences = Preferences::default();
+        preferences
+            .views
+            .insert("work".to_string(), "+work project:office".to_string());
+        preferences.active_context = Some("work".to_string());
+
+        let dir = tempfile::TempDir::new().unwrap();
+        let mut state = LocalState::load(&dir.path().join("state.bin"));
+        let context = state.effective_context(&preferences).unwrap();
+        assert_eq!(context.tags, vec!["work"]);
+        assert_eq!(context.project, "office");
+
+        // A context set with `rstask context` takes precedence
+        state
+            .set_context(Query {
+                tags: vec!["home".to_string()],
+                ..Default::default()
+            })
+            .unwrap();
+        let context = state.effective_context(&preferences).unwrap();
+        assert_eq!(context.tags, vec!["home"]);
+        assert!(context.project.is_empty());
+
+        preferences.active_context = None;
+        state.set_context(Query::default()).unwrap();
+        assert_eq!(
+            state.effective_context(&preferences).unwrap(),
+            Query::default()
+        );
+    }
+}
@@ -0,0 +1,132 @@
+// Store locking - advisory lock against concurrent edits of a task store
+use crate::{Result, RstaskError};
+use std::fs::{File, TryLockError};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+/// How long [`TaskLock::acquire`] waits for another process to let go
+pub const DEFAULT_LOCK_TIMEOUT: Duration = Duration::from_secs(5);
+
+const RETRY_INTERVAL: Duration = Duration::from_millis(50);
+
+/// Advisory lock on a task store, held until dropped. Exclusive locks are
+/// for changing the store, shared ones for reading it, so listings can run
+/// side by side. The lock file lives next to the local state under
+/// `.git/rstask`, so it is never committed. Other processes only respect it
+/// if they take the lock too.
+#[derive(Debug)]
+pub struct TaskLock {
+    file: File,
+    path: PathBuf,
+}
+
+impl TaskLock {
+    /// Locks the store at `root`, waiting up to [`DEFAULT_LOCK_TIMEOUT`]
+    /// while another process holds it
+    pub fn acquire(root: &Path) -> Result<TaskLock> {
+        Self::acquire_timeout(root, DEFAULT_LOCK_TIMEOUT)
+    }
+
+    /// Locks the store at `root`, retrying until `timeout` has passed and
+    /// then failing with [`RstaskError::StoreLocked`]. A zero timeout tries
+    /// once.
+    pub fn acquire_timeout(root: &Path, timeout: Duration) -> Result<TaskLock> {
+        Self::acquire_with(root, timeout, false)
+    }
+
+    /// Takes a shared lock on the store at `root`, waiting up to
+    /// [`DEFAULT_LOCK_TIMEOUT`] while another process holds an exclusive one
+    pub fn acquire_shared(root: &Path) -> Result<TaskLock> {
+        Self::acquire_shared_timeout(root, DEFAULT_LOCK_TIMEOUT)
+    }
+
+    /// Same as [`TaskLock::acquire_shared`], waiting up to `timeout`
+    pub fn acquire_shared_timeout(root: &Path, timeout: Duration) -> Result<TaskLock> {
+        Self::acquire_with(root, timeout, true)
+    }
+
+    fn acquire_with(root: &Path, timeout: Duration, shared: bool) -> Result<TaskLock> {
+        let path = root.join(".git").join("rstask").join("lock");
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let file = File::options()
+            .create(true)
+            .truncate(false)
+            .write(true)
+            .open(&path)?;
+
+        let deadline = Instant::now() + timeout;
+        loop {
+            let attempt = if shared {
+                file.try_lock_shared()
+            } else {
+                file.try_lock()
+            };
+            match attempt {
+                Ok(()) => return Ok(TaskLock { file, path }),
+                Err(TryLockError::WouldBlock) if Instant::now() < deadline => {
+                    std::thread::sleep(RETRY_INTERVAL);
+                }
+                Err(TryLockError::WouldBlock) => {
+                    return Err(RstaskError::StoreLocked(root.display().to_string()));
+                }
+                Err(TryLockError::Error(e)) => return Err(e.into()),
+            }
+        }
+    }
+
+    /// Path of the lock file
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+}
+
+impl Drop for TaskLock {
+    fn drop(&mut self) {
+        // Closing the file releases the lock too, this just doesn't wait for it
+        let _ = self.file.unlock();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_second_acquire_fails_while_held() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let timeout = Duration::from_millis(100);
+
+        let lock = TaskLock::acquire_timeout(dir.path(), timeout).unwrap();
+        assert!(lock.path().exists());
+        assert!(matches!(
+            TaskLock::acquire_timeout(dir.path(), timeout),
+            Err(RstaskError::StoreLocked(_))
+        ));
+
+        drop(lock);
+        assert!(TaskLock::acquire_timeout(dir.path(), timeout).is_ok());
+    }
+
+    #[test]
+    fn test_shared_locks_only_exclude_exclusive_ones() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let timeout = Duration::from_millis(100);
+
+        let first = TaskLock::acquire_shared_timeout(dir.path(), timeout).unwrap();
+        let second = TaskLock::acquire_shared_timeout(dir.path(), timeout).unwrap();
+        assert!(matches!(
+            TaskLock::acquire_timeout(dir.path(), timeout),
+            Err(RstaskError::StoreLocked(_))
+        ));
+
+        drop((first, second));
+        let exclusive = TaskLock::acquire_timeout(dir.path(), timeout).unwrap();
+        assert!(matches!(
+            TaskLock::acquire_shared_timeout(dir.path(), timeout),
+            Err(RstaskError::StoreLocked(_))
+        ));
+        drop(exclusive);
+    }
+}
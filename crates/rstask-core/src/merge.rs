@@ -0,0 +1,122 @@
+// Store merging - combines task directories copied from different machines
+use crate::diff::FieldChange;
+use crate::task::Task;
+use std::collections::HashMap;
+
+/// Two versions of a task whose content differs
+#[derive(Debug, Clone)]
+pub struct Conflict {
+    pub uuid: String,
+    pub a: Task,
+    pub b: Task,
+    /// Fields that differ going from `a` to `b`
+    pub changes: Vec<FieldChange>,
+}
+
+/// Merges two versions of the same task. Versions with the same content
+/// merge into the more recently modified one. Versions whose content
+/// differs conflict even when one is newer: every edit stamps `modified`,
+/// and without a common ancestor there is no telling whether the newer
+/// version saw the other's edits, so picking it could silently drop them.
+pub fn merge_tasks(a: Task, b: Task) -> Result<Task, Box<Conflict>> {
+    if a.content_hash() == b.content_hash() {
+        return Ok(if b.modified > a.modified { b } else { a });
+    }
+
+    Err(Box::new(Conflict {
+        uuid: a.uuid.clone(),
+        changes: a.diff(&b),
+        a,
+        b,
+    }))
+}
+
+/// Merges two stores by UUID. Tasks found in only one pass through and
+/// tasks in both are combined with [`merge_tasks`]. Conflicting tasks are
+/// left out of the merged store, to be resolved from the returned
+/// conflicts. Without a common ancestor a task edited in only one store
+/// can't be told apart from one edited in both, so one-sided edits are
+/// reported as conflicts too. Tasks keep the order of `a`, followed by
+/// those only in `b`.
+pub fn merge_stores(a: Vec<Task>, b: Vec<Task>) -> (Vec<Task>, Vec<Conflict>) {
+    let mut only_b: Vec<Option<Task>> = b.into_iter().map(Some).collect();
+    let index: HashMap<String, usize> = only_b
+        .iter()
+        .enumerate()
+        .filter_map(|(i, task)| Some((task.as_ref()?.uuid.clone(), i)))
+        .collect();
+
+    let mut merged = Vec::new();
+    let mut conflicts = Vec::new();
+    for task in a {
+        let other = index.get(&task.uuid).and_then(|&i| only_b[i].take());
+        match other {
+            None => merged.push(task),
+            Some(other) => match merge_tasks(task, other) {
+                Ok(task) => merged.push(task),
+                Err(conflict) => conflicts.push(*conflict),
+            },
+        }
+    }
+    merged.extend(only_b.into_iter().flatten());
+
+    (merged, conflicts)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{Duration, TimeZone, Utc};
+
+    #[test]
+    fn test_merge_stores() {
+        let earlier = Utc.with_ymd_and_hms(2024, 6, 1, 9, 0, 0).unwrap();
+        let mut shared = Task::new("shared".to_string());
+        shared.modified = Some(earlier);
+        let mut saved_again = shared.clone();
+        saved_again.modified = Some(earlier + Duration::hours(2));
+
+        let only_a = Task::new("only in a".to_string());
+        let only_b = Task::new("only in b".to_string());
+
+        let (merged, conflicts) = merge_stores(
+            vec![only_a.clone(), shared.clone()],
+            vec![saved_again.clone(), only_b.clone()],
+        );
+        assert!(conflicts.is_empty());
+        let summaries: Vec<&str> = merged.iter().map(|t| t.summary.as_str()).collect();
+        assert_eq!(summaries, vec!["only in a", "shared", "only in b"]);
+        assert_eq!(merged[1].modified, saved_again.modified);
+    }
+
+    #[test]
+    fn test_differing_content_conflicts_despite_newer_stamp() {
+        let earlier = Utc.with_ymd_and_hms(2024, 6, 1, 9, 0, 0).unwrap();
+        let mut desktop = Task::new("shared".to_string());
+        desktop.project = "home".to_string();
+        desktop.modified = Some(earlier);
+        let mut laptop = desktop.clone();
+        laptop.project = String::new();
+        laptop.summary = "shared, edited on the laptop".to_string();
+        laptop.modified = Some(earlier + Duration::hours(2));
+
+        let (merged, conflicts) = merge_stores(vec![desktop.clone()], vec![laptop]);
+        assert!(merged.is_empty());
+        assert_eq!(conflicts.len(), 1);
+        assert_eq!(conflicts[0].uuid, desktop.uuid);
+        let fields: Vec<&str> = conflicts[0]
+            .changes
+            .iter()
+            .map(|c| c.field.as_str())
+            .collect();
+        assert!(fields.contains(&"summary"));
+        assert!(fields.contains(&"project"));
+
+        // Same for versions without stamps
+        let mut unstamped = desktop.clone();
+        unstamped.modified = None;
+        let mut other = unstamped.clone();
+        other.summary = "edited".to_string();
+        assert!(merge_tasks(unstamped, other).is_err());
+    }
+}
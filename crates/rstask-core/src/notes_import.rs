@@ -0,0 +1,80 @@
+// Notes import - turns a directory of plain markdown notes into tasks
+use crate::Result;
+use crate::task::Task;
+use std::path::Path;
+
+/// Splits a note into its first `# heading` and the text around it
+fn split_heading(content: &str) -> Option<(String, String)> {
+    let mut lines = content.lines();
+    let mut before = Vec::new();
+    for line in lines.by_ref() {
+        if let Some(heading) = line.strip_prefix("# ") {
+            let rest: Vec<&str> = before.into_iter().chain(lines).collect();
+            return Some((
+                heading.trim().to_string(),
+                rest.join("\n").trim().to_string(),
+            ));
+        }
+        before.push(line);
+    }
+    None
+}
+
+/// Reads each `.md` file directly in `path` as a new pending task, with the
+/// first `# heading` as its summary and the rest of the file as its notes.
+/// Notes without a heading are summarised by their file name. Tasks are
+/// returned in file name order.
+pub fn tasks_from_notes_dir(path: &Path) -> Result<Vec<Task>> {
+    let mut files = Vec::new();
+    for entry in std::fs::read_dir(path)? {
+        let file = entry?.path();
+        if file.is_file() && file.extension().is_some_and(|ext| ext == "md") {
+            files.push(file);
+        }
+    }
+    files.sort();
+
+    let mut tasks = Vec::new();
+    for file in files {
+        let content = std::fs::read_to_string(&file)?;
+        let (summary, notes) = split_heading(&content).unwrap_or_else(|| {
+            let name = file
+                .file_stem()
+                .map(|stem| stem.to_string_lossy().into_owned())
+                .unwrap_or_default();
+            (name, content.trim().to_string())
+        });
+
+        let mut task = Task::new(summary);
+        task.notes = notes;
+        tasks.push(task);
+    }
+
+    Ok(tasks)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tasks_from_notes_dir() {
+        let dir = tempfile::TempDir::new().unwrap();
+        std::fs::write(
+            dir.path().join("a.md"),
+            "# Renew passport\n\nForms are in the drawer.\n",
+        )
+        .unwrap();
+        std::fs::write(dir.path().join("call-plumber.md"), "Kitchen sink leaks\n").unwrap();
+        std::fs::write(dir.path().join("ignored.txt"), "# Not a note\n").unwrap();
+
+        let tasks = tasks_from_notes_dir(dir.path()).unwrap();
+        assert_eq!(tasks.len(), 2);
+
+        assert_eq!(tasks[0].summary, "Renew passport");
+        assert_eq!(tasks[0].notes, "Forms are in the drawer.");
+        assert_eq!(tasks[1].summary, "call-plumber");
+        assert_eq!(tasks[1].notes, "Kitchen sink leaks");
+        assert_ne!(tasks[0].uuid, tasks[1].uuid);
+    }
+}
@@ -1,8 +1,20 @@
-use serde::Deserialize;
+use crate::constants::*;
+use crate::crypto::NoteKey;
+use crate::query::{Query, parse_query};
+use crate::task::Task;
+use crate::uda::UdaType;
+use chrono_tz::Tz;
+#[cfg(test)]
+use serde::Serialize;
+use serde::de::IgnoredAny;
+use serde::{Deserialize, Deserializer};
+use std::collections::HashMap;
+use std::fmt;
 use std::fs;
 use std::path::PathBuf;
 
 #[derive(Debug, Deserialize, Clone, Copy, PartialEq)]
+#[cfg_attr(test, derive(Serialize))]
 #[serde(rename_all = "snake_case")]
 pub enum SyncFrequency {
     Never,
@@ -17,6 +29,7 @@ impl Default for SyncFrequency {
 }
 
 #[derive(Debug, Deserialize, Clone, Copy, PartialEq)]
+#[cfg_attr(test, derive(Serialize))]
 #[serde(rename_all = "snake_case")]
 pub enum BulkCommitStrategy {
     Single,
@@ -30,12 +43,314 @@ impl Default for BulkCommitStrategy {
     }
 }
 
+/// What to do when two tasks claim the same numeric ID while loading
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq)]
+#[cfg_attr(test, derive(Serialize))]
+#[serde(rename_all = "snake_case")]
+pub enum DuplicateIdPolicy {
+    /// Refuse to load the task set
+    Error,
+    /// Keep the ID on the first task and give the others a fresh one
+    Renumber,
+    /// Keep the duplicated IDs, lookups resolve to the first task loaded
+    Ignore,
+}
+
+#[allow(clippy::derivable_impls)]
+impl Default for DuplicateIdPolicy {
+    fn default() -> Self {
+        DuplicateIdPolicy::Renumber
+    }
+}
+
+/// How estimates are written to task files
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(test, derive(Serialize))]
+#[serde(rename_all = "snake_case")]
+pub enum EstimateFormat {
+    /// ISO-8601, e.g. "PT1H30M"
+    Iso8601,
+    /// Shorthand, e.g. "1h30m"
+    Shorthand,
+}
+
+/// Defaults for new tasks in one project, replacing the global ones they set
+#[derive(Debug, Deserialize, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(test, derive(Serialize))]
+pub struct ProjectDefaults {
+    /// Tags added instead of `default_tags`; an empty list adds none
+    #[serde(default)]
+    pub tags: Option<Vec<String>>,
+    /// Priority for tasks created without one
+    #[serde(default)]
+    pub priority: Option<String>,
+}
+
+#[allow(clippy::derivable_impls)]
+impl Default for EstimateFormat {
+    fn default() -> Self {
+        EstimateFormat::Iso8601
+    }
+}
+
 #[derive(Debug, Deserialize, Clone)]
+#[cfg_attr(test, derive(Serialize))]
 pub struct Preferences {
     #[serde(default)]
     pub sync_frequency: SyncFrequency,
     #[serde(default)]
     pub bulk_commit_strategy: BulkCommitStrategy,
+    #[serde(default)]
+    pub on_duplicate_id: DuplicateIdPolicy,
+    /// Emit tags and dependencies in sorted order when writing task files
+    #[serde(default)]
+    pub sort_tags_on_write: bool,
+    /// IANA timezone name used to display dates, e.g. "Europe/Paris"
+    #[serde(default)]
+    pub timezone: Option<String>,
+    /// Encrypt task notes when writing them, using the key from
+    /// RSTASK_NOTES_PASSPHRASE
+    #[serde(default)]
+    pub encrypt_notes: bool,
+    /// Key for encrypted notes, taken from the environment rather than the config
+    #[serde(skip)]
+    pub notes_key: Option<NoteKey>,
+    /// Extra typed fields tasks may carry in their frontmatter
+    #[serde(default)]
+    pub user_fields: HashMap<String, UdaType>,
+    /// Line opening and closing the frontmatter of task files
+    #[serde(default = "default_frontmatter_delimiter")]
+    pub frontmatter_delimiter: String,
+    /// Tags added to every new task
+    #[serde(default)]
+    pub default_tags: Vec<String>,
+    /// Append an HTML comment carrying the task's UUID to task files, for
+    /// editors that hide frontmatter
+    #[serde(default)]
+    pub emit_footer: bool,
+    /// Tag prefix marking a context, e.g. "@home"
+    #[serde(default = "default_context_prefix")]
+    pub context_prefix: String,
+    /// Tag prefix marking an area of responsibility, e.g. "#finance"
+    #[serde(default = "default_area_prefix")]
+    pub area_prefix: String,
+    /// Numbers exported for each priority, overriding
+    /// [`DEFAULT_PRIORITY_NUMERIC_MAP`] for the priorities listed
+    #[serde(default)]
+    pub priority_numeric_map: HashMap<String, u8>,
+    /// Accept YAML anchors and aliases in task files, which are rejected by
+    /// default
+    #[serde(default)]
+    pub allow_yaml_anchors: bool,
+    /// Format estimates are written in; both are always accepted when reading
+    #[serde(default)]
+    pub estimate_format: EstimateFormat,
+    /// Directory names for statuses in the repository, e.g. `{resolved done}`.
+    /// Statuses left out use their own name.
+    #[serde(default)]
+    pub status_dirs: HashMap<String, String>,
+    /// Notes longer than this many bytes are moved to a file under `notes/`
+    /// when saving, referenced by `notes_file`. Zero keeps all notes inline.
+    #[serde(default)]
+    pub notes_file_threshold: usize,
+    /// Named filters, e.g. `{work "+work project:office"}`, written in the
+    /// same syntax as on the command line
+    #[serde(default)]
+    pub views: HashMap<String, String>,
+    /// Lowest ID handed out to tasks
+    #[serde(default = "default_id_base")]
+    pub id_base: i32,
+    /// Store task files in subdirectories named after the first two
+    /// characters of their UUID, e.g. `pending/3f/3f2a….md`. Both layouts
+    /// are always read.
+    #[serde(default)]
+    pub shard_uuids: bool,
+    /// Priorities suggested for tags, e.g. `((urgent P1) (outage P0))`
+    #[serde(default, deserialize_with = "deserialize_pairs")]
+    pub priority_rules: Vec<(String, String)>,
+    /// Defaults for new tasks by project, e.g.
+    /// `{work {priority P1, tags (office)}}`
+    #[serde(default)]
+    pub project_defaults: HashMap<String, ProjectDefaults>,
+    /// Largest frontmatter read from a task file, in bytes, so a huge or
+    /// malicious file can't exhaust memory while being parsed
+    #[serde(default = "default_max_frontmatter_bytes")]
+    pub max_frontmatter_bytes: usize,
+    /// Name of the view whose filter applies to every listing until it is
+    /// switched or unset. A context set with `rstask context` takes its place.
+    #[serde(default)]
+    pub active_context: Option<String>,
+    /// Frontmatter fields every open task must have, e.g. `(project due)`
+    #[serde(default)]
+    pub required_fields: Vec<String>,
+    /// Reject task files with frontmatter keys that are neither known nor
+    /// declared in `user_fields`, rather than keeping them
+    #[serde(default)]
+    pub strict_frontmatter: bool,
+}
+
+pub const DEFAULT_FRONTMATTER_DELIMITER: &str = "---";
+
+/// Numeric priorities on the iCalendar scale, where 1 is the highest and 9
+/// the lowest
+pub const DEFAULT_PRIORITY_NUMERIC_MAP: &[(&str, u8)] = &[
+    (PRIORITY_CRITICAL, 1),
+    (PRIORITY_HIGH, 3),
+    (PRIORITY_NORMAL, 5),
+    (PRIORITY_LOW, 9),
+];
+
+/// Maps a priority to a number for tools that expect one, using the
+/// configured mapping first and the default scale otherwise. Unknown
+/// priorities map to None.
+pub fn priority_to_numeric(priority: &str, prefs: &Preferences) -> Option<u8> {
+    prefs
+        .priority_numeric_map
+        .get(priority)
+        .copied()
+        .or_else(|| {
+            DEFAULT_PRIORITY_NUMERIC_MAP
+                .iter()
+                .find(|(name, _)| *name == priority)
+                .map(|(_, value)| *value)
+        })
+}
+
+fn default_frontmatter_delimiter() -> String {
+    DEFAULT_FRONTMATTER_DELIMITER.to_string()
+}
+
+fn default_context_prefix() -> String {
+    "@".to_string()
+}
+
+fn default_area_prefix() -> String {
+    "#".to_string()
+}
+
+/// Reads `((a b) (c d))` as pairs; styx has no tuples of its own, so each
+/// pair is a two-item sequence
+fn deserialize_pairs<'de, D>(deserializer: D) -> Result<Vec<(String, String)>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    Vec::<Vec<String>>::deserialize(deserializer)?
+        .into_iter()
+        .map(|pair| match <[String; 2]>::try_from(pair) {
+            Ok([first, second]) => Ok((first, second)),
+            Err(pair) => Err(serde::de::Error::invalid_length(
+                pair.len(),
+                &"a pair of values",
+            )),
+        })
+        .collect()
+}
+
+/// Far beyond any hand-written task, which rarely reaches a kilobyte
+pub const DEFAULT_MAX_FRONTMATTER_BYTES: usize = 1024 * 1024;
+
+fn default_max_frontmatter_bytes() -> usize {
+    DEFAULT_MAX_FRONTMATTER_BYTES
+}
+
+fn default_id_base() -> i32 {
+    1
+}
+
+/// Top-level keys understood in the config file. Keep in sync with the
+/// fields of [`Preferences`], which `test_known_config_keys` checks.
+pub const KNOWN_CONFIG_KEYS: &[&str] = &[
+    "sync_frequency",
+    "bulk_commit_strategy",
+    "on_duplicate_id",
+    "sort_tags_on_write",
+    "timezone",
+    "encrypt_notes",
+    "user_fields",
+    "frontmatter_delimiter",
+    "default_tags",
+    "emit_footer",
+    "context_prefix",
+    "area_prefix",
+    "priority_numeric_map",
+    "allow_yaml_anchors",
+    "estimate_format",
+    "status_dirs",
+    "notes_file_threshold",
+    "views",
+    "id_base",
+    "shard_uuids",
+    "priority_rules",
+    "project_defaults",
+    "max_frontmatter_bytes",
+    "active_context",
+    "required_fields",
+    "strict_frontmatter",
+];
+
+/// A problem found by [`Preferences::validate_config`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ConfigWarning {
+    /// A key serde would silently ignore, with the known key it most
+    /// likely meant when one is close enough
+    UnknownKey {
+        key: String,
+        suggestion: Option<&'static str>,
+    },
+    /// The config doesn't parse, so the defaults are used instead
+    Invalid(String),
+}
+
+impl fmt::Display for ConfigWarning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConfigWarning::UnknownKey {
+                key,
+                suggestion: Some(suggestion),
+            } => write!(
+                f,
+                "unknown config key '{}', did you mean '{}'?",
+                key, suggestion
+            ),
+            ConfigWarning::UnknownKey { key, .. } => write!(f, "unknown config key '{}'", key),
+            ConfigWarning::Invalid(e) => {
+                write!(f, "config could not be read, using defaults: {}", e)
+            }
+        }
+    }
+}
+
+/// Levenshtein distance between two keys
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut row: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut diagonal = row[0];
+        row[0] = i + 1;
+        for (j, cb) in b.iter().enumerate() {
+            let substitution = diagonal + usize::from(ca != *cb);
+            diagonal = row[j + 1];
+            row[j + 1] = substitution.min(row[j] + 1).min(diagonal + 1);
+        }
+    }
+    row[b.len()]
+}
+
+/// Suggests a priority from the configured priority rules: the highest one
+/// among the rules whose tag the task carries, or None if no rule matches.
+/// Tags are compared ignoring case and rules naming unknown priorities are
+/// skipped.
+pub fn suggest_priority(task: &Task, prefs: &Preferences) -> Option<String> {
+    prefs
+        .priority_rules
+        .iter()
+        .filter(|(tag, priority)| {
+            is_valid_priority(priority) && task.tags.iter().any(|t| t.eq_ignore_ascii_case(tag))
+        })
+        // P0 is the highest priority and sorts first
+        .map(|(_, priority)| priority)
+        .min()
+        .cloned()
 }
 
 impl Default for Preferences {
@@ -43,6 +358,31 @@ impl Default for Preferences {
         Preferences {
             sync_frequency: SyncFrequency::Never,
             bulk_commit_strategy: BulkCommitStrategy::PerTask,
+            on_duplicate_id: DuplicateIdPolicy::Renumber,
+            sort_tags_on_write: false,
+            timezone: None,
+            encrypt_notes: false,
+            notes_key: None,
+            user_fields: HashMap::new(),
+            frontmatter_delimiter: default_frontmatter_delimiter(),
+            default_tags: Vec::new(),
+            emit_footer: false,
+            context_prefix: default_context_prefix(),
+            area_prefix: default_area_prefix(),
+            priority_numeric_map: HashMap::new(),
+            allow_yaml_anchors: false,
+            estimate_format: EstimateFormat::Iso8601,
+            status_dirs: HashMap::new(),
+            notes_file_threshold: 0,
+            views: HashMap::new(),
+            id_base: default_id_base(),
+            shard_uuids: false,
+            priority_rules: Vec::new(),
+            project_defaults: HashMap::new(),
+            max_frontmatter_bytes: default_max_frontmatter_bytes(),
+            active_context: None,
+            required_fields: Vec::new(),
+            strict_frontmatter: false,
         }
     }
 }
@@ -52,6 +392,103 @@ impl Preferences {
         dirs::config_dir().map(|config_dir| config_dir.join("rstask").join("config.styx"))
     }
 
+    /// Returns the frontmatter delimiter, falling back to `---` if the
+    /// configured one is blank
+    pub fn frontmatter_delimiter(&self) -> &str {
+        let delimiter = self.frontmatter_delimiter.trim();
+        if delimiter.is_empty() {
+            DEFAULT_FRONTMATTER_DELIMITER
+        } else {
+            delimiter
+        }
+    }
+
+    /// Returns the directory tasks with `status` are stored in
+    pub fn status_dir<'a>(&'a self, status: &'a str) -> &'a str {
+        self.status_dirs
+            .get(status)
+            .map(String::as_str)
+            .unwrap_or(status)
+    }
+
+    /// Returns the status stored in directory `dir`, the reverse of
+    /// [`Preferences::status_dir`]
+    pub fn status_from_dir(&self, dir: &str) -> Option<&'static str> {
+        ALL_STATUSES
+            .iter()
+            .copied()
+            .find(|status| self.status_dir(status) == dir)
+    }
+
+    /// Parses the filter saved as view `name`. Returns None if there is no
+    /// such view, and an error if its filter doesn't parse.
+    pub fn view_filter(&self, name: &str) -> crate::Result<Option<Query>> {
+        let Some(filter) = self.views.get(name) else {
+            return Ok(None);
+        };
+        let args: Vec<String> = filter.split_whitespace().map(str::to_string).collect();
+        parse_query(&args)
+            .map(Some)
+            .map_err(|e| crate::RstaskError::Parse(format!("view {}: {}", name, e)))
+    }
+
+    /// Parses the filter of the view named by `active_context`. Returns None
+    /// if no context is active, and an error if it names no view or one
+    /// whose filter doesn't parse.
+    pub fn active_context_filter(&self) -> crate::Result<Option<Query>> {
+        let Some(name) = self.active_context.as_deref() else {
+            return Ok(None);
+        };
+        match self.view_filter(name)? {
+            Some(filter) => Ok(Some(filter)),
+            None => Err(crate::RstaskError::Parse(format!(
+                "active_context names no view: {}",
+                name
+            ))),
+        }
+    }
+
+    /// Returns the configured timezone, or UTC if none (or an unknown one) is set
+    pub fn tz(&self) -> Tz {
+        self.timezone
+            .as_deref()
+            .and_then(|name| name.parse().ok())
+            .unwrap_or(Tz::UTC)
+    }
+
+    /// Checks raw config content for keys that would be silently ignored,
+    /// such as typos, and for content that doesn't parse at all
+    pub fn validate_config(content: &str) -> Vec<ConfigWarning> {
+        let keys: HashMap<String, IgnoredAny> = match serde_styx::from_str(content) {
+            Ok(keys) => keys,
+            Err(e) => return vec![ConfigWarning::Invalid(e.to_string())],
+        };
+
+        let mut unknown: Vec<String> = keys
+            .into_keys()
+            .filter(|key| !KNOWN_CONFIG_KEYS.contains(&key.as_str()))
+            .collect();
+        unknown.sort();
+
+        let mut warnings: Vec<ConfigWarning> = unknown
+            .into_iter()
+            .map(|key| {
+                let suggestion = KNOWN_CONFIG_KEYS
+                    .iter()
+                    .map(|known| (edit_distance(&key, known), *known))
+                    .filter(|(distance, _)| *distance <= 2)
+                    .min()
+                    .map(|(_, known)| known);
+                ConfigWarning::UnknownKey { key, suggestion }
+            })
+            .collect();
+
+        if let Err(e) = serde_styx::from_str::<Preferences>(content) {
+            warnings.push(ConfigWarning::Invalid(e.to_string()));
+        }
+        warnings
+    }
+
     /// Load preferences from config file, or return default if file doesn't exist
     pub fn load() -> Self {
         let config_path = match Self::config_path() {
@@ -64,6 +501,201 @@ impl Preferences {
             Err(_) => return Self::default(),
         };
 
-        serde_styx::from_str(&config_content).unwrap_or_default()
+        for warning in Self::validate_config(&config_content) {
+            eprintln!("Warning: {}", warning);
+        }
+        let mut preferences: Self = serde_styx::from_str(&config_content).unwrap_or_default();
+        preferences.notes_key = match NoteKey::from_env() {
+            Ok(key) => key,
+            Err(e) => {
+                eprintln!("Warning: could not derive the notes key: {}", e);
+                None
+            }
+        };
+        preferences
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::date_util::format_relative_due;
+    use chrono::{TimeZone, Utc};
+
+    #[test]
+    fn test_tz() {
+        assert_eq!(Preferences::default().tz(), Tz::UTC);
+
+        let prefs: Preferences = serde_styx::from_str("timezone Asia/Tokyo").unwrap();
+        assert_eq!(prefs.tz(), Tz::Asia__Tokyo);
+
+        let prefs = Preferences {
+            timezone: Some("Not/AZone".to_string()),
+            ..Default::default()
+        };
+        assert_eq!(prefs.tz(), Tz::UTC);
+    }
+
+    #[test]
+    fn test_priority_to_numeric_default() {
+        let prefs = Preferences::default();
+        assert_eq!(priority_to_numeric(PRIORITY_CRITICAL, &prefs), Some(1));
+        assert_eq!(priority_to_numeric(PRIORITY_HIGH, &prefs), Some(3));
+        assert_eq!(priority_to_numeric(PRIORITY_NORMAL, &prefs), Some(5));
+        assert_eq!(priority_to_numeric(PRIORITY_LOW, &prefs), Some(9));
+        assert_eq!(priority_to_numeric("P7", &prefs), None);
+    }
+
+    #[test]
+    fn test_status_dirs() {
+        let prefs = Preferences::default();
+        assert_eq!(prefs.status_dir(STATUS_RESOLVED), "resolved");
+        assert_eq!(prefs.status_from_dir("resolved"), Some(STATUS_RESOLVED));
+        assert_eq!(prefs.status_from_dir("done"), None);
+
+        let prefs: Preferences = serde_styx::from_str("status_dirs {resolved done}").unwrap();
+        assert_eq!(prefs.status_dir(STATUS_RESOLVED), "done");
+        assert_eq!(prefs.status_dir(STATUS_PENDING), "pending");
+        assert_eq!(prefs.status_from_dir("done"), Some(STATUS_RESOLVED));
+        assert_eq!(prefs.status_from_dir("resolved"), None);
+    }
+
+    #[test]
+    fn test_view_filter() {
+        let prefs: Preferences = serde_styx::from_str(
+            r#"views {work "+work project:office -meeting", urgent "+\"on fire\" P0"}"#,
+        )
+        .unwrap();
+
+        let work = prefs.view_filter("work").unwrap().unwrap();
+        assert_eq!(work.tags, vec!["work"]);
+        assert_eq!(work.anti_tags, vec!["meeting"]);
+        assert_eq!(work.project, "office");
+
+        let urgent = prefs.view_filter("urgent").unwrap().unwrap();
+        assert_eq!(urgent.tags, vec!["on fire"]);
+        assert_eq!(urgent.priority, PRIORITY_CRITICAL);
+
+        assert!(prefs.view_filter("missing").unwrap().is_none());
+
+        // A filter that doesn't parse isn't mistaken for a missing view
+        let prefs: Preferences = serde_styx::from_str(r#"views {broken "due:whenever"}"#).unwrap();
+        let err = prefs.view_filter("broken").unwrap_err();
+        assert!(err.to_string().contains("view broken"), "{}", err);
+    }
+
+    #[test]
+    fn test_active_context_filter() {
+        let mut prefs: Preferences =
+            serde_styx::from_str(r#"views {work "+work project:office"}, active_context work"#)
+                .unwrap();
+        assert_eq!(prefs.active_context.as_deref(), Some("work"));
+
+        let filter = prefs.active_context_filter().unwrap().unwrap();
+        assert_eq!(filter.tags, vec!["work"]);
+        assert_eq!(filter.project, "office");
+
+        prefs.active_context = None;
+        assert!(prefs.active_context_filter().unwrap().is_none());
+
+        prefs.active_context = Some("missing".to_string());
+        assert!(prefs.active_context_filter().is_err());
+    }
+
+    #[test]
+    fn test_known_config_keys() {
+        let value = serde_json::to_value(Preferences::default()).unwrap();
+        let keys: Vec<&String> = value.as_object().unwrap().keys().collect();
+        for key in &keys {
+            assert!(
+                KNOWN_CONFIG_KEYS.contains(&key.as_str()),
+                "{} is missing from KNOWN_CONFIG_KEYS",
+                key
+            );
+        }
+        assert_eq!(keys.len(), KNOWN_CONFIG_KEYS.len());
+    }
+
+    #[test]
+    fn test_validate_config() {
+        assert!(Preferences::validate_config("timezone Asia/Tokyo\nid_base 10").is_empty());
+
+        let warnings = Preferences::validate_config("timzone Asia/Tokyo\ncolour_scheme dark");
+        assert_eq!(
+            warnings,
+            vec![
+                ConfigWarning::UnknownKey {
+                    key: "colour_scheme".to_string(),
+                    suggestion: None,
+                },
+                ConfigWarning::UnknownKey {
+                    key: "timzone".to_string(),
+                    suggestion: Some("timezone"),
+                },
+            ]
+        );
+        assert_eq!(
+            warnings[1].to_string(),
+            "unknown config key 'timzone', did you mean 'timezone'?"
+        );
+
+        let warnings = Preferences::validate_config("id_base lots");
+        assert!(matches!(warnings[..], [ConfigWarning::Invalid(_)]));
+    }
+
+    #[test]
+    fn test_suggest_priority() {
+        let prefs: Preferences =
+            serde_styx::from_str("priority_rules ((urgent P1) (outage P0) (someday P3) (odd P9))")
+                .unwrap();
+
+        let mut task = Task::new("Servers down".to_string());
+        assert_eq!(suggest_priority(&task, &prefs), None);
+
+        task.tags = vec!["Urgent".to_string(), "outage".to_string()];
+        assert_eq!(
+            suggest_priority(&task, &prefs).as_deref(),
+            Some(PRIORITY_CRITICAL)
+        );
+
+        assert!(serde_styx::from_str::<Preferences>("priority_rules ((urgent))").is_err());
+
+        task.tags = vec!["someday".to_string(), "odd".to_string()];
+        assert_eq!(
+            suggest_priority(&task, &prefs).as_deref(),
+            Some(PRIORITY_LOW)
+        );
+    }
+
+    #[test]
+    fn test_priority_to_numeric_custom() {
+        let prefs: Preferences = serde_styx::from_str("priority_numeric_map {P0 4, P1 3}").unwrap();
+        assert_eq!(priority_to_numeric(PRIORITY_CRITICAL, &prefs), Some(4));
+        assert_eq!(priority_to_numeric(PRIORITY_HIGH, &prefs), Some(3));
+        // Priorities left out of the map keep their default
+        assert_eq!(priority_to_numeric(PRIORITY_LOW, &prefs), Some(9));
+    }
+
+    #[test]
+    fn test_default_tags_from_config() {
+        let prefs: Preferences = serde_styx::from_str("default_tags (inbox later)").unwrap();
+        assert_eq!(prefs.default_tags, vec!["inbox", "later"]);
+        assert!(Preferences::default().default_tags.is_empty());
+    }
+
+    #[test]
+    fn test_tz_display() {
+        let prefs = Preferences {
+            timezone: Some("Asia/Tokyo".to_string()),
+            ..Default::default()
+        };
+        let now = Utc.with_ymd_and_hms(2024, 6, 1, 12, 0, 0).unwrap();
+        let due = Utc.with_ymd_and_hms(2024, 6, 1, 16, 0, 0).unwrap();
+
+        assert_eq!(
+            due.with_timezone(&prefs.tz()).to_rfc3339(),
+            "2024-06-02T01:00:00+09:00"
+        );
+        assert_eq!(format_relative_due(due, now, &prefs.tz()), "tomorrow");
     }
 }
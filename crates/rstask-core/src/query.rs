@@ -23,6 +23,14 @@ pub struct Query {
     pub text: String,
     pub ignore_context: bool,
     pub note: String,
+    // Queries are only stored with bincode, which ignores serde defaults.
+    // Contexts saved before a field was added are read by `decode_context`
+    // in local_state, so new fields need a case there too.
+    /// Priorities listed in `priority:P1,P2`, any of which a task may have.
+    /// A single `priority:` value sets `priority` instead.
+    pub priorities: Vec<String>,
+    /// Statuses listed in `status:`, any of which a task may have
+    pub statuses: Vec<String>,
 }
 
 impl Query {
@@ -57,6 +65,8 @@ impl Query {
             || self.due.is_some()
             || !self.date_filter.is_empty()
             || !self.priority.is_empty()
+            || !self.priorities.is_empty()
+            || !self.statuses.is_empty()
             || self.template > 0
     }
 
@@ -98,12 +108,101 @@ impl Query {
             q.priority = q2.priority.clone();
         }
 
+        if !q2.priorities.is_empty() {
+            if !q.priorities.is_empty() && q.priorities != q2.priorities {
+                panic!("Could not apply context, priority conflict");
+            }
+            q.priorities = q2.priorities.clone();
+        }
+
+        if !q2.statuses.is_empty() {
+            if !q.statuses.is_empty() && q.statuses != q2.statuses {
+                panic!("Could not apply context, status conflict");
+            }
+            q.statuses = q2.statuses.clone();
+        }
+
         q
     }
 }
 
+/// Parses the part of a tag token after its `+`/`-` sign. A quoted tag such as
+/// `"needs review"` may contain spaces and `\"` escapes; an unquoted one is
+/// taken as is. Returns None for an empty or unterminated tag.
+pub fn parse_tag_token(token: &str) -> Option<String> {
+    let Some(quoted) = token.strip_prefix('"') else {
+        return (!token.is_empty()).then(|| token.to_string());
+    };
+
+    let mut tag = String::new();
+    let mut chars = quoted.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => tag.push(chars.next()?),
+            '"' => return (chars.next().is_none() && !tag.is_empty()).then_some(tag),
+            _ => tag.push(c),
+        }
+    }
+
+    None
+}
+
+/// Rejoins quoted tags the shell split into several arguments, e.g.
+/// `+"needs` and `review"`
+fn join_quoted_tags(args: &[String]) -> Vec<String> {
+    let mut joined: Vec<String> = Vec::new();
+    let mut open: Option<Vec<&str>> = None;
+
+    for item in args {
+        if let Some(parts) = &mut open {
+            parts.push(item);
+            let candidate = parts.join(" ");
+            if parse_tag_token(&candidate[1..]).is_some() {
+                joined.push(candidate);
+                open = None;
+            }
+            continue;
+        }
+
+        let is_quoted_tag = item.starts_with("+\"") || item.starts_with("-\"");
+        if is_quoted_tag && parse_tag_token(&item[1..]).is_none() {
+            open = Some(vec![item]);
+        } else {
+            joined.push(item.clone());
+        }
+    }
+
+    // Never closed: keep the arguments as they were
+    if let Some(parts) = open {
+        joined.extend(parts.into_iter().map(str::to_string));
+    }
+
+    joined
+}
+
+/// Formats a tag for a query string, quoting it if needed
+fn format_tag_token(tag: &str) -> String {
+    if tag.contains(char::is_whitespace) || tag.starts_with('"') {
+        format!("\"{}\"", tag.replace('\\', "\\\\").replace('"', "\\\""))
+    } else {
+        tag.to_string()
+    }
+}
+
+/// Splits the comma-separated values of a `key:a,b` token, skipping empty
+/// ones
+fn split_values(values: &str) -> Vec<String> {
+    values
+        .split(',')
+        .map(str::trim)
+        .filter(|value| !value.is_empty())
+        .map(str::to_string)
+        .collect()
+}
+
 /// Parses command line arguments into a Query
 pub fn parse_query(args: &[String]) -> Result<Query> {
+    let args = join_quoted_tags(args);
     let mut query = Query::new();
     let mut words = Vec::new();
     let mut notes_mode_activated = false;
@@ -111,7 +210,7 @@ pub fn parse_query(args: &[String]) -> Result<Query> {
     let mut ids_exhausted = false;
     let mut due_date_set = false;
 
-    for item in args {
+    for item in &args {
         let lc_item = item.to_lowercase();
 
         if notes_mode_activated {
@@ -156,17 +255,38 @@ pub fn parse_query(args: &[String]) -> Result<Query> {
             query.date_filter = date_filter;
             query.due = Some(due_date.with_timezone(&Utc));
             due_date_set = true;
+        } else if lc_item.starts_with("priority:") {
+            let mut priorities = split_values(&item["priority:".len()..]);
+            if let Some(invalid) = priorities.iter().find(|p| !is_valid_priority(p)) {
+                return Err(crate::RstaskError::InvalidPriority(invalid.clone()));
+            }
+            if priorities.len() == 1 {
+                if query.priority.is_empty() {
+                    query.priority = priorities.remove(0);
+                }
+            } else {
+                query.priorities = priorities;
+            }
+        } else if let Some(statuses) = lc_item.strip_prefix("status:") {
+            let statuses = split_values(statuses);
+            if let Some(invalid) = statuses
+                .iter()
+                .find(|s| !ALL_STATUSES.contains(&s.as_str()))
+            {
+                return Err(crate::RstaskError::InvalidStatus(invalid.clone()));
+            }
+            query.statuses = statuses;
         } else if let Some(template_str) = lc_item.strip_prefix("template:") {
             if let Ok(template_id) = template_str.parse::<i32>() {
                 query.template = template_id;
             }
         } else if let Some(tag) = lc_item.strip_prefix('+') {
-            if !tag.is_empty() {
-                query.tags.push(tag.to_string());
+            if let Some(tag) = parse_tag_token(tag) {
+                query.tags.push(tag);
             }
         } else if let Some(tag) = lc_item.strip_prefix('-') {
-            if !tag.is_empty() {
-                query.anti_tags.push(tag.to_string());
+            if let Some(tag) = parse_tag_token(tag) {
+                query.anti_tags.push(tag);
             }
         } else if query.priority.is_empty() && is_valid_priority(item) {
             query.priority = item.clone();
@@ -193,11 +313,11 @@ impl fmt::Display for Query {
         }
 
         for tag in &self.tags {
-            args.push(format!("+{}", tag));
+            args.push(format!("+{}", format_tag_token(tag)));
         }
 
         for tag in &self.anti_tags {
-            args.push(format!("-{}", tag));
+            args.push(format!("-{}", format_tag_token(tag)));
         }
 
         if !self.project.is_empty() {
@@ -223,6 +343,14 @@ impl fmt::Display for Query {
             args.push(self.priority.clone());
         }
 
+        if !self.priorities.is_empty() {
+            args.push(format!("priority:{}", self.priorities.join(",")));
+        }
+
+        if !self.statuses.is_empty() {
+            args.push(format!("status:{}", self.statuses.join(",")));
+        }
+
         if self.template > 0 {
             args.push(format!("template:{}", self.template));
         }
@@ -273,6 +401,47 @@ mod tests {
         assert_eq!(query.text, "have an adventure");
     }
 
+    #[test]
+    fn test_parse_query_with_quoted_tags() {
+        let args: Vec<String> = [
+            "add",
+            "+\"needs",
+            "review\"",
+            "+solo",
+            "-\"on hold\"",
+            "fix",
+            "it",
+        ]
+        .iter()
+        .map(|s| s.to_string())
+        .collect();
+        let query = parse_query(&args).unwrap();
+
+        assert_eq!(query.tags, vec!["needs review", "solo"]);
+        assert_eq!(query.anti_tags, vec!["on hold"]);
+        assert_eq!(query.text, "fix it");
+        assert_eq!(
+            query.to_string(),
+            "+\"needs review\" +solo -\"on hold\" \"fix it\""
+        );
+    }
+
+    #[test]
+    fn test_parse_tag_token() {
+        assert_eq!(parse_tag_token("tag"), Some("tag".to_string()));
+        assert_eq!(
+            parse_tag_token("\"needs review\""),
+            Some("needs review".to_string())
+        );
+        assert_eq!(
+            parse_tag_token(r#""say \"hi\"""#),
+            Some("say \"hi\"".to_string())
+        );
+        assert_eq!(parse_tag_token("\"unterminated"), None);
+        assert_eq!(parse_tag_token("\"\""), None);
+        assert_eq!(parse_tag_token(""), None);
+    }
+
     #[test]
     fn test_parse_query_with_note() {
         let args = vec![
@@ -335,6 +504,46 @@ mod tests {
         assert_eq!(query.text, "P2 P3");
     }
 
+    #[test]
+    fn test_parse_query_priority_list() {
+        let args = vec![
+            "priority:P1,P2".to_string(),
+            "status:pending,active".to_string(),
+        ];
+        let query = parse_query(&args).unwrap();
+        assert_eq!(query.priorities, vec!["P1", "P2"]);
+        assert!(query.priority.is_empty());
+        assert_eq!(query.statuses, vec!["pending", "active"]);
+        assert_eq!(query.to_string(), "priority:P1,P2 status:pending,active");
+
+        let task = |priority: &str, status: &str| {
+            let mut task = crate::task::Task::new("task".to_string());
+            task.priority = priority.to_string();
+            task.status = status.to_string();
+            task
+        };
+        assert!(task("P1", STATUS_PENDING).matches_filter(&query));
+        assert!(task("P2", STATUS_ACTIVE).matches_filter(&query));
+        assert!(!task("P3", STATUS_PENDING).matches_filter(&query));
+        assert!(!task("P1", STATUS_PAUSED).matches_filter(&query));
+
+        // A single value works like the bare priority
+        let query = parse_query(&["priority:P1".to_string(), "status:paused".to_string()]).unwrap();
+        assert_eq!(query.priority, "P1");
+        assert!(query.priorities.is_empty());
+        assert!(task("P1", STATUS_PAUSED).matches_filter(&query));
+        assert!(!task("P2", STATUS_PAUSED).matches_filter(&query));
+
+        assert!(matches!(
+            parse_query(&["priority:P1,high".to_string()]),
+            Err(crate::RstaskError::InvalidPriority(_))
+        ));
+        assert!(matches!(
+            parse_query(&["status:pending,done".to_string()]),
+            Err(crate::RstaskError::InvalidStatus(_))
+        ));
+    }
+
     #[test]
     fn test_parse_query_template() {
         let args = vec![
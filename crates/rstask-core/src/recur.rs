@@ -0,0 +1,208 @@
+// Recurrence - tasks that come back once resolved
+use crate::constants::*;
+use crate::task::{SubTask, Task};
+use chrono::{DateTime, Days, Duration, Months, NaiveDateTime, TimeZone, Utc};
+use chrono_tz::Tz;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+/// How often a recurring task comes back
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum Recurrence {
+    Daily,
+    Weekly,
+    Monthly,
+    Yearly,
+}
+
+impl Recurrence {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Recurrence::Daily => "daily",
+            Recurrence::Weekly => "weekly",
+            Recurrence::Monthly => "monthly",
+            Recurrence::Yearly => "yearly",
+        }
+    }
+
+    /// The occurrence following `date`, as seen from `tz`
+    pub fn next_after(self, date: DateTime<Utc>, tz: Tz) -> Option<DateTime<Utc>> {
+        self.nth_after(date, 1, tz)
+    }
+
+    /// The `n`th occurrence after `anchor`. Calendar days are counted in
+    /// `tz`, so a task due at midnight stays due at midnight across DST
+    /// changes; a time skipped by a DST change moves past the gap. Each
+    /// occurrence is counted from the anchor, and days past the end of a
+    /// shorter month are clamped for that month only: 31 January is followed
+    /// by 29 February, then 31 March.
+    pub fn nth_after(self, anchor: DateTime<Utc>, n: u32, tz: Tz) -> Option<DateTime<Utc>> {
+        let local = anchor.with_timezone(&tz).naive_local();
+        let next = match self {
+            Recurrence::Daily => local.checked_add_days(Days::new(n.into())),
+            Recurrence::Weekly => local.checked_add_days(Days::new(u64::from(n) * 7)),
+            Recurrence::Monthly => local.checked_add_months(Months::new(n)),
+            Recurrence::Yearly => local.checked_add_months(Months::new(n.checked_mul(12)?)),
+        }?;
+        from_local(tz, next)
+    }
+}
+
+/// The instant of a local time in `tz`. An ambiguous time resolves to its
+/// first occurrence, and a time skipped by a DST change to the one just past
+/// the gap.
+fn from_local(tz: Tz, local: NaiveDateTime) -> Option<DateTime<Utc>> {
+    let resolved = tz.from_local_datetime(&local).earliest().or_else(|| {
+        // Gaps are at most a couple of hours; step over them
+        (1..=4)
+            .filter_map(|hours| local.checked_add_signed(Duration::hours(hours)))
+            .find_map(|later| tz.from_local_datetime(&later).earliest())
+    })?;
+    Some(resolved.with_timezone(&Utc))
+}
+
+/// Resolves the task and, if it recurs, returns a fresh pending copy due at
+/// the next occurrence after `now`, with calendar days counted in `tz`.
+/// Occurrences missed while the task was overdue are skipped rather than
+/// created one after another. A task without a due date recurs from `now`.
+///
+/// The copy gets a new UUID, unresolved subtasks and no dependencies, which
+/// belonged to the instance just completed. Non-recurring tasks return None.
+pub fn complete_recurring(task: &mut Task, now: DateTime<Utc>, tz: Tz) -> Option<Task> {
+    task.status = STATUS_RESOLVED.to_string();
+    task.resolved = Some(now);
+    task.mark_dirty();
+
+    let recur = task.recur?;
+    let anchor = task.due.unwrap_or(now);
+    let mut n = 1;
+    let mut due = recur.nth_after(anchor, n, tz)?;
+    while due <= now {
+        n = n.checked_add(1)?;
+        due = recur.nth_after(anchor, n, tz)?;
+    }
+
+    Some(Task {
+        uuid: Uuid::new_v4().to_string(),
+        status: STATUS_PENDING.to_string(),
+        write_pending: true,
+        id: 0,
+        summary: task.summary.clone(),
+        notes: task.notes.clone(),
+        tags: task.tags.clone(),
+        project: task.project.clone(),
+        priority: task.priority.clone(),
+        delegated_to: task.delegated_to.clone(),
+        subtasks: task
+            .subtasks
+            .iter()
+            .map(|subtask| SubTask {
+                summary: subtask.summary.clone(),
+                resolved: false,
+            })
+            .collect(),
+        created: now,
+        due: Some(due),
+        estimate: task.estimate,
+        reminders: task.reminders.clone(),
+        recur: task.recur,
+        extra: task.extra.clone(),
+        ..Default::default()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TZ: Tz = Tz::Europe__Paris;
+
+    #[test]
+    fn test_complete_weekly() {
+        let due = TZ.with_ymd_and_hms(2024, 6, 3, 0, 0, 0).unwrap();
+        let now = Utc.with_ymd_and_hms(2024, 6, 4, 12, 0, 0).unwrap();
+
+        let mut task = Task::new("water the plants".to_string());
+        task.recur = Some(Recurrence::Weekly);
+        task.due = Some(due.with_timezone(&Utc));
+        task.tags = vec!["home".to_string()];
+        task.subtasks = vec![SubTask {
+            summary: "balcony".to_string(),
+            resolved: true,
+        }];
+
+        let next = complete_recurring(&mut task, now, TZ).unwrap();
+        assert_eq!(task.status, STATUS_RESOLVED);
+        assert_eq!(task.resolved, Some(now));
+
+        assert_ne!(next.uuid, task.uuid);
+        assert_eq!(next.status, STATUS_PENDING);
+        assert_eq!(next.summary, task.summary);
+        assert_eq!(next.tags, task.tags);
+        assert_eq!(next.recur, Some(Recurrence::Weekly));
+        assert!(!next.subtasks[0].resolved);
+        let expected = TZ.with_ymd_and_hms(2024, 6, 10, 0, 0, 0).unwrap();
+        assert_eq!(next.due, Some(expected.with_timezone(&Utc)));
+
+        // Three weeks overdue: the missed weeks are skipped
+        let mut task = Task::new("water the plants".to_string());
+        task.recur = Some(Recurrence::Weekly);
+        task.due = Some(due.with_timezone(&Utc));
+        let late = Utc.with_ymd_and_hms(2024, 6, 25, 12, 0, 0).unwrap();
+        let next = complete_recurring(&mut task, late, TZ).unwrap();
+        let expected = TZ.with_ymd_and_hms(2024, 7, 1, 0, 0, 0).unwrap();
+        assert_eq!(next.due, Some(expected.with_timezone(&Utc)));
+    }
+
+    #[test]
+    fn test_complete_non_recurring() {
+        let now = Utc.with_ymd_and_hms(2024, 6, 4, 12, 0, 0).unwrap();
+        let mut task = Task::new("one-off".to_string());
+
+        assert!(complete_recurring(&mut task, now, TZ).is_none());
+        assert_eq!(task.status, STATUS_RESOLVED);
+        assert_eq!(task.resolved, Some(now));
+    }
+
+    #[test]
+    fn test_monthly_clamps_to_month_end() {
+        let date = TZ.with_ymd_and_hms(2024, 1, 31, 0, 0, 0).unwrap();
+        let next = Recurrence::Monthly.next_after(date.with_timezone(&Utc), TZ);
+        let expected = TZ.with_ymd_and_hms(2024, 2, 29, 0, 0, 0).unwrap();
+        assert_eq!(next, Some(expected.with_timezone(&Utc)));
+    }
+
+    #[test]
+    fn test_monthly_keeps_anchor_day() {
+        let due = TZ.with_ymd_and_hms(2024, 1, 31, 9, 0, 0).unwrap();
+        let mut task = Task::new("pay rent".to_string());
+        task.recur = Some(Recurrence::Monthly);
+        task.due = Some(due.with_timezone(&Utc));
+
+        // Two months overdue: March keeps the 31st rather than February's 29th
+        let now = Utc.with_ymd_and_hms(2024, 3, 2, 12, 0, 0).unwrap();
+        let next = complete_recurring(&mut task, now, TZ).unwrap();
+        let expected = TZ.with_ymd_and_hms(2024, 3, 31, 9, 0, 0).unwrap();
+        assert_eq!(next.due, Some(expected.with_timezone(&Utc)));
+    }
+
+    #[test]
+    fn test_recurrence_across_dst_gap() {
+        // 02:30 doesn't exist in Paris on 31 March 2024
+        let due = TZ.with_ymd_and_hms(2024, 3, 30, 2, 30, 0).unwrap();
+        let mut task = Task::new("night shift".to_string());
+        task.recur = Some(Recurrence::Daily);
+        task.due = Some(due.with_timezone(&Utc));
+
+        let now = Utc.with_ymd_and_hms(2024, 3, 30, 12, 0, 0).unwrap();
+        let next = complete_recurring(&mut task, now, TZ).unwrap();
+        let expected = TZ.with_ymd_and_hms(2024, 3, 31, 3, 30, 0).unwrap();
+        assert_eq!(next.due, Some(expected.with_timezone(&Utc)));
+
+        // The day after is back at 02:30
+        let next = Recurrence::Daily.nth_after(due.with_timezone(&Utc), 2, TZ);
+        let expected = TZ.with_ymd_and_hms(2024, 4, 1, 2, 30, 0).unwrap();
+        assert_eq!(next, Some(expected.with_timezone(&Utc)));
+    }
+}
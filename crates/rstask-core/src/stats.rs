@@ -0,0 +1,827 @@
+// Stats - task counts for progress reporting
+use crate::constants::*;
+use crate::task::Task;
+use chrono::{DateTime, Datelike, Days, Duration, NaiveDate, Utc};
+use chrono_tz::Tz;
+use std::collections::{BTreeMap, HashSet};
+
+/// Task counts for a single project
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ProjectStats {
+    pub total: usize,
+    pub completed: usize,
+    /// Open tasks, including overdue ones
+    pub pending: usize,
+    /// Open tasks whose due date has passed
+    pub overdue: usize,
+}
+
+/// Counts tasks per project. Tasks without a project are omitted, as are
+/// deleted tasks, templates and recurring tasks.
+pub fn project_stats(tasks: &[Task]) -> BTreeMap<String, ProjectStats> {
+    project_stats_at(tasks, Utc::now())
+}
+
+/// Same as [`project_stats`], with overdue tasks determined relative to `now`
+pub fn project_stats_at(tasks: &[Task], now: DateTime<Utc>) -> BTreeMap<String, ProjectStats> {
+    let mut stats: BTreeMap<String, ProjectStats> = BTreeMap::new();
+
+    for task in tasks {
+        if task.deleted || task.project.is_empty() {
+            continue;
+        }
+
+        if task.status != STATUS_RESOLVED && HIDDEN_STATUSES.contains(&task.status.as_str()) {
+            continue;
+        }
+
+        let entry = stats.entry(task.project.clone()).or_default();
+        entry.total += 1;
+
+        if task.status == STATUS_RESOLVED {
+            entry.completed += 1;
+        } else {
+            entry.pending += 1;
+            if task.due.is_some_and(|due| due < now) {
+                entry.overdue += 1;
+            }
+        }
+    }
+
+    stats
+}
+
+/// Counts open tasks per project, busiest project first and ties by name.
+/// Projects without open tasks are left out.
+pub fn project_balance(tasks: &[Task]) -> Vec<(String, usize)> {
+    let mut balance: Vec<(String, usize)> = project_stats(tasks)
+        .into_iter()
+        .filter(|(_, stats)| stats.pending > 0)
+        .map(|(project, stats)| (project, stats.pending))
+        .collect();
+    balance.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+    balance
+}
+
+/// Returns the projects from [`project_balance`] with more than
+/// `threshold` open tasks
+pub fn overloaded_projects(balance: &[(String, usize)], threshold: usize) -> Vec<&str> {
+    balance
+        .iter()
+        .filter(|(_, pending)| *pending > threshold)
+        .map(|(project, _)| project.as_str())
+        .collect()
+}
+
+/// Counts how often each pair of tags appears on the same task. Pairs are
+/// keyed in alphabetical order, so ("home", "work") also counts tasks
+/// tagged work before home. Deleted tasks are left out.
+pub fn tag_cooccurrence(tasks: &[Task]) -> BTreeMap<(String, String), usize> {
+    let mut counts: BTreeMap<(String, String), usize> = BTreeMap::new();
+
+    for task in tasks.iter().filter(|task| !task.deleted) {
+        let mut tags: Vec<&String> = task.tags.iter().collect();
+        tags.sort();
+        tags.dedup();
+
+        for (i, first) in tags.iter().enumerate() {
+            for second in &tags[i + 1..] {
+                *counts
+                    .entry(((*first).clone(), (*second).clone()))
+                    .or_default() += 1;
+            }
+        }
+    }
+
+    counts
+}
+
+/// A level in the tag hierarchy, e.g. `clientA` in `work/clientA/urgent`
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TagNode {
+    /// The last segment of the tag, empty for the root
+    pub name: String,
+    /// Tasks tagged with this tag or one nested under it, each counted once
+    pub count: usize,
+    pub children: BTreeMap<String, TagNode>,
+}
+
+/// Builds the tag hierarchy from slash-delimited tags, for a collapsible
+/// tag sidebar. The root counts every task with a tag. Empty segments, as
+/// in `work//urgent`, are skipped. Deleted tasks are left out.
+pub fn tag_tree(tasks: &[Task]) -> TagNode {
+    let mut root = TagNode::default();
+
+    for task in tasks.iter().filter(|task| !task.deleted) {
+        let paths: Vec<Vec<&str>> = task
+            .tags
+            .iter()
+            .map(|tag| tag.split('/').filter(|s| !s.is_empty()).collect::<Vec<_>>())
+            .filter(|path| !path.is_empty())
+            .collect();
+        if paths.is_empty() {
+            continue;
+        }
+
+        // Every prefix of every tag, so a node shared by two tags on the same
+        // task counts it once
+        let mut prefixes: Vec<&[&str]> = paths
+            .iter()
+            .flat_map(|path| (1..=path.len()).map(|len| &path[..len]))
+            .collect();
+        prefixes.sort();
+        prefixes.dedup();
+
+        root.count += 1;
+        for prefix in prefixes {
+            let mut node = &mut root;
+            for segment in prefix {
+                node = node
+                    .children
+                    .entry(segment.to_string())
+                    .or_insert_with(|| TagNode {
+                        name: segment.to_string(),
+                        ..Default::default()
+                    });
+            }
+            node.count += 1;
+        }
+    }
+
+    root
+}
+
+/// Averages the time from creation to resolution of resolved tasks, per
+/// tag. Tasks without a resolution time and deleted tasks are left out.
+pub fn avg_resolution_by_tag(tasks: &[Task]) -> BTreeMap<String, Duration> {
+    let mut totals: BTreeMap<String, (Duration, i32)> = BTreeMap::new();
+
+    for task in tasks {
+        if task.deleted || task.status != STATUS_RESOLVED {
+            continue;
+        }
+        let Some(resolved) = task.resolved else {
+            continue;
+        };
+
+        let taken = resolved - task.created;
+        for tag in &task.tags {
+            let (total, count) = totals.entry(tag.clone()).or_insert((Duration::zero(), 0));
+            *total += taken;
+            *count += 1;
+        }
+    }
+
+    totals
+        .into_iter()
+        .map(|(tag, (total, count))| (tag, total / count))
+        .collect()
+}
+
+/// Average number of tasks resolved per week over the last `weeks` weeks
+/// before `now`. A window of zero weeks has a velocity of zero.
+pub fn velocity(tasks: &[Task], now: DateTime<Utc>, weeks: u32) -> f64 {
+    resolved_per_week(tasks.iter(), now, weeks)
+}
+
+fn resolved_per_week<'a>(
+    tasks: impl Iterator<Item = &'a Task>,
+    now: DateTime<Utc>,
+    weeks: u32,
+) -> f64 {
+    if weeks == 0 {
+        return 0.0;
+    }
+
+    let start = now - Duration::weeks(weeks as i64);
+    let resolved = tasks
+        .filter(|task| !task.deleted && task.status == STATUS_RESOLVED)
+        .filter_map(|task| task.resolved)
+        .filter(|resolved| *resolved > start && *resolved <= now)
+        .count();
+
+    resolved as f64 / weeks as f64
+}
+
+/// Fraction of completed tasks with a due date that were resolved on or
+/// before it, or None if no completed task had a due date
+pub fn on_time_rate(tasks: &[Task]) -> Option<f64> {
+    let (on_time, total) = tasks
+        .iter()
+        .filter(|task| !task.deleted && task.status == STATUS_RESOLVED)
+        .filter_map(|task| Some((task.resolved?, task.due?)))
+        .fold((0, 0), |(on_time, total), (resolved, due)| {
+            (on_time + usize::from(resolved <= due), total + 1)
+        });
+
+    (total > 0).then(|| on_time as f64 / total as f64)
+}
+
+/// Time spent on a completed task relative to its estimate, so above 1.0
+/// means it was underestimated. None unless the task is completed and has
+/// both a non-zero estimate and tracked time.
+pub fn estimate_accuracy(task: &Task) -> Option<f64> {
+    if task.status != STATUS_RESOLVED {
+        return None;
+    }
+    let estimate = task.estimate?.num_seconds();
+    let spent = task.time_spent?.num_seconds();
+    (estimate > 0).then(|| spent as f64 / estimate as f64)
+}
+
+/// Mean [`estimate_accuracy`] over the tasks that have one, or None if
+/// none do
+pub fn avg_estimate_accuracy(tasks: &[Task]) -> Option<f64> {
+    let accuracies: Vec<f64> = tasks
+        .iter()
+        .filter(|task| !task.deleted)
+        .filter_map(estimate_accuracy)
+        .collect();
+
+    (!accuracies.is_empty()).then(|| accuracies.iter().sum::<f64>() / accuracies.len() as f64)
+}
+
+/// Remaining tasks at each `step` from `from` to `to` inclusive, for a
+/// burndown chart. A task remains from its creation until it is resolved.
+/// Templates and recurring parents never count, and neither do resolved
+/// tasks missing a resolution time. A step that isn't positive gives an
+/// empty series.
+pub fn burndown(
+    tasks: &[Task],
+    from: DateTime<Utc>,
+    to: DateTime<Utc>,
+    step: Duration,
+) -> Vec<(DateTime<Utc>, usize)> {
+    if step <= Duration::zero() {
+        return Vec::new();
+    }
+
+    let spans: Vec<(DateTime<Utc>, Option<DateTime<Utc>>)> = tasks
+        .iter()
+        .filter(|task| {
+            !task.deleted && task.status != STATUS_TEMPLATE && task.status != STATUS_RECURRING
+        })
+        .filter(|task| task.status != STATUS_RESOLVED || task.resolved.is_some())
+        .map(|task| (task.created, task.resolved))
+        .collect();
+
+    let mut series = Vec::new();
+    let mut at = from;
+    while at <= to {
+        let remaining = spans
+            .iter()
+            .filter(|(created, resolved)| *created <= at && resolved.is_none_or(|r| r > at))
+            .count();
+        series.push((at, remaining));
+        at += step;
+    }
+
+    series
+}
+
+/// Estimated completion date per project: its open tasks divided by its
+/// [`velocity`] over the last `weeks` weeks, counted from `now`. Projects
+/// with nothing resolved in that window have no forecast, unless they have
+/// no open tasks left, in which case they are done as of `now`.
+pub fn forecast_completion(
+    tasks: &[Task],
+    now: DateTime<Utc>,
+    weeks: u32,
+) -> BTreeMap<String, Option<DateTime<Utc>>> {
+    project_stats_at(tasks, now)
+        .into_iter()
+        .map(|(project, stats)| {
+            let in_project = tasks.iter().filter(|task| task.project == project);
+            let velocity = resolved_per_week(in_project, now, weeks);
+            let forecast = if stats.pending == 0 {
+                Some(now)
+            } else if velocity > 0.0 {
+                let weeks_left = stats.pending as f64 / velocity;
+                Some(now + Duration::seconds((weeks_left * 604_800.0).round() as i64))
+            } else {
+                None
+            };
+            (project, forecast)
+        })
+        .collect()
+}
+
+/// When the open tasks should be done, working `hours_per_day` on weekdays
+/// starting at `now`. Each task needs its estimate less any time already
+/// spent on it; tasks without an estimate are counted as no work. Without
+/// remaining work the ETA is `now`, and without working hours there is
+/// none.
+pub fn eta(tasks: &[Task], now: DateTime<Utc>, hours_per_day: f64) -> Option<DateTime<Utc>> {
+    if !hours_per_day.is_finite() || hours_per_day <= 0.0 || hours_per_day > 24.0 {
+        return None;
+    }
+    let per_day = Duration::try_seconds((hours_per_day * 3600.0).round() as i64)?;
+    if per_day <= Duration::zero() {
+        return None;
+    }
+
+    let mut left = Duration::zero();
+    for task in tasks {
+        if task.deleted
+            || task.status == STATUS_RESOLVED
+            || HIDDEN_STATUSES.contains(&task.status.as_str())
+        {
+            continue;
+        }
+        let Some(estimate) = task.estimate else {
+            continue;
+        };
+        let spent = task.time_spent.unwrap_or_else(Duration::zero);
+        let remaining = estimate.checked_sub(&spent)?.max(Duration::zero());
+        left = left.checked_add(&remaining)?;
+    }
+
+    // Any seven days hold five working days, so whole weeks are skipped at
+    // once, keeping the last one to be walked day by day
+    let per_week = per_day * 5;
+    let weeks = (left.num_seconds() - 1).max(0) / per_week.num_seconds();
+    let mut day = now.checked_add_days(Days::new(weeks as u64 * 7))?;
+    left -= per_week * weeks as i32;
+
+    loop {
+        if day.weekday().number_from_monday() <= 5 {
+            if left <= per_day {
+                return day.checked_add_signed(left);
+            }
+            left -= per_day;
+        }
+        day = day.checked_add_days(Days::new(1))?;
+    }
+}
+
+/// Tasks resolved on each day of `year` as seen from `tz`, for a
+/// contributions-style heatmap. Every day of the year is present, days
+/// without completions counting zero.
+pub fn completion_heatmap(tasks: &[Task], year: i32, tz: Tz) -> BTreeMap<NaiveDate, usize> {
+    let mut heatmap: BTreeMap<NaiveDate, usize> = NaiveDate::from_ymd_opt(year, 1, 1)
+        .into_iter()
+        .flat_map(|first| first.iter_days())
+        .take_while(|day| day.year() == year)
+        .map(|day| (day, 0))
+        .collect();
+
+    for task in tasks {
+        if task.deleted || task.status != STATUS_RESOLVED {
+            continue;
+        }
+        if let Some(resolved) = task.resolved
+            && let Some(count) = heatmap.get_mut(&resolved.with_timezone(&tz).date_naive())
+        {
+            *count += 1;
+        }
+    }
+
+    heatmap
+}
+
+/// Counts the consecutive days, as seen from `tz`, on which at least one
+/// task was resolved, ending today. A streak running up to yesterday still
+/// counts while today has no completion yet, as the day isn't over.
+pub fn completion_streak(tasks: &[Task], now: DateTime<Utc>, tz: Tz) -> u32 {
+    let days: HashSet<NaiveDate> = tasks
+        .iter()
+        .filter(|task| !task.deleted && task.status == STATUS_RESOLVED)
+        .filter_map(|task| task.resolved)
+        .map(|resolved| resolved.with_timezone(&tz).date_naive())
+        .collect();
+
+    let today = now.with_timezone(&tz).date_naive();
+    let mut day = if days.contains(&today) {
+        today
+    } else {
+        today - Days::new(1)
+    };
+
+    let mut streak = 0;
+    while days.contains(&day) {
+        streak += 1;
+        day = day - Days::new(1);
+    }
+
+    streak
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn task(project: &str, status: &str, due: Option<DateTime<Utc>>) -> Task {
+        let mut task = Task::new(format!("{} task", project));
+        task.project = project.to_string();
+        task.status = status.to_string();
+        task.due = due;
+        task
+    }
+
+    fn resolved_at(resolved: DateTime<Utc>) -> Task {
+        let mut task = task("streak", STATUS_RESOLVED, None);
+        task.resolved = Some(resolved);
+        task
+    }
+
+    #[test]
+    fn test_eta() {
+        // A Wednesday morning
+        let now = Utc.with_ymd_and_hms(2024, 6, 5, 9, 0, 0).unwrap();
+        let estimated = |status: &str, hours: i64| {
+            let mut task = task("eta", status, None);
+            task.estimate = Some(Duration::hours(hours));
+            task
+        };
+
+        let mut started = estimated(STATUS_ACTIVE, 12);
+        started.time_spent = Some(Duration::hours(4));
+        let tasks = vec![
+            estimated(STATUS_PENDING, 12),
+            started,
+            task("unestimated", STATUS_PENDING, None),
+            estimated(STATUS_RESOLVED, 40),
+        ];
+
+        // 20 hours left: Wednesday to Friday at 6 a day, then 2 hours on
+        // Monday after the weekend
+        assert_eq!(
+            eta(&tasks, now, 6.0),
+            Some(Utc.with_ymd_and_hms(2024, 6, 10, 11, 0, 0).unwrap())
+        );
+        assert_eq!(eta(&tasks[3..], now, 6.0), Some(now));
+        assert_eq!(eta(&tasks, now, 0.0), None);
+        assert_eq!(eta(&[], now, f64::INFINITY), None);
+        assert_eq!(eta(&[], now, 1e20), None);
+        assert_eq!(eta(&[], now, f64::NAN), None);
+
+        // Whole weeks are skipped without changing the result: 62 hours at 6
+        // a day is ten working days and two hours, ending on a Wednesday
+        let long = vec![estimated(STATUS_PENDING, 62)];
+        assert_eq!(
+            eta(&long, now, 6.0),
+            Some(Utc.with_ymd_and_hms(2024, 6, 19, 11, 0, 0).unwrap())
+        );
+    }
+
+    #[test]
+    fn test_tag_tree() {
+        let tagged = |tags: &[&str]| {
+            let mut task = task("tags", STATUS_PENDING, None);
+            task.tags = tags.iter().map(|tag| tag.to_string()).collect();
+            task
+        };
+        let tasks = vec![
+            tagged(&["work/clientA/urgent", "work/clientA"]),
+            tagged(&["work/clientB"]),
+            tagged(&["home"]),
+            tagged(&[]),
+        ];
+
+        let root = tag_tree(&tasks);
+        assert_eq!(root.count, 3);
+        assert_eq!(
+            root.children.keys().collect::<Vec<_>>(),
+            vec!["home", "work"]
+        );
+
+        let work = &root.children["work"];
+        assert_eq!(work.count, 2);
+        assert_eq!(work.children["clientA"].count, 1);
+        assert_eq!(work.children["clientB"].count, 1);
+
+        let urgent = &work.children["clientA"].children["urgent"];
+        assert_eq!(urgent.name, "urgent");
+        assert_eq!(urgent.count, 1);
+        assert!(urgent.children.is_empty());
+        assert_eq!(root.children["home"].count, 1);
+    }
+
+    #[test]
+    fn test_completion_heatmap() {
+        let date = |m, d| NaiveDate::from_ymd_opt(2024, m, d).unwrap();
+        let tasks = vec![
+            resolved_at(Utc.with_ymd_and_hms(2024, 3, 5, 9, 0, 0).unwrap()),
+            resolved_at(Utc.with_ymd_and_hms(2024, 3, 5, 16, 0, 0).unwrap()),
+            // Still 31 December in UTC, already New Year in Tokyo
+            resolved_at(Utc.with_ymd_and_hms(2024, 12, 31, 20, 0, 0).unwrap()),
+            resolved_at(Utc.with_ymd_and_hms(2023, 7, 1, 12, 0, 0).unwrap()),
+            task("open", STATUS_PENDING, None),
+        ];
+
+        let heatmap = completion_heatmap(&tasks, 2024, Tz::UTC);
+        assert_eq!(heatmap.len(), 366);
+        assert_eq!(heatmap[&date(3, 5)], 2);
+        assert_eq!(heatmap[&date(12, 31)], 1);
+        assert_eq!(heatmap[&date(1, 1)], 0);
+        assert_eq!(heatmap.values().sum::<usize>(), 3);
+
+        let heatmap = completion_heatmap(&tasks, 2024, Tz::Asia__Tokyo);
+        assert_eq!(heatmap[&date(12, 31)], 0);
+        assert_eq!(heatmap.values().sum::<usize>(), 2);
+    }
+
+    #[test]
+    fn test_completion_streak() {
+        let now = Utc.with_ymd_and_hms(2024, 6, 10, 18, 0, 0).unwrap();
+        let days_ago = |days: i64| resolved_at(now - Duration::days(days));
+
+        let tasks = vec![
+            days_ago(0),
+            days_ago(0),
+            days_ago(1),
+            days_ago(2),
+            days_ago(4),
+        ];
+        assert_eq!(completion_streak(&tasks, now, Tz::UTC), 3);
+
+        // Nothing yet today, but the streak up to yesterday is still alive
+        let tasks = vec![days_ago(1), days_ago(2)];
+        assert_eq!(completion_streak(&tasks, now, Tz::UTC), 2);
+
+        // Open tasks don't count
+        let tasks = vec![task("open", STATUS_PENDING, None)];
+        assert_eq!(completion_streak(&tasks, now, Tz::UTC), 0);
+    }
+
+    #[test]
+    fn test_avg_resolution_by_tag() {
+        let created = Utc.with_ymd_and_hms(2024, 6, 1, 9, 0, 0).unwrap();
+        let took = |hours: i64, tags: &[&str]| {
+            let mut task = resolved_at(created + Duration::hours(hours));
+            task.created = created;
+            task.tags = tags.iter().map(|t| t.to_string()).collect();
+            task
+        };
+        let mut open = task("open", STATUS_PENDING, None);
+        open.tags = vec!["quick".to_string()];
+
+        let tasks = vec![
+            took(1, &["quick"]),
+            took(3, &["quick", "review"]),
+            took(45, &["review"]),
+            open,
+        ];
+
+        let averages = avg_resolution_by_tag(&tasks);
+        assert_eq!(averages.len(), 2);
+        assert_eq!(averages["quick"], Duration::hours(2));
+        assert_eq!(averages["review"], Duration::hours(24));
+    }
+
+    #[test]
+    fn test_velocity() {
+        let now = Utc.with_ymd_and_hms(2024, 6, 30, 12, 0, 0).unwrap();
+        let days_ago = |days: i64| resolved_at(now - Duration::days(days));
+
+        // Five completions in the last four weeks, one just outside it
+        let tasks = vec![
+            days_ago(1),
+            days_ago(3),
+            days_ago(9),
+            days_ago(16),
+            days_ago(27),
+            days_ago(29),
+            task("open", STATUS_PENDING, None),
+        ];
+        assert_eq!(velocity(&tasks, now, 4), 1.25);
+        assert_eq!(velocity(&tasks, now, 1), 2.0);
+        assert_eq!(velocity(&tasks, now, 0), 0.0);
+    }
+
+    #[test]
+    fn test_completion_streak_broken() {
+        let now = Utc.with_ymd_and_hms(2024, 6, 10, 18, 0, 0).unwrap();
+        let days_ago = |days: i64| resolved_at(now - Duration::days(days));
+
+        let tasks = vec![days_ago(0), days_ago(2), days_ago(3)];
+        assert_eq!(completion_streak(&tasks, now, Tz::UTC), 1);
+
+        let tasks = vec![days_ago(2), days_ago(3)];
+        assert_eq!(completion_streak(&tasks, now, Tz::UTC), 0);
+
+        // 20:00 UTC on the 9th is already the 10th in Tokyo, joining the
+        // completion made on the 10th instead of extending the streak
+        let now = Utc.with_ymd_and_hms(2024, 6, 10, 12, 0, 0).unwrap();
+        let tasks = vec![
+            resolved_at(Utc.with_ymd_and_hms(2024, 6, 9, 20, 0, 0).unwrap()),
+            resolved_at(Utc.with_ymd_and_hms(2024, 6, 10, 1, 0, 0).unwrap()),
+        ];
+        assert_eq!(completion_streak(&tasks, now, Tz::UTC), 2);
+        assert_eq!(completion_streak(&tasks, now, Tz::Asia__Tokyo), 1);
+    }
+
+    #[test]
+    fn test_project_balance() {
+        let mut tasks = Vec::new();
+        for (project, count) in [("garden", 2), ("work", 4), ("home", 2), ("taxes", 1)] {
+            for _ in 0..count {
+                tasks.push(task(project, STATUS_PENDING, None));
+            }
+        }
+        tasks.push(task("work", STATUS_RESOLVED, None));
+        tasks.push(task("done", STATUS_RESOLVED, None));
+        tasks.push(task("taxes", STATUS_ACTIVE, None));
+
+        let balance = project_balance(&tasks);
+        let expected: Vec<(String, usize)> =
+            [("work", 4), ("garden", 2), ("home", 2), ("taxes", 2)]
+                .into_iter()
+                .map(|(project, count)| (project.to_string(), count))
+                .collect();
+        assert_eq!(balance, expected);
+
+        assert_eq!(overloaded_projects(&balance, 2), vec!["work"]);
+        assert!(overloaded_projects(&balance, 4).is_empty());
+    }
+
+    #[test]
+    fn test_tag_cooccurrence() {
+        let tagged = |tags: &[&str]| {
+            let mut task = task("", STATUS_PENDING, None);
+            task.tags = tags.iter().map(|t| t.to_string()).collect();
+            task
+        };
+        let mut deleted = tagged(&["home", "work"]);
+        deleted.deleted = true;
+
+        let tasks = vec![
+            tagged(&["work", "urgent", "home"]),
+            tagged(&["urgent", "work"]),
+            tagged(&["home", "garden", "home"]),
+            tagged(&["solo"]),
+            deleted,
+        ];
+
+        let pair = |a: &str, b: &str| (a.to_string(), b.to_string());
+        let counts = tag_cooccurrence(&tasks);
+        assert_eq!(counts.len(), 4);
+        assert_eq!(counts[&pair("urgent", "work")], 2);
+        assert_eq!(counts[&pair("home", "urgent")], 1);
+        assert_eq!(counts[&pair("home", "work")], 1);
+        assert_eq!(counts[&pair("garden", "home")], 1);
+    }
+
+    #[test]
+    fn test_on_time_rate() {
+        let due = Utc.with_ymd_and_hms(2024, 6, 10, 17, 0, 0).unwrap();
+        let finished = |offset: Duration| {
+            let mut task = resolved_at(due + offset);
+            task.due = Some(due);
+            task
+        };
+
+        let mut tasks = vec![
+            finished(-Duration::days(2)),
+            finished(Duration::zero()),
+            finished(Duration::hours(3)),
+            finished(-Duration::hours(1)),
+            // Neither counts: no due date, and not completed
+            resolved_at(due),
+            task("sprint", STATUS_PENDING, Some(due)),
+        ];
+        assert_eq!(on_time_rate(&tasks), Some(0.75));
+
+        tasks.retain(|task| task.due.is_none() || task.status != STATUS_RESOLVED);
+        assert_eq!(on_time_rate(&tasks), None);
+    }
+
+    #[test]
+    fn test_estimate_accuracy() {
+        let tracked = |status: &str, estimate: i64, spent: i64| {
+            let mut task = task("tracked", status, None);
+            task.estimate = Some(Duration::hours(estimate));
+            task.time_spent = Some(Duration::hours(spent));
+            task
+        };
+
+        // Took three hours against two estimated, and one against four
+        let under = tracked(STATUS_RESOLVED, 2, 3);
+        let over = tracked(STATUS_RESOLVED, 4, 1);
+        assert_eq!(estimate_accuracy(&under), Some(1.5));
+        assert_eq!(estimate_accuracy(&over), Some(0.25));
+
+        let open = tracked(STATUS_PENDING, 1, 5);
+        let mut untracked = tracked(STATUS_RESOLVED, 1, 1);
+        untracked.time_spent = None;
+        assert_eq!(estimate_accuracy(&open), None);
+        assert_eq!(estimate_accuracy(&untracked), None);
+
+        let tasks = vec![under, over, open, untracked];
+        assert_eq!(avg_estimate_accuracy(&tasks), Some(0.875));
+        assert_eq!(avg_estimate_accuracy(&tasks[2..]), None);
+    }
+
+    #[test]
+    fn test_forecast_completion() {
+        let now = Utc.with_ymd_and_hms(2024, 6, 10, 12, 0, 0).unwrap();
+        let done = |project: &str, days_ago: i64| {
+            let mut task = task(project, STATUS_RESOLVED, None);
+            task.resolved = Some(now - Duration::days(days_ago));
+            task
+        };
+
+        let mut tasks = vec![
+            // Four resolved over the last two weeks: two a week, six to go
+            done("web", 1),
+            done("web", 3),
+            done("web", 8),
+            done("web", 12),
+            // Too long ago to count
+            done("web", 30),
+            done("stalled", 30),
+            task("stalled", STATUS_PENDING, None),
+            done("finished", 2),
+        ];
+        for _ in 0..6 {
+            tasks.push(task("web", STATUS_PENDING, None));
+        }
+
+        let forecast = forecast_completion(&tasks, now, 2);
+        assert_eq!(forecast.len(), 3);
+        assert_eq!(forecast["web"], Some(now + Duration::weeks(3)));
+        assert_eq!(forecast["stalled"], None);
+        assert_eq!(forecast["finished"], Some(now));
+    }
+
+    #[test]
+    fn test_burndown() {
+        let start = Utc.with_ymd_and_hms(2024, 6, 3, 9, 0, 0).unwrap();
+        let day = |n: i64| start + Duration::days(n);
+        let span = |created: i64, resolved: Option<i64>| {
+            let status = if resolved.is_some() {
+                STATUS_RESOLVED
+            } else {
+                STATUS_PENDING
+            };
+            let mut task = task("sprint", status, None);
+            task.created = day(created);
+            task.resolved = resolved.map(day);
+            task
+        };
+        let mut template = span(0, None);
+        template.status = STATUS_TEMPLATE.to_string();
+
+        let tasks = vec![
+            span(0, Some(1)),
+            span(0, Some(3)),
+            span(0, None),
+            span(2, Some(3)),
+            span(5, None),
+            template,
+        ];
+
+        let counts: Vec<usize> = burndown(&tasks, day(0), day(4), Duration::days(1))
+            .into_iter()
+            .map(|(_, remaining)| remaining)
+            .collect();
+        assert_eq!(counts, vec![3, 2, 3, 1, 1]);
+
+        let series = burndown(&tasks, day(0), day(1), Duration::hours(12));
+        assert_eq!(series.len(), 3);
+        assert_eq!(series[2], (day(1), 2));
+        assert!(burndown(&tasks, day(0), day(4), Duration::zero()).is_empty());
+    }
+
+    #[test]
+    fn test_project_stats() {
+        let now = Utc.with_ymd_and_hms(2024, 6, 1, 12, 0, 0).unwrap();
+        let past = Some(now - Duration::days(1));
+        let future = Some(now + Duration::days(1));
+
+        let mut deleted = task("home", STATUS_PENDING, None);
+        deleted.deleted = true;
+
+        let tasks = vec![
+            task("home", STATUS_PENDING, past),
+            task("home", STATUS_ACTIVE, future),
+            task("home", STATUS_RESOLVED, past),
+            deleted,
+            task("work", STATUS_RESOLVED, None),
+            task("work", STATUS_TEMPLATE, None),
+            task("", STATUS_PENDING, None),
+        ];
+
+        let stats = project_stats_at(&tasks, now);
+        assert_eq!(stats.len(), 2);
+        assert_eq!(
+            stats["home"],
+            ProjectStats {
+                total: 3,
+                completed: 1,
+                pending: 2,
+                overdue: 1,
+            }
+        );
+        assert_eq!(
+            stats["work"],
+            ProjectStats {
+                total: 1,
+                completed: 1,
+                pending: 0,
+                overdue: 0,
+            }
+        );
+    }
+}
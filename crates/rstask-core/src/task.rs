@@ -1,11 +1,16 @@
-use chrono::{DateTime, Utc};
+use chrono::{DateTime, Duration, Utc};
 use serde::{Deserialize, Serialize};
-use std::path::Path;
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::{Path, PathBuf};
 use uuid::Uuid;
 
+use crate::clock::{Clock, SystemClock};
 use crate::constants::*;
-use crate::date_util::format_due_date;
+use crate::date_util::{format_due_date, parse_str_to_date_at};
+use crate::preferences::Preferences;
 use crate::query::Query;
+use crate::recur::Recurrence;
+use crate::uda::{UdaType, UdaValue};
 use crate::util::{is_valid_uuid4_string, must_get_repo_path};
 use crate::{Result, RstaskError};
 
@@ -34,7 +39,7 @@ pub mod datetime_rfc3339 {
 
 // Custom serialization module for Option<DateTime> fields
 pub mod optional_datetime_rfc3339 {
-    use chrono::{DateTime, Utc};
+    use chrono::{DateTime, NaiveDate, NaiveTime, Utc};
     use serde::{Deserialize, Deserializer, Serializer};
 
     // Zero date constant matching Go's "0001-01-01T00:00:00Z"
@@ -58,11 +63,194 @@ pub mod optional_datetime_rfc3339 {
         if s == ZERO_DATE_STR || s.starts_with("0001-01-01") {
             Ok(None)
         } else {
-            DateTime::parse_from_rfc3339(&s)
-                .map(|dt| Some(dt.with_timezone(&Utc)))
+            parse_rfc3339_or_date(&s)
+                .map(Some)
                 .map_err(serde::de::Error::custom)
         }
     }
+
+    /// Parses a full RFC3339 timestamp, a bare "YYYY-MM-DD" date or an ISO
+    /// week such as "2024-W23", the latter two taken as midnight UTC on the
+    /// day, or on the Monday of the week
+    pub fn parse_rfc3339_or_date(s: &str) -> Result<DateTime<Utc>, chrono::ParseError> {
+        match DateTime::parse_from_rfc3339(s) {
+            Ok(dt) => Ok(dt.with_timezone(&Utc)),
+            Err(e) => NaiveDate::parse_from_str(s, "%Y-%m-%d")
+                .ok()
+                .or_else(|| crate::date_util::parse_iso_week(s))
+                .map(|date| date.and_time(NaiveTime::MIN).and_utc())
+                .ok_or(e),
+        }
+    }
+}
+
+// Custom serialization module for Option<Duration> fields as ISO-8601 durations
+pub mod optional_duration_iso8601 {
+    use crate::date_util::{format_iso8601_duration, parse_duration};
+    use chrono::Duration;
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S>(duration: &Option<Duration>, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        match duration {
+            Some(d) => serializer.serialize_str(&format_iso8601_duration(*d)),
+            None => serializer.serialize_none(),
+        }
+    }
+
+    pub fn deserialize<'de, D>(deserializer: D) -> Result<Option<Duration>, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        match Option::<String>::deserialize(deserializer)? {
+            // Shorthand like "2h" is read too, as some users write it by hand
+            Some(s) if !s.is_empty() => parse_duration(&s)
+                .map(Some)
+                .map_err(serde::de::Error::custom),
+            _ => Ok(None),
+        }
+    }
+}
+
+// Custom serialization module for Vec<Duration> fields as ISO-8601 durations
+pub mod duration_list_iso8601 {
+    use crate::date_util::{format_iso8601_duration, parse_iso8601_duration};
+    use chrono::Duration;
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S>(durations: &[Duration], serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.collect_seq(durations.iter().map(|d| format_iso8601_duration(*d)))
+    }
+
+    pub fn deserialize<'de, D>(deserializer: D) -> Result<Vec<Duration>, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        Option::<Vec<String>>::deserialize(deserializer)?
+            .unwrap_or_default()
+            .iter()
+            .map(|s| parse_iso8601_duration(s).map_err(serde::de::Error::custom))
+            .collect()
+    }
+}
+
+// Custom deserialization for tags written either as a YAML list or as a
+// comma/space separated string, e.g. "work, urgent"
+pub mod tags_list_or_string {
+    use serde::{Deserialize, Deserializer};
+
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum ListOrString {
+        List(Vec<String>),
+        String(String),
+    }
+
+    pub fn deserialize<'de, D>(deserializer: D) -> Result<Vec<String>, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        Ok(deserialize_option(deserializer)?.unwrap_or_default())
+    }
+
+    pub fn deserialize_option<'de, D>(deserializer: D) -> Result<Option<Vec<String>>, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        Ok(match Option::<ListOrString>::deserialize(deserializer)? {
+            Some(ListOrString::List(tags)) => Some(tags),
+            Some(ListOrString::String(s)) => Some(
+                s.split(|c: char| c == ',' || c.is_whitespace())
+                    .filter(|tag| !tag.is_empty())
+                    .map(str::to_string)
+                    .collect(),
+            ),
+            None => None,
+        })
+    }
+}
+
+/// Deserializes a list field that may also be written as a single string,
+/// e.g. `dependencies: abc-123`. The string is kept whole, unlike tags, and
+/// an empty one means an empty list.
+pub mod string_or_list {
+    use serde::{Deserialize, Deserializer};
+
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum StringOrList {
+        String(String),
+        List(Vec<String>),
+    }
+
+    pub fn deserialize<'de, D>(deserializer: D) -> Result<Vec<String>, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        Ok(deserialize_option(deserializer)?.unwrap_or_default())
+    }
+
+    pub fn deserialize_option<'de, D>(deserializer: D) -> Result<Option<Vec<String>>, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        Ok(match Option::<StringOrList>::deserialize(deserializer)? {
+            Some(StringOrList::String(s)) if s.is_empty() => Some(Vec::new()),
+            Some(StringOrList::String(s)) => Some(vec![s]),
+            Some(StringOrList::List(list)) => Some(list),
+            None => None,
+        })
+    }
+}
+
+// Custom serialization module for delegates, written as a single string
+// when there's one (as older versions expect) and as a list otherwise
+pub mod delegates_string_or_list {
+    use serde::{Deserializer, Serialize, Serializer};
+
+    pub fn serialize<S>(delegates: &[String], serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        match delegates {
+            [] => serializer.serialize_str(""),
+            [delegate] => serializer.serialize_str(delegate),
+            _ => delegates.serialize(serializer),
+        }
+    }
+
+    pub fn serialize_option<S>(
+        delegates: &Option<Vec<String>>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        match delegates {
+            Some(delegates) => serialize(delegates, serializer),
+            None => serializer.serialize_none(),
+        }
+    }
+
+    pub fn deserialize<'de, D>(deserializer: D) -> Result<Vec<String>, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        Ok(deserialize_option(deserializer)?.unwrap_or_default())
+    }
+
+    /// A single name is kept whole, unlike tags: "Jane Doe" is one delegate
+    pub fn deserialize_option<'de, D>(deserializer: D) -> Result<Option<Vec<String>>, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        super::string_or_list::deserialize_option(deserializer)
+    }
 }
 
 /// JSON representation of a task (matches Go version output)
@@ -87,6 +275,44 @@ pub struct SubTask {
     pub resolved: bool,
 }
 
+/// What an attachment points at
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum AttachmentKind {
+    File,
+    Url,
+    Image,
+}
+
+impl AttachmentKind {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            AttachmentKind::File => "file",
+            AttachmentKind::Url => "url",
+            AttachmentKind::Image => "image",
+        }
+    }
+}
+
+/// A file, URL or image attached to a task
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct Attachment {
+    pub kind: AttachmentKind,
+    pub path: String,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub title: Option<String>,
+}
+
+impl Attachment {
+    /// `kind path`, followed by the title in quotes when there is one
+    pub fn describe(&self) -> String {
+        match &self.title {
+            Some(title) => format!("{} {} \"{}\"", self.kind.as_str(), self.path, title),
+            None => format!("{} {}", self.kind.as_str(), self.path),
+        }
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize, Default)]
 pub struct Task {
     #[serde(skip)]
@@ -101,15 +327,28 @@ pub struct Task {
     #[serde(skip_serializing, default)]
     pub id: i32,
 
+    /// Set for tasks to drop. Without `deleted_at` the file is removed on the
+    /// next save, otherwise the task is kept on disk as soft-deleted.
     #[serde(skip)]
     pub deleted: bool,
 
+    #[serde(alias = "desc", alias = "title")]
     pub summary: String,
 
     #[serde(default)]
     pub notes: String,
 
-    #[serde(default)]
+    /// File the notes are kept in instead of the body, relative to the
+    /// repository root
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub notes_file: Option<String>,
+
+    /// Whether `notes` holds what was read from `notes_file`, so saving may
+    /// replace the file's contents with it
+    #[serde(skip)]
+    pub notes_loaded: bool,
+
+    #[serde(default, deserialize_with = "tags_list_or_string::deserialize")]
     pub tags: Vec<String>,
 
     #[serde(default)]
@@ -118,15 +357,22 @@ pub struct Task {
     #[serde(default)]
     pub priority: String,
 
-    #[serde(default, rename = "delegatedto")]
-    pub delegated_to: String,
+    #[serde(default, rename = "delegatedto", with = "delegates_string_or_list")]
+    pub delegated_to: Vec<String>,
+
+    /// What the task is parked on, e.g. "reply from the vendor"
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub waiting_for: Option<String>,
 
     #[serde(default)]
     pub subtasks: Vec<SubTask>,
 
-    #[serde(default)]
+    #[serde(default, deserialize_with = "string_or_list::deserialize")]
     pub dependencies: Vec<String>,
 
+    #[serde(skip_serializing_if = "Vec::is_empty", default)]
+    pub attachments: Vec<Attachment>,
+
     #[serde(with = "datetime_rfc3339")]
     pub created: DateTime<Utc>,
 
@@ -136,6 +382,55 @@ pub struct Task {
     #[serde(with = "optional_datetime_rfc3339", default)]
     pub due: Option<DateTime<Utc>>,
 
+    /// When the task file was last written
+    #[serde(
+        with = "optional_datetime_rfc3339",
+        skip_serializing_if = "Option::is_none",
+        default
+    )]
+    pub modified: Option<DateTime<Utc>>,
+
+    /// Expected effort for the task
+    #[serde(
+        with = "optional_duration_iso8601",
+        skip_serializing_if = "Option::is_none",
+        default
+    )]
+    pub estimate: Option<Duration>,
+
+    /// Effort actually spent on the task
+    #[serde(
+        with = "optional_duration_iso8601",
+        skip_serializing_if = "Option::is_none",
+        default
+    )]
+    pub time_spent: Option<Duration>,
+
+    /// Lead times before the due date at which to be reminded
+    #[serde(
+        with = "duration_list_iso8601",
+        skip_serializing_if = "Vec::is_empty",
+        default
+    )]
+    pub reminders: Vec<Duration>,
+
+    /// When the task was soft-deleted
+    #[serde(
+        with = "optional_datetime_rfc3339",
+        skip_serializing_if = "Option::is_none",
+        default
+    )]
+    pub deleted_at: Option<DateTime<Utc>>,
+
+    /// How often the task comes back once resolved
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub recur: Option<Recurrence>,
+
+    /// Frontmatter keys rstask doesn't know about, kept so they survive a
+    /// rewrite. User-defined fields live here.
+    #[serde(skip)]
+    pub extra: BTreeMap<String, serde_yaml::Value>,
+
     #[serde(skip)]
     pub filtered: bool,
 }
@@ -143,6 +438,11 @@ pub struct Task {
 impl Task {
     /// Creates a new task with default values
     pub fn new(summary: String) -> Self {
+        Self::new_with_clock(summary, &SystemClock)
+    }
+
+    /// Creates a new task with default values, stamped using the given clock
+    pub fn new_with_clock(summary: String, clock: &dyn Clock) -> Self {
         Task {
             uuid: Uuid::new_v4().to_string(),
             status: STATUS_PENDING.to_string(),
@@ -151,15 +451,26 @@ impl Task {
             deleted: false,
             summary,
             notes: String::new(),
+            notes_file: None,
+            notes_loaded: false,
             tags: Vec::new(),
             project: String::new(),
             priority: PRIORITY_NORMAL.to_string(),
-            delegated_to: String::new(),
+            delegated_to: Vec::new(),
+            waiting_for: None,
             subtasks: Vec::new(),
             dependencies: Vec::new(),
-            created: Utc::now(),
+            attachments: Vec::new(),
+            created: clock.now(),
             resolved: None,
             due: None,
+            modified: None,
+            estimate: None,
+            time_spent: None,
+            reminders: Vec::new(),
+            deleted_at: None,
+            recur: None,
+            extra: BTreeMap::new(),
             filtered: false,
         }
     }
@@ -193,15 +504,91 @@ impl Task {
             && self.status == other.status
             && self.summary == other.summary
             && self.notes == other.notes
+            && self.notes_file == other.notes_file
             && self.tags == other.tags
             && self.project == other.project
             && self.priority == other.priority
             && self.delegated_to == other.delegated_to
+            && self.waiting_for == other.waiting_for
             && self.subtasks == other.subtasks
             && self.dependencies == other.dependencies
+            && self.attachments == other.attachments
             && self.created == other.created
             && self.resolved == other.resolved
             && self.due == other.due
+            && self.modified == other.modified
+            && self.estimate == other.estimate
+            && self.time_spent == other.time_spent
+            && self.reminders == other.reminders
+            && self.deleted_at == other.deleted_at
+            && self.recur == other.recur
+            && self.extra == other.extra
+    }
+
+    /// Lists the fields changed going from this task to `other`
+    pub fn diff(&self, other: &Task) -> Vec<crate::diff::FieldChange> {
+        crate::diff::diff_tasks(self, other)
+    }
+
+    /// Stable hash of the task's content, for detecting changes across syncs.
+    ///
+    /// Covers status, summary, notes, tags, project, priority, delegated_to,
+    /// waiting_for, subtasks, dependencies, attachments, created, resolved, due,
+    /// estimate, time_spent, reminders, deleted_at, recur and extra fields.
+    /// Identity, in-memory state (uuid, id, write_pending, deleted, filtered)
+    /// the modified stamp and where the notes are kept are left out, so the
+    /// same content always hashes the same.
+    pub fn content_hash(&self) -> u64 {
+        let mut hasher = blake3::Hasher::new();
+        let mut field = |value: &str| {
+            // Length prefixes keep adjacent fields from running together
+            hasher.update(&(value.len() as u64).to_le_bytes());
+            hasher.update(value.as_bytes());
+        };
+        let date = |date: &Option<DateTime<Utc>>| date.map(|d| d.to_rfc3339()).unwrap_or_default();
+
+        field(&self.status);
+        field(&self.summary);
+        field(&self.notes);
+        field(&self.tags.join("\n"));
+        field(&self.project);
+        field(&self.priority);
+        field(&self.delegated_to.join("\n"));
+        field(self.waiting_for.as_deref().unwrap_or_default());
+        for subtask in &self.subtasks {
+            field(&format!("{}:{}", subtask.resolved, subtask.summary));
+        }
+        field(&self.dependencies.join("\n"));
+        for attachment in &self.attachments {
+            field(&attachment.describe());
+        }
+        field(&self.created.to_rfc3339());
+        field(&date(&self.resolved));
+        field(&date(&self.due));
+        field(
+            &self
+                .estimate
+                .map(crate::date_util::format_iso8601_duration)
+                .unwrap_or_default(),
+        );
+        field(
+            &self
+                .time_spent
+                .map(crate::date_util::format_iso8601_duration)
+                .unwrap_or_default(),
+        );
+        for reminder in &self.reminders {
+            field(&crate::date_util::format_iso8601_duration(*reminder));
+        }
+        field(&date(&self.deleted_at));
+        field(self.recur.map(Recurrence::as_str).unwrap_or_default());
+        for (key, value) in &self.extra {
+            field(key);
+            field(&serde_yaml::to_string(value).unwrap_or_default());
+        }
+
+        let digest = hasher.finalize();
+        u64::from_le_bytes(digest.as_bytes()[..8].try_into().unwrap())
     }
 
     /// Checks if task matches a filter query
@@ -253,6 +640,14 @@ impl Task {
         if !query.priority.is_empty() && self.priority != query.priority {
             return false;
         }
+        if !query.priorities.is_empty() && !query.priorities.contains(&self.priority) {
+            return false;
+        }
+
+        // Check status
+        if !query.statuses.is_empty() && !query.statuses.contains(&self.status) {
+            return false;
+        }
 
         // Check text search
         if !query.text.is_empty() {
@@ -293,6 +688,224 @@ impl Task {
         }
     }
 
+    /// Splits the notes at `## ` headings into (heading, body) pairs. Text
+    /// before the first heading has no heading and is only included if not
+    /// blank. Headings inside fenced code blocks are left alone.
+    pub fn note_sections(&self) -> Vec<(Option<String>, String)> {
+        let mut sections = Vec::new();
+        let mut heading: Option<String> = None;
+        let mut body: Vec<&str> = Vec::new();
+        let mut in_fence = false;
+
+        let mut flush = |heading: Option<String>, body: &[&str]| {
+            let text = body.join("\n").trim_matches('\n').to_string();
+            if heading.is_some() || !text.trim().is_empty() {
+                sections.push((heading, text));
+            }
+        };
+
+        for line in self.notes.lines() {
+            if line.trim_start().starts_with("```") {
+                in_fence = !in_fence;
+            }
+
+            if !in_fence && let Some(title) = line.strip_prefix("## ") {
+                flush(heading.take(), &body);
+                body.clear();
+                heading = Some(title.trim().to_string());
+            } else {
+                body.push(line);
+            }
+        }
+        flush(heading, &body);
+
+        sections
+    }
+
+    /// Whether the task can be worked on now: pending, active or paused, and
+    /// not waiting for anything. Delegated and deferred tasks aren't
+    /// actionable. Dependencies live in other tasks, so they aren't checked.
+    pub fn is_actionable(&self) -> bool {
+        !self.deleted
+            && [STATUS_PENDING, STATUS_ACTIVE, STATUS_PAUSED].contains(&self.status.as_str())
+            && self.waiting_for.is_none()
+    }
+
+    /// People the task is delegated to
+    pub fn delegates(&self) -> &[String] {
+        &self.delegated_to
+    }
+
+    /// Sets the due date from user input such as "tomorrow", "+1w" or
+    /// "2024-12-25", with relative dates counted from `now`
+    pub fn set_due_str(&mut self, input: &str, now: DateTime<Utc>) -> Result<()> {
+        let due = parse_str_to_date_at(input, now.with_timezone(&chrono::Local))?;
+        self.due = Some(due.with_timezone(&Utc));
+        self.mark_dirty();
+        Ok(())
+    }
+
+    /// Returns when reminders are due, one per lead time before the due date,
+    /// soonest first. Empty without a due date. Lead times reaching before
+    /// the earliest representable date are skipped.
+    pub fn reminder_times(&self) -> Vec<DateTime<Utc>> {
+        let Some(due) = self.due else {
+            return Vec::new();
+        };
+
+        let mut times: Vec<DateTime<Utc>> = self
+            .reminders
+            .iter()
+            .filter_map(|lead| due.checked_sub_signed(*lead))
+            .collect();
+        times.sort();
+        times
+    }
+
+    /// Returns a user-defined field read as the type declared for it in
+    /// `user_fields`, None if the value doesn't fit. The type of an
+    /// undeclared field is guessed from its value.
+    pub fn uda(&self, name: &str, preferences: &Preferences) -> Option<UdaValue> {
+        let value = self.extra.get(name)?;
+        match preferences.user_fields.get(name) {
+            Some(uda_type) => uda_type.parse(value),
+            None => UdaType::infer(value)?.parse(value),
+        }
+    }
+
+    /// Returns a user-defined field read as the given type, None if it's
+    /// missing or doesn't fit
+    pub fn uda_as(&self, name: &str, uda_type: UdaType) -> Option<UdaValue> {
+        uda_type.parse(self.extra.get(name)?)
+    }
+
+    /// Checks the fields declared in `user_fields` hold values of their type
+    pub fn validate_user_fields(&self, preferences: &Preferences) -> Result<()> {
+        let mut names: Vec<&String> = preferences.user_fields.keys().collect();
+        names.sort();
+
+        for name in names {
+            if let Some(value) = self.extra.get(name) {
+                preferences.user_fields[name].validate(name, value)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Fills in values configured as defaults for new tasks. Existing values
+    /// are kept, default tags are added alongside the task's own. Defaults
+    /// for the task's project take precedence over the global ones.
+    pub fn apply_defaults(&mut self, preferences: &Preferences) {
+        let project = preferences.project_defaults.get(&self.project);
+
+        let tags = project
+            .and_then(|defaults| defaults.tags.as_ref())
+            .unwrap_or(&preferences.default_tags);
+        for tag in tags {
+            if !self.tags.contains(tag) {
+                self.tags.push(tag.clone());
+                self.mark_dirty();
+            }
+        }
+
+        if self.priority.is_empty()
+            && let Some(priority) = project.and_then(|defaults| defaults.priority.as_ref())
+        {
+            self.priority = priority.clone();
+            self.mark_dirty();
+        }
+    }
+
+    /// Marks the task as deleted at `now`, keeping it on disk until purged
+    pub fn soft_delete(&mut self, now: DateTime<Utc>) {
+        self.deleted = true;
+        self.deleted_at = Some(now);
+        self.mark_dirty();
+    }
+
+    /// Parks the task as someday/maybe, out of every open list until it is
+    /// brought back to pending. Only pending and paused tasks can be parked.
+    pub fn defer_to_someday(&mut self) -> Result<()> {
+        if !is_valid_status_transition(&self.status, STATUS_SOMEDAY) {
+            return Err(RstaskError::InvalidStatusTransition(
+                self.status.clone(),
+                STATUS_SOMEDAY.to_string(),
+            ));
+        }
+        self.status = STATUS_SOMEDAY.to_string();
+        self.mark_dirty();
+        Ok(())
+    }
+
+    /// Attaches a file, URL or image, optionally with a title to show
+    /// instead of the path
+    pub fn add_attachment(&mut self, kind: AttachmentKind, path: String, title: Option<String>) {
+        self.attachments.push(Attachment { kind, path, title });
+        self.mark_dirty();
+    }
+
+    /// Flips the subtask at `index` between done and not done. The task is
+    /// marked dirty, so saving it stamps `modified` from the task set's clock.
+    pub fn toggle_subtask(&mut self, index: usize) -> Result<()> {
+        let count = self.subtasks.len();
+        let subtask = self.subtasks.get_mut(index).ok_or_else(|| {
+            RstaskError::Other(format!(
+                "No subtask at index {}, the task has {}",
+                index, count
+            ))
+        })?;
+        subtask.resolved = !subtask.resolved;
+        self.mark_dirty();
+        Ok(())
+    }
+
+    /// Returns the names of tags carrying the given prefix, with the prefix
+    /// stripped. An empty prefix matches nothing.
+    pub fn tags_with_prefix(&self, prefix: &str) -> Vec<&str> {
+        if prefix.is_empty() {
+            return Vec::new();
+        }
+
+        self.tags
+            .iter()
+            .filter_map(|tag| tag.strip_prefix(prefix))
+            .filter(|name| !name.is_empty())
+            .collect()
+    }
+
+    /// Returns the task's contexts, "@home" being the context "home"
+    pub fn contexts(&self) -> Vec<&str> {
+        self.contexts_with(&Preferences::default())
+    }
+
+    /// Returns the task's contexts using the configured context prefix
+    pub fn contexts_with(&self, preferences: &Preferences) -> Vec<&str> {
+        self.tags_with_prefix(&preferences.context_prefix)
+    }
+
+    /// Returns the task's areas, "#finance" being the area "finance"
+    pub fn areas(&self) -> Vec<&str> {
+        self.areas_with(&Preferences::default())
+    }
+
+    /// Returns the task's areas using the configured area prefix
+    pub fn areas_with(&self, preferences: &Preferences) -> Vec<&str> {
+        self.tags_with_prefix(&preferences.area_prefix)
+    }
+
+    /// Moves a resolved task back to pending and clears its resolved time.
+    /// Returns false, leaving the task untouched, if it wasn't resolved.
+    pub fn reopen(&mut self) -> bool {
+        if self.status != STATUS_RESOLVED {
+            return false;
+        }
+
+        self.status = STATUS_PENDING.to_string();
+        self.resolved = None;
+        self.mark_dirty();
+        true
+    }
+
     /// Validates task data
     pub fn validate(&self) -> Result<()> {
         if !is_valid_uuid4_string(&self.uuid) {
@@ -367,89 +980,125 @@ impl Task {
             self.notes.push_str(&query.note);
         }
 
+        self.mark_dirty();
+    }
+
+    /// Flags the task as needing to be written on the next save
+    pub fn mark_dirty(&mut self) {
         self.write_pending = true;
     }
 
-    /// Saves task to disk
-    pub fn save_to_disk(&mut self, repo_path: &Path) -> Result<()> {
-        self.write_pending = false;
+    /// Whether the task has changes not yet written to disk
+    pub fn is_dirty(&self) -> bool {
+        self.write_pending
+    }
+
+    /// Path of the task file in the status directory of `repo_path`, within
+    /// its shard when `shard_uuids` is set
+    pub fn file_path(&self, repo_path: &Path, preferences: &Preferences) -> PathBuf {
+        let dir = preferences.status_dir(&self.status);
+        let filename = format!("{}.md", self.uuid);
+        if preferences.shard_uuids {
+            repo_path
+                .join(dir)
+                .join(shard_of(&self.uuid))
+                .join(filename)
+        } else {
+            must_get_repo_path(repo_path, dir, &filename)
+        }
+    }
+
+    /// Every place the task may be stored under a status directory: flat or
+    /// sharded, as markdown or legacy YAML
+    fn stored_paths(&self, repo_path: &Path, dir: &str) -> [PathBuf; 4] {
+        let md = format!("{}.md", self.uuid);
+        let yml = format!("{}.yml", self.uuid);
+        let shard = repo_path.join(dir).join(shard_of(&self.uuid));
+        [
+            must_get_repo_path(repo_path, dir, &md),
+            must_get_repo_path(repo_path, dir, &yml),
+            shard.join(md),
+            shard.join(yml),
+        ]
+    }
 
-        let filepath = must_get_repo_path(repo_path, &self.status, &format!("{}.md", self.uuid));
+    /// Saves task to disk. The task stays dirty if writing fails.
+    pub fn save_to_disk(&mut self, repo_path: &Path, preferences: &Preferences) -> Result<()> {
+        let filepath = self.file_path(repo_path, preferences);
 
-        if self.deleted {
+        if self.deleted && self.deleted_at.is_none() {
             // Delete the task file
             if filepath.exists() {
                 std::fs::remove_file(&filepath)?;
             }
+            self.delete_notes_file(repo_path)?;
         } else {
-            // Save task to disk using markdown with frontmatter
-            let markdown_data = crate::frontmatter::task_to_markdown(self)?;
-
-            // Ensure directory exists
-            if let Some(parent) = filepath.parent() {
-                std::fs::create_dir_all(parent)?;
+            let threshold = preferences.notes_file_threshold;
+            if self.notes_file.is_none() && threshold > 0 && self.notes.len() > threshold {
+                self.notes_file = Some(format!("notes/{}.md", self.uuid));
+            }
+            if let Some(notes_file) = &self.notes_file {
+                let path = crate::frontmatter::notes_file_path(repo_path, notes_file)?;
+                // Notes never read from the file don't replace it, unless
+                // there is nothing there yet
+                if self.notes_loaded || !path.exists() {
+                    let notes = crate::frontmatter::notes_to_write(self, preferences)?;
+                    write_if_changed(&path, &notes)?;
+                    self.notes_loaded = true;
+                }
             }
 
-            std::fs::write(&filepath, markdown_data)?;
+            // Save task to disk using markdown with frontmatter
+            let markdown_data = crate::frontmatter::task_to_markdown_with(self, preferences)?;
+            write_if_changed(&filepath, &markdown_data)?;
         }
 
-        // Delete task from other status directories (both .md and legacy .yml)
+        // Delete task from other status directories (both .md and legacy
+        // .yml), and from the other layout of its own, e.g. once sharding
+        // is turned on
         for status in ALL_STATUSES {
-            if *status == self.status {
-                continue;
-            }
-
-            // Delete .md file
-            let other_filepath =
-                must_get_repo_path(repo_path, status, &format!("{}.md", self.uuid));
-            if other_filepath.exists() {
-                std::fs::remove_file(&other_filepath)?;
-            }
-
-            // Delete legacy .yml file if it exists
-            let legacy_filepath =
-                must_get_repo_path(repo_path, status, &format!("{}.yml", self.uuid));
-            if legacy_filepath.exists() {
-                std::fs::remove_file(&legacy_filepath)?;
+            let dir = preferences.status_dir(status);
+            for path in self.stored_paths(repo_path, dir) {
+                let own_legacy =
+                    *status == self.status && path.extension().is_some_and(|ext| ext == "yml");
+                if path != filepath && !own_legacy && path.exists() {
+                    std::fs::remove_file(&path)?;
+                }
             }
         }
 
+        self.write_pending = false;
         Ok(())
     }
 
     /// Deletes task from disk
     pub fn delete_from_disk(&self, repo_path: &Path) -> Result<()> {
-        // Delete both .yml and .md files from current status directory
-        let yml_filepath =
-            must_get_repo_path(repo_path, &self.status, &format!("{}.yml", self.uuid));
-        if yml_filepath.exists() {
-            std::fs::remove_file(&yml_filepath)?;
-        }
-
-        let md_filepath = must_get_repo_path(repo_path, &self.status, &format!("{}.md", self.uuid));
-        if md_filepath.exists() {
-            std::fs::remove_file(&md_filepath)?;
-        }
+        self.delete_from_disk_with(repo_path, &Preferences::default())
+    }
 
-        // Also check other status directories
+    /// Same as [`Task::delete_from_disk`], honouring the configured status
+    /// directories
+    pub fn delete_from_disk_with(&self, repo_path: &Path, preferences: &Preferences) -> Result<()> {
+        // Delete both .yml and .md files from every status directory, flat
+        // or sharded
         for status in ALL_STATUSES {
-            if *status == self.status {
-                continue;
+            for path in self.stored_paths(repo_path, preferences.status_dir(status)) {
+                if path.exists() {
+                    std::fs::remove_file(&path)?;
+                }
             }
+        }
 
-            let other_yml_filepath =
-                must_get_repo_path(repo_path, status, &format!("{}.yml", self.uuid));
-            if other_yml_filepath.exists() {
-                std::fs::remove_file(&other_yml_filepath)?;
-            }
+        self.delete_notes_file(repo_path)
+    }
 
-            let other_md_filepath =
-                must_get_repo_path(repo_path, status, &format!("{}.md", self.uuid));
-            if other_md_filepath.exists() {
-                std::fs::remove_file(&other_md_filepath)?;
+    fn delete_notes_file(&self, repo_path: &Path) -> Result<()> {
+        if let Some(notes_file) = &self.notes_file {
+            let path = crate::frontmatter::notes_file_path(repo_path, notes_file)?;
+            if path.exists() {
+                std::fs::remove_file(&path)?;
             }
         }
-
         Ok(())
     }
 
@@ -472,12 +1121,46 @@ impl std::fmt::Display for Task {
     }
 }
 
+/// Writes a task to `path` only if its serialized form differs from the file's
+/// current content. Returns whether a write occurred.
+pub fn save_if_changed(path: &Path, task: &Task) -> Result<bool> {
+    let markdown_data = crate::frontmatter::task_to_markdown(task)?;
+    write_if_changed(path, &markdown_data)
+}
+
+fn write_if_changed(path: &Path, data: &str) -> Result<bool> {
+    if let Ok(existing) = std::fs::read_to_string(path)
+        && existing.trim_end_matches('\n') == data.trim_end_matches('\n')
+    {
+        return Ok(false);
+    }
+
+    // Ensure directory exists
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+
+    std::fs::write(path, data)?;
+    Ok(true)
+}
+
 /// Unmarshals a task from disk
 pub fn unmarshal_task(
     path: &Path,
     filename: &str,
     ids: &std::collections::HashMap<String, i32>,
     status: &str,
+) -> Result<Task> {
+    unmarshal_task_with(path, filename, ids, status, &Preferences::default())
+}
+
+/// Reads a task file, honouring the given preferences
+pub fn unmarshal_task_with(
+    path: &Path,
+    filename: &str,
+    ids: &std::collections::HashMap<String, i32>,
+    status: &str,
+    preferences: &Preferences,
 ) -> Result<Task> {
     // Support both .md (new format) and .yml (legacy format)
     let is_markdown = filename.ends_with(".md");
@@ -510,11 +1193,20 @@ pub fn unmarshal_task(
     let data = std::fs::read_to_string(path)?;
 
     let task = if is_markdown {
-        // Parse markdown with frontmatter
-        crate::frontmatter::task_from_markdown(&data, uuid, status, id)?
+        // Parse markdown with frontmatter; notes files are relative to the
+        // repository, above the status directory
+        let repo_path = status_dir_of(path)
+            .and_then(Path::parent)
+            .unwrap_or(Path::new("."));
+        crate::frontmatter::task_from_markdown_at(&data, uuid, status, id, preferences, repo_path)?
     } else {
-        // Parse legacy YAML format
-        let mut task: Task = serde_yaml::from_str(&data)?;
+        // Parse legacy YAML format, the whole file being frontmatter
+        crate::frontmatter::check_frontmatter_size(&data, preferences)?;
+        crate::frontmatter::check_yaml_anchors(&data, preferences)?;
+        let legacy: LegacyTask = serde_yaml::from_str(&data)?;
+        crate::frontmatter::check_unknown_keys(legacy.extra.keys(), preferences)?;
+        let mut task = legacy.task;
+        task.extra = legacy.extra;
         task.uuid = uuid.to_string();
         task.status = status.to_string();
         task.id = id;
@@ -524,9 +1216,166 @@ pub fn unmarshal_task(
     Ok(task)
 }
 
+/// A legacy `.yml` task file, keeping the keys [`Task`] doesn't know about
+#[derive(Deserialize)]
+struct LegacyTask {
+    #[serde(flatten)]
+    task: Task,
+    #[serde(flatten)]
+    extra: BTreeMap<String, serde_yaml::Value>,
+}
+
+/// Shard directory of a task: the first two characters of its UUID
+fn shard_of(uuid: &str) -> &str {
+    uuid.get(..2).unwrap_or(uuid)
+}
+
+/// The status directory holding a task file, looking past its shard
+fn status_dir_of(path: &Path) -> Option<&Path> {
+    let parent = path.parent()?;
+    let filename = path.file_name()?.to_string_lossy();
+    let in_shard = parent
+        .file_name()
+        .is_some_and(|dir| filename.len() > 2 && *dir == *shard_of(&filename));
+    if in_shard {
+        parent.parent()
+    } else {
+        Some(parent)
+    }
+}
+
+/// Reads the task file at `path`, taking its status from the name of the
+/// directory it is in, or above its shard, as mapped by the configured
+/// status directories
+pub fn task_from_path(
+    path: &Path,
+    ids: &std::collections::HashMap<String, i32>,
+    preferences: &Preferences,
+) -> Result<Task> {
+    let filename = path
+        .file_name()
+        .map(|name| name.to_string_lossy())
+        .ok_or_else(|| RstaskError::Parse(format!("not a task file: {}", path.display())))?;
+    let dir = status_dir_of(path)
+        .and_then(Path::file_name)
+        .map(|name| name.to_string_lossy())
+        .unwrap_or_default();
+    let status = preferences.status_from_dir(&dir).ok_or_else(|| {
+        RstaskError::Parse(format!("{} is not a status directory", path.display()))
+    })?;
+
+    unmarshal_task_with(path, &filename, ids, status, preferences)
+}
+
+/// Whether `needle` occurs in `haystack` as a whole word, i.e. not directly
+/// next to another letter, digit or underscore
+fn contains_word(haystack: &str, needle: &str) -> bool {
+    let is_word = |c: char| c.is_alphanumeric() || c == '_';
+    haystack.match_indices(needle).any(|(start, _)| {
+        let before = haystack[..start].chars().next_back();
+        let after = haystack[start + needle.len()..].chars().next();
+        !before.is_some_and(is_word) && !after.is_some_and(is_word)
+    })
+}
+
+/// Suggests known tags mentioned in the task's summary or notes as whole
+/// words, ignoring case, that the task doesn't carry yet
+pub fn suggest_tags(task: &Task, known_tags: &BTreeSet<String>) -> Vec<String> {
+    let text = format!("{}\n{}", task.summary, task.notes).to_lowercase();
+    let applied: Vec<String> = task.tags.iter().map(|tag| tag.to_lowercase()).collect();
+    known_tags
+        .iter()
+        .filter(|tag| !tag.is_empty())
+        .filter(|tag| {
+            let tag = tag.to_lowercase();
+            !applied.contains(&tag) && contains_word(&text, &tag)
+        })
+        .cloned()
+        .collect()
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use chrono::TimeZone;
+
+    #[test]
+    fn test_legacy_yml_checks_frontmatter() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let uuid = Task::new(String::new()).uuid;
+        let filename = format!("{}.yml", uuid);
+        let path = dir.path().join(&filename);
+        std::fs::write(
+            &path,
+            "summary: legacy\ncreated: 2024-01-01T00:00:00Z\nsprint: 12\n",
+        )
+        .unwrap();
+        let load = |preferences: &Preferences| {
+            unmarshal_task_with(
+                &path,
+                &filename,
+                &Default::default(),
+                "pending",
+                preferences,
+            )
+        };
+
+        let task = load(&Preferences::default()).unwrap();
+        assert_eq!(task.summary, "legacy");
+        assert!(task.extra.contains_key("sprint"));
+
+        let strict = Preferences {
+            strict_frontmatter: true,
+            ..Default::default()
+        };
+        assert!(matches!(load(&strict), Err(RstaskError::Parse(_))));
+
+        let small = Preferences {
+            max_frontmatter_bytes: 16,
+            ..Default::default()
+        };
+        assert!(matches!(load(&small), Err(RstaskError::Parse(_))));
+    }
+
+    #[test]
+    fn test_toggle_subtask() {
+        let mut task = Task::new("Release".to_string());
+        task.subtasks = vec![SubTask {
+            summary: "tag".to_string(),
+            resolved: false,
+        }];
+        task.write_pending = false;
+
+        task.toggle_subtask(0).unwrap();
+        assert!(task.subtasks[0].resolved);
+        assert_eq!(task.modified, None);
+        assert!(task.is_dirty());
+        task.toggle_subtask(0).unwrap();
+        assert!(!task.subtasks[0].resolved);
+
+        assert!(matches!(task.toggle_subtask(1), Err(RstaskError::Other(_))));
+    }
+
+    #[test]
+    fn test_suggest_tags() {
+        let mut task = Task::new("Fix the Billing export".to_string());
+        task.notes = "Ask ops about it, see also the reports page.".to_string();
+        task.tags = vec!["Reports".to_string()];
+
+        let known: BTreeSet<String> = ["billing", "ops", "reports", "port", "urgent"]
+            .iter()
+            .map(|tag| tag.to_string())
+            .collect();
+
+        // "port" only appears inside "export" and "reports"
+        assert_eq!(suggest_tags(&task, &known), vec!["billing", "ops"]);
+
+        // Applied tags match whatever their case, beyond ASCII too
+        let mut task = Task::new("Plan the élan offsite".to_string());
+        task.tags = vec!["Élan".to_string()];
+        let known: BTreeSet<String> = ["élan".to_string()].into();
+        assert!(suggest_tags(&task, &known).is_empty());
+    }
 
     #[test]
     fn test_yaml_serialization_format() {
@@ -536,7 +1385,7 @@ mod tests {
             project: "myproject".to_string(),
             priority: "P1".to_string(),
             notes: String::new(),
-            delegated_to: String::new(),
+            delegated_to: Vec::new(),
             subtasks: Vec::new(),
             dependencies: Vec::new(),
             created: Utc::now(),
@@ -591,6 +1440,50 @@ due: 0001-01-01T00:00:00Z
         assert!(task.due.is_none());
     }
 
+    #[test]
+    fn test_parse_date_only_due_and_resolved() {
+        let yaml = r#"
+summary: date only
+created: 2024-01-01T00:00:00Z
+due: 2024-06-01
+resolved: 2024-06-02T15:30:00+02:00
+"#;
+        let task: Task = serde_yaml::from_str(yaml).unwrap();
+        assert_eq!(
+            task.due,
+            Some(Utc.with_ymd_and_hms(2024, 6, 1, 0, 0, 0).unwrap())
+        );
+        assert_eq!(
+            task.resolved,
+            Some(Utc.with_ymd_and_hms(2024, 6, 2, 13, 30, 0).unwrap())
+        );
+
+        // Serialization stays RFC3339
+        let yaml = serde_yaml::to_string(&task).unwrap();
+        assert!(yaml.contains("2024-06-01T00:00:00+00:00"));
+
+        let week = "summary: x\ncreated: 2024-01-01T00:00:00Z\ndue: 2024-W23\n";
+        let task: Task = serde_yaml::from_str(week).unwrap();
+        assert_eq!(
+            task.due,
+            Some(Utc.with_ymd_and_hms(2024, 6, 3, 0, 0, 0).unwrap())
+        );
+        let yaml = serde_yaml::to_string(&task).unwrap();
+        assert!(yaml.contains("2024-06-03T00:00:00+00:00"));
+
+        let bad = "summary: x\ncreated: 2024-01-01T00:00:00Z\ndue: 2024-13-01\n";
+        assert!(serde_yaml::from_str::<Task>(bad).is_err());
+    }
+
+    #[test]
+    fn test_new_with_fixed_clock() {
+        let now = DateTime::parse_from_rfc3339("2024-03-01T09:30:00Z")
+            .unwrap()
+            .with_timezone(&Utc);
+        let task = Task::new_with_clock("Test".to_string(), &crate::clock::FixedClock(now));
+        assert_eq!(task.created, now);
+    }
+
     #[test]
     fn test_task_modify_adds_note() {
         let mut task = Task::new("Test".to_string());
@@ -637,6 +1530,553 @@ due: 0001-01-01T00:00:00Z
         assert_eq!(task.project, "");
     }
 
+    #[test]
+    fn test_status_dirs_on_disk() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let ids = std::collections::HashMap::new();
+        let mut task = Task::new("Test".to_string());
+        task.status = STATUS_RESOLVED.to_string();
+
+        let default = Preferences::default();
+        let path = task.file_path(dir.path(), &default);
+        assert_eq!(
+            path,
+            dir.path()
+                .join("resolved")
+                .join(format!("{}.md", task.uuid))
+        );
+        task.save_to_disk(dir.path(), &default).unwrap();
+        let loaded = task_from_path(&path, &ids, &default).unwrap();
+        assert_eq!(loaded.status, STATUS_RESOLVED);
+
+        let custom = Preferences {
+            status_dirs: [(STATUS_RESOLVED.to_string(), "done".to_string())].into(),
+            ..Default::default()
+        };
+        let moved = task.file_path(dir.path(), &custom);
+        assert_eq!(
+            moved,
+            dir.path().join("done").join(format!("{}.md", task.uuid))
+        );
+        task.save_to_disk(dir.path(), &custom).unwrap();
+        assert!(moved.exists());
+        assert_eq!(
+            task_from_path(&moved, &ids, &custom).unwrap().status,
+            STATUS_RESOLVED
+        );
+        // "resolved" no longer names a status directory
+        assert!(task_from_path(&path, &ids, &custom).is_err());
+
+        task.delete_from_disk_with(dir.path(), &custom).unwrap();
+        assert!(!moved.exists());
+    }
+
+    #[test]
+    fn test_sharded_file_path() {
+        let repo = Path::new("/repo");
+        let mut task = Task::new("Test".to_string());
+        task.uuid = "3f2a6c1e-1111-4111-8111-111111111111".to_string();
+
+        let flat = Preferences::default();
+        assert_eq!(
+            task.file_path(repo, &flat),
+            repo.join("pending").join(format!("{}.md", task.uuid))
+        );
+
+        let sharded = Preferences {
+            shard_uuids: true,
+            ..Default::default()
+        };
+        let path = task.file_path(repo, &sharded);
+        assert_eq!(
+            path,
+            repo.join("pending/3f").join(format!("{}.md", task.uuid))
+        );
+        assert_eq!(status_dir_of(&path), Some(repo.join("pending").as_path()));
+    }
+
+    #[test]
+    fn test_large_notes_moved_to_file() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let prefs = Preferences {
+            notes_file_threshold: 10,
+            ..Default::default()
+        };
+
+        let mut short = Task::new("short".to_string());
+        short.notes = "brief".to_string();
+        short.save_to_disk(dir.path(), &prefs).unwrap();
+        assert!(short.notes_file.is_none());
+
+        let mut long = Task::new("long".to_string());
+        long.notes = "a note well over ten bytes".to_string();
+        long.save_to_disk(dir.path(), &prefs).unwrap();
+        let notes_file = format!("notes/{}.md", long.uuid);
+        assert_eq!(long.notes_file.as_deref(), Some(notes_file.as_str()));
+
+        let path = long.file_path(dir.path(), &prefs);
+        assert!(
+            !std::fs::read_to_string(&path)
+                .unwrap()
+                .contains("well over")
+        );
+        let ids = std::collections::HashMap::new();
+        let loaded = task_from_path(&path, &ids, &prefs).unwrap();
+        assert_eq!(loaded.notes, long.notes);
+
+        long.delete_from_disk_with(dir.path(), &prefs).unwrap();
+        assert!(!dir.path().join(&notes_file).exists());
+    }
+
+    #[test]
+    fn test_edit_roundtrip_with_notes_file() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let prefs = Preferences {
+            notes_file_threshold: 10,
+            ..Default::default()
+        };
+        let ids = std::collections::HashMap::new();
+
+        let mut task = Task::new("long".to_string());
+        task.notes = "a note well over ten bytes".to_string();
+        task.save_to_disk(dir.path(), &prefs).unwrap();
+        let path = task.file_path(dir.path(), &prefs);
+        let notes_path = dir.path().join(task.notes_file.as_ref().unwrap());
+
+        // The edit buffer carries the notes, and edits to them are written
+        // back to the notes file
+        let loaded = task_from_path(&path, &ids, &prefs).unwrap();
+        let buffer = crate::frontmatter::task_to_edit_markdown(&loaded, &prefs).unwrap();
+        assert!(buffer.contains("a note well over ten bytes"));
+        let edited = buffer.replace("ten bytes", "ten bytes, edited");
+        let mut edited = crate::frontmatter::task_from_markdown_with(
+            &edited,
+            &task.uuid,
+            &task.status,
+            task.id,
+            &prefs,
+        )
+        .unwrap();
+        assert_eq!(edited.notes_file, task.notes_file);
+        edited.notes_loaded = true;
+        edited.save_to_disk(dir.path(), &prefs).unwrap();
+        assert_eq!(
+            task_from_path(&path, &ids, &prefs).unwrap().notes,
+            "a note well over ten bytes, edited"
+        );
+
+        // A task parsed without reading its notes file leaves it alone
+        let unloaded = std::fs::read_to_string(&path).unwrap();
+        let mut unloaded = crate::frontmatter::task_from_markdown_with(
+            &unloaded,
+            &task.uuid,
+            &task.status,
+            task.id,
+            &prefs,
+        )
+        .unwrap();
+        assert!(unloaded.notes.is_empty());
+        unloaded.save_to_disk(dir.path(), &prefs).unwrap();
+        assert_eq!(
+            std::fs::read_to_string(&notes_path).unwrap(),
+            "a note well over ten bytes, edited"
+        );
+    }
+
+    #[test]
+    fn test_cleared_notes_file_stays_empty() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let prefs = Preferences {
+            notes_file_threshold: 10,
+            ..Default::default()
+        };
+        let ids = std::collections::HashMap::new();
+
+        let mut task = Task::new("long".to_string());
+        task.notes = "a note well over ten bytes".to_string();
+        task.save_to_disk(dir.path(), &prefs).unwrap();
+        let path = task.file_path(dir.path(), &prefs);
+
+        let mut loaded = task_from_path(&path, &ids, &prefs).unwrap();
+        assert!(loaded.notes_loaded);
+        loaded.notes = String::new();
+        loaded.save_to_disk(dir.path(), &prefs).unwrap();
+
+        let reloaded = task_from_path(&path, &ids, &prefs).unwrap();
+        assert_eq!(reloaded.notes_file, task.notes_file);
+        assert_eq!(reloaded.notes, "");
+    }
+
+    #[test]
+    fn test_save_if_changed() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let path = dir.path().join("pending").join("task.md");
+        let mut task = Task::new("Test".to_string());
+
+        assert!(save_if_changed(&path, &task).unwrap());
+        assert!(!save_if_changed(&path, &task).unwrap());
+
+        // A missing trailing newline on disk is not a change
+        let content = std::fs::read_to_string(&path).unwrap();
+        std::fs::write(&path, content.trim_end()).unwrap();
+        assert!(!save_if_changed(&path, &task).unwrap());
+
+        task.summary = "Changed".to_string();
+        assert!(save_if_changed(&path, &task).unwrap());
+        assert!(
+            std::fs::read_to_string(&path)
+                .unwrap()
+                .contains("summary: Changed")
+        );
+    }
+
+    #[test]
+    fn test_mutation_marks_dirty_and_save_clears() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let mut task = Task::new("dirty".to_string());
+        assert!(task.is_dirty());
+
+        task.save_to_disk(dir.path(), &Preferences::default())
+            .unwrap();
+        assert!(!task.is_dirty());
+
+        let query = Query {
+            tags: vec!["work".to_string()],
+            ..Query::new()
+        };
+        task.modify(&query);
+        assert!(task.is_dirty());
+        task.save_to_disk(dir.path(), &Preferences::default())
+            .unwrap();
+
+        task.status = STATUS_RESOLVED.to_string();
+        assert!(task.reopen());
+        assert!(task.is_dirty());
+
+        // A failed write leaves the task dirty
+        let not_a_dir = dir.path().join("file");
+        std::fs::write(&not_a_dir, "").unwrap();
+        assert!(
+            task.save_to_disk(&not_a_dir, &Preferences::default())
+                .is_err()
+        );
+        assert!(task.is_dirty());
+    }
+
+    #[test]
+    fn test_numeric_user_field() {
+        let preferences: Preferences = serde_styx::from_str("user_fields {points number}").unwrap();
+        assert_eq!(preferences.user_fields["points"], UdaType::Number);
+
+        let parse = |value: &str| {
+            let content = format!(
+                "---\nsummary: x\ncreated: 2024-01-01T00:00:00Z\npoints: {}\n---\n",
+                value
+            );
+            crate::frontmatter::task_from_markdown(&content, "uuid", "pending", 1).unwrap()
+        };
+
+        let good = parse("3.5");
+        assert!(good.validate_user_fields(&preferences).is_ok());
+        assert_eq!(
+            good.uda("points", &preferences),
+            Some(UdaValue::Number(3.5))
+        );
+        assert_eq!(good.uda_as("points", UdaType::Bool), None);
+
+        let bad = parse("lots");
+        let err = bad.validate_user_fields(&preferences).unwrap_err();
+        assert!(
+            err.to_string()
+                .contains("points should be a Number, found lots")
+        );
+        assert_eq!(bad.uda("points", &preferences), None);
+
+        // Undeclared fields aren't checked, and their type is guessed
+        assert!(bad.validate_user_fields(&Preferences::default()).is_ok());
+        assert_eq!(
+            bad.uda("points", &Preferences::default()),
+            Some(UdaValue::String("lots".to_string()))
+        );
+        assert_eq!(bad.uda("missing", &preferences), None);
+    }
+
+    #[test]
+    fn test_uda_uses_declared_type() {
+        let content = "---\nsummary: x\ncreated: 2024-01-01T00:00:00Z\nrelease: 2024-06-01\n---\n";
+        let task = crate::frontmatter::task_from_markdown(content, "uuid", "pending", 1).unwrap();
+
+        let preferences: Preferences =
+            serde_styx::from_str("user_fields {release string}").unwrap();
+        assert_eq!(
+            task.uda("release", &preferences),
+            Some(UdaValue::String("2024-06-01".to_string()))
+        );
+        assert!(matches!(
+            task.uda("release", &Preferences::default()),
+            Some(UdaValue::Date(_))
+        ));
+    }
+
+    #[test]
+    fn test_set_due_str() {
+        use crate::date_util::start_of_day;
+        use chrono::{Days, Local};
+
+        let now = Utc.with_ymd_and_hms(2024, 6, 5, 12, 0, 0).unwrap();
+        let local_now = now.with_timezone(&Local);
+        let mut task = Task::new("due soon".to_string());
+
+        task.set_due_str("tomorrow", now).unwrap();
+        let tomorrow = start_of_day(local_now + Days::new(1));
+        assert_eq!(task.due, Some(tomorrow.with_timezone(&Utc)));
+
+        task.set_due_str("+1w", now).unwrap();
+        let next_week = start_of_day(local_now + Days::new(7));
+        assert_eq!(task.due, Some(next_week.with_timezone(&Utc)));
+
+        assert!(task.set_due_str("someday", now).is_err());
+        assert_eq!(task.due, Some(next_week.with_timezone(&Utc)));
+    }
+
+    #[test]
+    fn test_reminder_times() {
+        let mut task = Task::new("remind me".to_string());
+        task.reminders = vec![Duration::hours(1), Duration::days(1)];
+        assert!(task.reminder_times().is_empty());
+
+        let due = Utc.with_ymd_and_hms(2024, 6, 10, 9, 0, 0).unwrap();
+        task.due = Some(due);
+        assert_eq!(
+            task.reminder_times(),
+            vec![
+                Utc.with_ymd_and_hms(2024, 6, 9, 9, 0, 0).unwrap(),
+                Utc.with_ymd_and_hms(2024, 6, 10, 8, 0, 0).unwrap(),
+            ]
+        );
+
+        // A lead time far beyond the calendar is skipped, not a panic
+        let content = "---\nsummary: x\ncreated: 2024-01-01T00:00:00Z\ndue: 2024-06-01\nreminders: [P99999999D, PT1H]\n---\n";
+        let task = crate::frontmatter::task_from_markdown(content, "uuid", "pending", 1).unwrap();
+        assert_eq!(
+            task.reminder_times(),
+            vec![task.due.unwrap() - Duration::hours(1)]
+        );
+    }
+
+    #[test]
+    fn test_note_sections() {
+        let mut task = Task::new("sections".to_string());
+        task.notes = "Intro line\n\n## Context\nWhy we do it\n\n## Steps\n```sh\n## not a heading\n```\n### Detail\nmore"
+            .to_string();
+
+        let sections = task.note_sections();
+        assert_eq!(
+            sections,
+            vec![
+                (None, "Intro line".to_string()),
+                (Some("Context".to_string()), "Why we do it".to_string()),
+                (
+                    Some("Steps".to_string()),
+                    "```sh\n## not a heading\n```\n### Detail\nmore".to_string()
+                ),
+            ]
+        );
+
+        task.notes = "## Only heading".to_string();
+        assert_eq!(
+            task.note_sections(),
+            vec![(Some("Only heading".to_string()), String::new())]
+        );
+    }
+
+    #[test]
+    fn test_note_sections_without_headings() {
+        let mut task = Task::new("plain".to_string());
+        task.notes = "just some\nnotes".to_string();
+        assert_eq!(
+            task.note_sections(),
+            vec![(None, "just some\nnotes".to_string())]
+        );
+
+        task.notes = String::new();
+        assert!(task.note_sections().is_empty());
+    }
+
+    #[test]
+    fn test_content_hash() {
+        let mut task = Task::new("hash me".to_string());
+        task.tags = vec!["work".to_string()];
+
+        let mut other = task.clone();
+        other.uuid = Uuid::new_v4().to_string();
+        other.id = 42;
+        other.write_pending = !task.write_pending;
+        other.filtered = true;
+        assert_eq!(task.content_hash(), other.content_hash());
+
+        other.tags.push("urgent".to_string());
+        assert_ne!(task.content_hash(), other.content_hash());
+
+        // Fields can't bleed into each other
+        let mut a = task.clone();
+        a.summary = "ab".to_string();
+        a.notes = "c".to_string();
+        let mut b = task.clone();
+        b.summary = "a".to_string();
+        b.notes = "bc".to_string();
+        assert_ne!(a.content_hash(), b.content_hash());
+    }
+
+    #[test]
+    fn test_apply_default_tags() {
+        let preferences = Preferences {
+            default_tags: vec!["inbox".to_string(), "work".to_string()],
+            ..Default::default()
+        };
+
+        let mut task = Task::new("with defaults".to_string());
+        task.tags = vec!["work".to_string(), "urgent".to_string()];
+        task.apply_defaults(&preferences);
+        assert_eq!(task.tags, vec!["work", "urgent", "inbox"]);
+
+        let mut task = Task::new("no defaults".to_string());
+        task.apply_defaults(&Preferences::default());
+        assert!(task.tags.is_empty());
+    }
+
+    #[test]
+    fn test_apply_project_defaults() {
+        let preferences: Preferences = serde_styx::from_str(
+            "default_tags (inbox)\nproject_defaults {work {priority P1, tags (office)}, home {tags ()}}",
+        )
+        .unwrap();
+        let new_task = |project: &str, priority: &str| Task {
+            project: project.to_string(),
+            priority: priority.to_string(),
+            ..Task::new("task".to_string())
+        };
+
+        let mut task = new_task("work", "");
+        task.apply_defaults(&preferences);
+        assert_eq!(task.tags, vec!["office"]);
+        assert_eq!(task.priority, "P1");
+
+        // An explicit priority wins over the project's
+        let mut task = new_task("work", "P3");
+        task.apply_defaults(&preferences);
+        assert_eq!(task.priority, "P3");
+
+        let mut task = new_task("home", "");
+        task.apply_defaults(&preferences);
+        assert!(task.tags.is_empty());
+        assert_eq!(task.priority, "");
+
+        let mut task = new_task("garden", "");
+        task.apply_defaults(&preferences);
+        assert_eq!(task.tags, vec!["inbox"]);
+    }
+
+    #[test]
+    fn test_soft_delete_stamps_time() {
+        let now = Utc.with_ymd_and_hms(2024, 6, 1, 12, 0, 0).unwrap();
+        let mut task = Task::new("to delete".to_string());
+        task.write_pending = false;
+
+        task.soft_delete(now);
+        assert!(task.deleted);
+        assert_eq!(task.deleted_at, Some(now));
+        assert!(task.write_pending);
+
+        let yaml = serde_yaml::to_string(&task).unwrap();
+        assert!(yaml.contains("deleted_at: 2024-06-01T12:00:00+00:00"));
+    }
+
+    #[test]
+    fn test_contexts_and_areas() {
+        let mut task = Task::new("mixed tags".to_string());
+        task.tags = ["@home", "#finance", "plain", "@errands", "@", "+x"]
+            .iter()
+            .map(|t| t.to_string())
+            .collect();
+
+        assert_eq!(task.contexts(), vec!["home", "errands"]);
+        assert_eq!(task.areas(), vec!["finance"]);
+
+        let prefs = Preferences {
+            context_prefix: "ctx:".to_string(),
+            area_prefix: String::new(),
+            ..Default::default()
+        };
+        task.tags.push("ctx:office".to_string());
+        assert_eq!(task.contexts_with(&prefs), vec!["office"]);
+        assert!(task.areas_with(&prefs).is_empty());
+    }
+
+    #[test]
+    fn test_reopen_resolved_task() {
+        let mut task = Task::new("done by mistake".to_string());
+        task.status = STATUS_RESOLVED.to_string();
+        task.resolved = Some(Utc::now());
+
+        assert!(task.reopen());
+        assert_eq!(task.status, STATUS_PENDING);
+        assert!(task.resolved.is_none());
+    }
+
+    #[test]
+    fn test_reopen_pending_task_is_noop() {
+        let mut task = Task::new("still open".to_string());
+        let before = task.clone();
+
+        assert!(!task.reopen());
+        assert!(task.equals(&before));
+    }
+
+    #[test]
+    fn test_defer_to_someday() {
+        let mut task = Task::new("learn the cello".to_string());
+        task.write_pending = false;
+        task.defer_to_someday().unwrap();
+        assert_eq!(task.status, STATUS_SOMEDAY);
+        assert!(task.is_dirty());
+        assert!(!task.is_actionable());
+        assert!(is_valid_status_transition(STATUS_SOMEDAY, STATUS_PENDING));
+
+        task.status = STATUS_RESOLVED.to_string();
+        assert!(matches!(
+            task.defer_to_someday(),
+            Err(RstaskError::InvalidStatusTransition(_, _))
+        ));
+        assert_eq!(task.status, STATUS_RESOLVED);
+    }
+
+    #[test]
+    fn test_is_actionable() {
+        let mut task = Task::new("reply to the vendor".to_string());
+        assert!(task.is_actionable());
+
+        task.waiting_for = Some("email from vendor".to_string());
+        assert!(!task.is_actionable());
+        task.waiting_for = None;
+
+        for status in [
+            STATUS_DELEGATED,
+            STATUS_DEFERRED,
+            STATUS_RESOLVED,
+            STATUS_TEMPLATE,
+            STATUS_SOMEDAY,
+        ] {
+            task.status = status.to_string();
+            assert!(!task.is_actionable(), "{}", status);
+        }
+
+        task.status = STATUS_ACTIVE.to_string();
+        assert!(task.is_actionable());
+        task.deleted = true;
+        assert!(!task.is_actionable());
+    }
+
     #[test]
     fn test_task_normalise() {
         let mut task = Task::new("Test".to_string());
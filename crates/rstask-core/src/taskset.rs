@@ -1,11 +1,14 @@
 // TaskSet - collection of tasks with filtering and loading capabilities
-use crate::Result;
+use crate::clock::{Clock, SystemClock};
 use crate::constants::*;
+use crate::crypto::decrypt_task_notes;
 use crate::local_state::{load_ids, save_ids};
+use crate::preferences::{DuplicateIdPolicy, Preferences};
 use crate::query::Query;
 use crate::table::RowStyle;
-use crate::task::{Task, unmarshal_task};
-use chrono::{DateTime, Utc};
+use crate::task::{Task, unmarshal_task_with};
+use crate::{Result, RstaskError};
+use chrono::{DateTime, Duration, Utc};
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::path::{Path, PathBuf};
@@ -48,6 +51,256 @@ pub struct TaskSet {
     tasks_by_uuid: HashMap<String, usize>,
     ids_file_path: PathBuf,
     repo_path: PathBuf,
+    preferences: Preferences,
+    clock: Box<dyn Clock>,
+    /// Content hash of each task as last read from or written to disk, so
+    /// saving a task that was marked dirty but not changed keeps its stamp
+    stored_hashes: HashMap<String, u64>,
+}
+
+/// Finds numeric IDs claimed by more than one task, returning each ID with the
+/// UUIDs of the tasks sharing it (sorted by ID)
+pub fn find_duplicate_ids(tasks: &[Task]) -> Vec<(i32, Vec<String>)> {
+    let mut uuids_by_id: HashMap<i32, Vec<String>> = HashMap::new();
+
+    for task in tasks {
+        if task.id > 0 {
+            uuids_by_id
+                .entry(task.id)
+                .or_default()
+                .push(task.uuid.clone());
+        }
+    }
+
+    let mut duplicates: Vec<(i32, Vec<String>)> = uuids_by_id
+        .into_iter()
+        .filter(|(_, uuids)| uuids.len() > 1)
+        .collect();
+    duplicates.sort_by_key(|(id, _)| *id);
+    duplicates
+}
+
+/// Removes tasks soft-deleted at least `after` before `now` and returns them,
+/// so the caller can remove their files. [`TaskSet::purge_deleted`] does both.
+pub fn purge_deleted(tasks: &mut Vec<Task>, now: DateTime<Utc>, after: Duration) -> Vec<Task> {
+    let cutoff = now - after;
+    let (purged, kept) = std::mem::take(tasks).into_iter().partition(|task| {
+        task.deleted_at
+            .is_some_and(|deleted_at| deleted_at <= cutoff)
+    });
+    *tasks = kept;
+    purged
+}
+
+/// Returns open tasks not assigned to a project, for inbox processing.
+/// Unlike `filter_unorganised`, tagged tasks are included.
+pub fn inbox_tasks(tasks: &[Task]) -> Vec<&Task> {
+    tasks
+        .iter()
+        .filter(|task| !task.deleted && task.project.is_empty())
+        .filter(|task| !HIDDEN_STATUSES.contains(&task.status.as_str()))
+        .collect()
+}
+
+/// Returns the open task created first, the one most overdue for a look
+/// when clearing the backlog. Ties go to the task listed first.
+pub fn oldest_pending(tasks: &[Task]) -> Option<&Task> {
+    tasks
+        .iter()
+        .filter(|task| !task.deleted && !HIDDEN_STATUSES.contains(&task.status.as_str()))
+        .min_by_key(|task| task.created)
+}
+
+/// Whether the task has a value for the frontmatter field `name`, or the
+/// status it is filed under. Names rstask doesn't know are looked up among
+/// the user-defined fields.
+fn has_field(task: &Task, name: &str) -> bool {
+    match name {
+        "status" => !task.status.is_empty(),
+        "created" => true,
+        "summary" => !task.summary.is_empty(),
+        "notes" => !task.notes.is_empty() || task.notes_file.is_some(),
+        "tags" => !task.tags.is_empty(),
+        "project" => !task.project.is_empty(),
+        "priority" => !task.priority.is_empty(),
+        "delegatedto" => !task.delegated_to.is_empty(),
+        "waiting_for" => task.waiting_for.is_some(),
+        "subtasks" => !task.subtasks.is_empty(),
+        "dependencies" => !task.dependencies.is_empty(),
+        "attachments" => !task.attachments.is_empty(),
+        "resolved" => task.resolved.is_some(),
+        "due" => task.due.is_some(),
+        "modified" => task.modified.is_some(),
+        "estimate" => task.estimate.is_some(),
+        "time_spent" => task.time_spent.is_some(),
+        "reminders" => !task.reminders.is_empty(),
+        "recur" => task.recur.is_some(),
+        "deleted_at" => task.deleted_at.is_some(),
+        "notes_file" => task.notes_file.is_some(),
+        _ => task.extra.get(name).is_some_and(|value| !value.is_null()),
+    }
+}
+
+/// Lists the open tasks lacking any of the `required_fields`, each with the
+/// names of the fields it is missing in the configured order
+pub fn tasks_missing_required<'a>(
+    tasks: &'a [Task],
+    prefs: &Preferences,
+) -> Vec<(&'a Task, Vec<String>)> {
+    tasks
+        .iter()
+        .filter(|task| !task.deleted && !HIDDEN_STATUSES.contains(&task.status.as_str()))
+        .filter_map(|task| {
+            let missing: Vec<String> = prefs
+                .required_fields
+                .iter()
+                .filter(|field| !has_field(task, field))
+                .cloned()
+                .collect();
+            (!missing.is_empty()).then_some((task, missing))
+        })
+        .collect()
+}
+
+/// Returns the tasks written at or after `since`, oldest change first.
+/// Tasks never saved with a modified stamp are left out.
+pub fn modified_since(tasks: &[Task], since: DateTime<Utc>) -> Vec<&Task> {
+    let mut modified: Vec<&Task> = tasks
+        .iter()
+        .filter(|task| task.modified.is_some_and(|modified| modified >= since))
+        .collect();
+    modified.sort_by_key(|task| task.modified);
+    modified
+}
+
+/// Returns open tasks last written more than `threshold` before `now`,
+/// least recently touched first. Tasks saved before modified stamps existed
+/// are judged by when they were created.
+pub fn stale_tasks(tasks: &[Task], now: DateTime<Utc>, threshold: Duration) -> Vec<&Task> {
+    let touched = |task: &Task| task.modified.unwrap_or(task.created);
+    let mut stale: Vec<&Task> = tasks
+        .iter()
+        .filter(|task| !task.deleted && !HIDDEN_STATUSES.contains(&task.status.as_str()))
+        .filter(|task| now - touched(task) > threshold)
+        .collect();
+    stale.sort_by_key(|task| touched(task));
+    stale
+}
+
+/// Sets each task's `filtered` flag from `filter`, hiding the tasks it
+/// doesn't match and showing the ones it does. Unlike [`TaskSet::filter`],
+/// tasks hidden earlier are shown again if they match.
+pub fn apply_filter(tasks: &mut [Task], filter: &Query) {
+    for task in tasks {
+        task.filtered = !task.matches_filter(filter);
+    }
+}
+
+/// Shows every task again, undoing [`apply_filter`]
+pub fn clear_filter(tasks: &mut [Task]) {
+    for task in tasks {
+        task.filtered = false;
+    }
+}
+
+/// Moves the due date of every open task matching `filter` by `by`, which
+/// may be negative, returning how many tasks changed. Undated, resolved and
+/// deleted tasks are left alone. If any due date would move past the range
+/// of representable dates, no task is changed and the error names it.
+pub fn shift_due(tasks: &mut [Task], filter: &Query, by: Duration) -> Result<usize> {
+    let mut shifted = Vec::new();
+    for (idx, task) in tasks.iter().enumerate() {
+        if task.deleted || task.status == STATUS_RESOLVED || !task.matches_filter(filter) {
+            continue;
+        }
+
+        if let Some(due) = task.due {
+            let moved = due.checked_add_signed(by).ok_or_else(|| {
+                RstaskError::Other(format!("the due date of {} can't be moved that far", task))
+            })?;
+            shifted.push((idx, moved));
+        }
+    }
+
+    for &(idx, moved) in &shifted {
+        tasks[idx].due = Some(moved);
+        tasks[idx].mark_dirty();
+    }
+    Ok(shifted.len())
+}
+
+/// Resolves a dependency given on the command line, either a UUID or a
+/// numeric ID, to the UUID stored in `dependencies`. Deleted tasks can't
+/// be depended on.
+pub fn resolve_dependency_ref(reference: &str, tasks: &[Task]) -> Result<String> {
+    let reference = reference.trim();
+    let candidates = tasks.iter().filter(|task| !task.deleted);
+
+    if let Ok(id) = reference.parse::<i32>() {
+        let matches: Vec<&Task> = candidates.filter(|task| id > 0 && task.id == id).collect();
+        return match matches.as_slice() {
+            [] => Err(RstaskError::TaskNotFound(reference.to_string())),
+            [task] => Ok(task.uuid.clone()),
+            _ => Err(RstaskError::DuplicateId(
+                id,
+                matches
+                    .iter()
+                    .map(|task| task.uuid.as_str())
+                    .collect::<Vec<_>>()
+                    .join(", "),
+            )),
+        };
+    }
+
+    let uuid = uuid::Uuid::parse_str(reference)
+        .map_err(|_| RstaskError::InvalidUuid(reference.to_string()))?
+        .to_string();
+    candidates
+        .map(|task| &task.uuid)
+        .find(|task_uuid| **task_uuid == uuid)
+        .cloned()
+        .ok_or_else(|| RstaskError::TaskNotFound(reference.to_string()))
+}
+
+/// Returns one more than the highest ID among non-deleted tasks, leaving
+/// any gaps below it alone, or 1 if no task has an ID
+pub fn next_id(tasks: &[Task]) -> i32 {
+    next_id_with(tasks, &Preferences::default())
+}
+
+/// Same as [`next_id`], never handing out an ID below the configured
+/// `id_base`
+pub fn next_id_with(tasks: &[Task], preferences: &Preferences) -> i32 {
+    tasks
+        .iter()
+        .filter(|task| !task.deleted && task.id > 0)
+        .map(|task| task.id + 1)
+        .max()
+        .map_or(preferences.id_base, |id| id.max(preferences.id_base))
+}
+
+/// Finds the task whose UUID starts with `prefix`, like a git short hash.
+/// Case is ignored and deleted tasks are skipped. An empty prefix matches
+/// nothing, and one matching several tasks is an error listing them.
+pub fn resolve_uuid_prefix<'a>(prefix: &str, tasks: &'a [Task]) -> Result<&'a Task> {
+    let prefix = prefix.trim().to_lowercase();
+    let matches: Vec<&Task> = tasks
+        .iter()
+        .filter(|task| !task.deleted && !prefix.is_empty() && task.uuid.starts_with(&prefix))
+        .collect();
+
+    match matches.as_slice() {
+        [] => Err(RstaskError::TaskNotFound(prefix)),
+        [task] => Ok(task),
+        _ => Err(RstaskError::AmbiguousUuidPrefix(
+            prefix,
+            matches
+                .iter()
+                .map(|task| task.uuid.as_str())
+                .collect::<Vec<_>>()
+                .join(", "),
+        )),
+    }
 }
 
 impl TaskSet {
@@ -58,13 +311,43 @@ impl TaskSet {
             tasks_by_uuid: HashMap::new(),
             ids_file_path,
             repo_path,
+            preferences: Preferences::default(),
+            clock: Box::new(SystemClock),
+            stored_hashes: HashMap::new(),
         }
     }
 
+    /// Replaces the clock used to stamp created and resolved times
+    pub fn set_clock(&mut self, clock: impl Clock + 'static) {
+        self.clock = Box::new(clock);
+    }
+
+    /// The clock used to stamp created and resolved times
+    pub fn clock(&self) -> &dyn Clock {
+        self.clock.as_ref()
+    }
+
     /// Loads tasks from the repository
     pub fn load(repo_path: &Path, ids_file_path: &Path, include_resolved: bool) -> Result<Self> {
+        Self::load_with_preferences(
+            repo_path,
+            ids_file_path,
+            include_resolved,
+            &Preferences::default(),
+        )
+    }
+
+    /// Loads tasks from the repository, honouring the given preferences
+    pub fn load_with_preferences(
+        repo_path: &Path,
+        ids_file_path: &Path,
+        include_resolved: bool,
+        preferences: &Preferences,
+    ) -> Result<Self> {
         let mut ts = TaskSet::new(repo_path.to_path_buf(), ids_file_path.to_path_buf());
+        ts.preferences = preferences.clone();
         let ids = load_ids(ids_file_path);
+        let mut loaded = Vec::new();
 
         let statuses = if include_resolved {
             ALL_STATUSES
@@ -73,14 +356,23 @@ impl TaskSet {
         };
 
         for status in statuses {
-            let dir = repo_path.join(status);
+            let dir = repo_path.join(ts.preferences.status_dir(status));
 
             if !dir.exists() {
                 continue;
             }
 
-            // Collect all entries first
-            let mut entries: Vec<_> = std::fs::read_dir(&dir)?.filter_map(|e| e.ok()).collect();
+            // Collect all entries first, including those in shard directories
+            let mut entries = Vec::new();
+            for entry in std::fs::read_dir(&dir)?.filter_map(|e| e.ok()) {
+                let is_shard = entry.file_type().is_ok_and(|t| t.is_dir())
+                    && !entry.file_name().to_string_lossy().starts_with('.');
+                if is_shard {
+                    entries.extend(std::fs::read_dir(entry.path())?.filter_map(|e| e.ok()));
+                } else {
+                    entries.push(entry);
+                }
+            }
 
             // Sort entries to prioritize .md files over .yml files
             // This ensures if both formats exist for the same task, .md is loaded
@@ -108,9 +400,18 @@ impl TaskSet {
                 }
 
                 let path = entry.path();
-                match unmarshal_task(&path, &filename_str, &ids, status) {
-                    Ok(task) => {
-                        ts.load_task(task)?;
+                match unmarshal_task_with(&path, &filename_str, &ids, status, &ts.preferences) {
+                    Ok(mut task) => {
+                        let key = ts.preferences.notes_key.as_ref();
+                        if let Err(e) = decrypt_task_notes(&mut task, key) {
+                            eprintln!("Warning: could not decrypt notes of {}: {}", task.uuid, e);
+                        }
+                        // Bad values are only rejected by `rstask edit`, a typo
+                        // in a hand-edited file shouldn't make the task disappear
+                        if let Err(e) = task.validate_user_fields(&ts.preferences) {
+                            eprintln!("Warning: task {}: {}", task.uuid, e);
+                        }
+                        loaded.push(task);
                     }
                     Err(e) => {
                         eprintln!("Warning: error loading task: {}", e);
@@ -119,6 +420,28 @@ impl TaskSet {
             }
         }
 
+        for (id, uuids) in find_duplicate_ids(&loaded) {
+            match ts.preferences.on_duplicate_id {
+                DuplicateIdPolicy::Error => {
+                    return Err(RstaskError::DuplicateId(id, uuids.join(", ")));
+                }
+                DuplicateIdPolicy::Renumber => {
+                    eprintln!(
+                        "Warning: task ID {} is shared by {}, renumbering",
+                        id,
+                        uuids.join(", ")
+                    );
+                }
+                DuplicateIdPolicy::Ignore => {}
+            }
+        }
+
+        for task in loaded {
+            ts.stored_hashes
+                .insert(task.uuid.clone(), task.content_hash());
+            ts.load_task(task)?;
+        }
+
         // hide some tasks by default. This is useful for things like templates and
         // recurring tasks which are shown either directly or with show- commands
         for task in &mut ts.tasks {
@@ -145,31 +468,37 @@ impl TaskSet {
             return Ok(());
         }
 
-        // Remove ID if already taken
-        if task.id > 0 && self.tasks_by_id.contains_key(&task.id) {
+        // Remove ID if already taken, unless duplicates are deliberately kept
+        if task.id > 0
+            && self.tasks_by_id.contains_key(&task.id)
+            && self.preferences.on_duplicate_id != DuplicateIdPolicy::Ignore
+        {
+            task.id = 0;
+        }
+
+        // Soft-deleted tasks stay hidden and don't hold an ID
+        if task.deleted_at.is_some() {
+            task.deleted = true;
+            task.filtered = true;
             task.id = 0;
         }
 
         // Assign ID if needed (for non-resolved tasks)
-        if task.id == 0 && task.status != STATUS_RESOLVED {
-            for id in 1..=MAX_TASKS_OPEN as i32 {
-                if !self.tasks_by_id.contains_key(&id) {
-                    task.id = id;
-                    break;
-                }
-            }
+        if task.id == 0 && task.status != STATUS_RESOLVED && !task.deleted {
+            task.id = next_id_with(&self.tasks, &self.preferences);
         }
 
         // Set created time if not set
         if task.created == DateTime::<Utc>::from_timestamp(0, 0).unwrap() {
-            task.created = Utc::now();
-            task.write_pending = true;
+            task.created = self.clock.now();
+            task.mark_dirty();
         }
 
         let idx = self.tasks.len();
         self.tasks_by_uuid.insert(task.uuid.clone(), idx);
         if task.id > 0 {
-            self.tasks_by_id.insert(task.id, idx);
+            // The first task loaded keeps the ID when duplicates are ignored
+            self.tasks_by_id.entry(task.id).or_insert(idx);
         }
         self.tasks.push(task);
         Ok(())
@@ -178,7 +507,7 @@ impl TaskSet {
     /// Assigns IDs to tasks
     pub fn assign_ids(&mut self) -> Result<()> {
         let mut ids = load_ids(&self.ids_file_path);
-        let mut next_id = 1;
+        let mut next_id = self.preferences.id_base;
 
         // Find next available ID
         while ids.values().any(|&id| id == next_id) {
@@ -186,7 +515,7 @@ impl TaskSet {
         }
 
         for (idx, task) in self.tasks.iter_mut().enumerate() {
-            if task.status != STATUS_RESOLVED && task.id == 0 {
+            if task.status != STATUS_RESOLVED && task.id == 0 && !task.deleted {
                 ids.insert(task.uuid.clone(), next_id);
                 task.id = next_id;
                 self.tasks_by_id.insert(next_id, idx);
@@ -227,8 +556,13 @@ impl TaskSet {
         let mut ids = std::collections::HashMap::new();
 
         for task in &mut self.tasks {
-            if task.write_pending {
-                task.save_to_disk(&self.repo_path)?;
+            if task.is_dirty() {
+                let hash = task.content_hash();
+                if self.stored_hashes.get(&task.uuid) != Some(&hash) {
+                    task.modified = Some(self.clock.now());
+                }
+                task.save_to_disk(&self.repo_path, &self.preferences)?;
+                self.stored_hashes.insert(task.uuid.clone(), hash);
             }
 
             // Build IDs map for all tasks with IDs
@@ -299,10 +633,10 @@ impl TaskSet {
 
         // Set resolved time
         if task.status == STATUS_RESOLVED && task.resolved.is_none() {
-            task.resolved = Some(Utc::now());
+            task.resolved = Some(self.clock.now());
         }
 
-        task.write_pending = true;
+        task.mark_dirty();
         self.tasks[idx] = task;
 
         Ok(())
@@ -315,7 +649,7 @@ impl TaskSet {
     }
 
     pub fn sort_by_created_descending(&mut self) {
-        self.tasks.sort_by(|a, b| b.created.cmp(&a.created));
+        self.tasks.sort_by_key(|t| std::cmp::Reverse(t.created));
     }
 
     /// Sorts tasks by priority (P0 > P1 > P2 > P3)
@@ -376,7 +710,7 @@ impl TaskSet {
     /// Unhides tasks with hidden statuses
     pub fn unhide(&mut self) {
         for task in &mut self.tasks {
-            if HIDDEN_STATUSES.contains(&task.status.as_str()) {
+            if HIDDEN_STATUSES.contains(&task.status.as_str()) && !task.deleted {
                 task.filtered = false;
             }
         }
@@ -413,7 +747,7 @@ impl TaskSet {
                     tasks: 0,
                     tasks_resolved: 0,
                     active: false,
-                    created: Utc::now(),
+                    created: self.clock.now(),
                     resolved: DateTime::<Utc>::from_timestamp(0, 0).unwrap(),
                     priority: PRIORITY_LOW.to_string(),
                 });
@@ -454,6 +788,11 @@ impl TaskSet {
             .collect()
     }
 
+    /// Returns the preferences the task set was loaded with
+    pub fn preferences(&self) -> &Preferences {
+        &self.preferences
+    }
+
     /// Returns the total number of tasks
     pub fn num_total(&self) -> usize {
         self.tasks.len()
@@ -509,7 +848,7 @@ impl TaskSet {
         let task = &self.tasks[idx];
 
         // Delete from disk
-        task.delete_from_disk(&self.repo_path)?;
+        task.delete_from_disk_with(&self.repo_path, &self.preferences)?;
 
         // Remove from in-memory structures
         let id = task.id;
@@ -525,6 +864,18 @@ impl TaskSet {
         Ok(())
     }
 
+    /// Permanently removes tasks soft-deleted at least `after` ago, deleting
+    /// their task and notes files. Returns the purged tasks.
+    pub fn purge_deleted(&mut self, after: Duration) -> Result<Vec<Task>> {
+        let purged = purge_deleted(&mut self.tasks, self.clock.now(), after);
+        self.rebuild_indices();
+
+        for task in &purged {
+            task.delete_from_disk_with(&self.repo_path, &self.preferences)?;
+        }
+        Ok(purged)
+    }
+
     /// Rebuild task indices after removal
     fn rebuild_indices(&mut self) {
         self.tasks_by_uuid.clear();
@@ -533,8 +884,689 @@ impl TaskSet {
         for (idx, task) in self.tasks.iter().enumerate() {
             self.tasks_by_uuid.insert(task.uuid.clone(), idx);
             if task.id > 0 {
-                self.tasks_by_id.insert(task.id, idx);
+                self.tasks_by_id.entry(task.id).or_insert(idx);
             }
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clock::FixedClock;
+    use crate::frontmatter::task_to_markdown;
+    use chrono::TimeZone;
+    use tempfile::TempDir;
+
+    const UUID_A: &str = "11111111-1111-4111-8111-111111111111";
+    const UUID_B: &str = "22222222-2222-4222-8222-222222222222";
+
+    /// Writes two pending tasks to disk whose IDs file maps both to ID 1
+    fn repo_with_duplicate_id() -> (TempDir, PathBuf) {
+        let dir = TempDir::new().unwrap();
+        let pending = dir.path().join(STATUS_PENDING);
+        std::fs::create_dir_all(&pending).unwrap();
+
+        let mut ids = HashMap::new();
+        for uuid in [UUID_A, UUID_B] {
+            let task = Task {
+                uuid: uuid.to_string(),
+                ..Task::new(format!("task {}", uuid))
+            };
+            std::fs::write(
+                pending.join(format!("{}.md", uuid)),
+                task_to_markdown(&task).unwrap(),
+            )
+            .unwrap();
+            ids.insert(uuid.to_string(), 1);
+        }
+
+        let ids_file = dir.path().join("ids.bin");
+        save_ids(&ids_file, &ids).unwrap();
+        (dir, ids_file)
+    }
+
+    fn load_with_policy(policy: DuplicateIdPolicy) -> (TempDir, Result<TaskSet>) {
+        let (dir, ids_file) = repo_with_duplicate_id();
+        let preferences = Preferences {
+            on_duplicate_id: policy,
+            ..Default::default()
+        };
+        let ts = TaskSet::load_with_preferences(dir.path(), &ids_file, false, &preferences);
+        (dir, ts)
+    }
+
+    #[test]
+    fn test_fixed_clock_stamps_tasks() {
+        let now = Utc.with_ymd_and_hms(2024, 3, 1, 9, 30, 0).unwrap();
+        let dir = TempDir::new().unwrap();
+        let mut ts = TaskSet::new(dir.path().to_path_buf(), dir.path().join("ids.bin"));
+        ts.set_clock(FixedClock(now));
+
+        let task = ts
+            .must_load_task(Task {
+                summary: "stamped".to_string(),
+                status: STATUS_PENDING.to_string(),
+                ..Default::default()
+            })
+            .unwrap();
+        assert_eq!(task.created, now);
+
+        let mut task = task.clone();
+        task.status = STATUS_RESOLVED.to_string();
+        ts.update_task(task.clone()).unwrap();
+        assert_eq!(ts.get_by_uuid(&task.uuid).unwrap().resolved, Some(now));
+    }
+
+    #[test]
+    fn test_inbox_tasks() {
+        let mut tagged = Task::new("tagged".to_string());
+        tagged.tags.push("someday".to_string());
+        let mut projected = Task::new("projected".to_string());
+        projected.project = "home".to_string();
+        let mut resolved = Task::new("resolved".to_string());
+        resolved.status = STATUS_RESOLVED.to_string();
+        let mut deleted = Task::new("deleted".to_string());
+        deleted.deleted = true;
+        let mut active = Task::new("active".to_string());
+        active.status = STATUS_ACTIVE.to_string();
+
+        let tasks = vec![
+            Task::new("loose".to_string()),
+            tagged,
+            projected,
+            resolved,
+            deleted,
+            active,
+        ];
+        let names: Vec<&str> = inbox_tasks(&tasks)
+            .iter()
+            .map(|t| t.summary.as_str())
+            .collect();
+        assert_eq!(names, vec!["loose", "tagged", "active"]);
+    }
+
+    #[test]
+    fn test_modified_since() {
+        let since = Utc.with_ymd_and_hms(2024, 6, 1, 12, 0, 0).unwrap();
+        let modified_at = |summary: &str, modified: Option<DateTime<Utc>>| {
+            let mut task = Task::new(summary.to_string());
+            task.modified = modified;
+            task
+        };
+
+        let tasks = vec![
+            modified_at("later", Some(since + Duration::hours(2))),
+            modified_at("before", Some(since - Duration::seconds(1))),
+            modified_at("at cutoff", Some(since)),
+            modified_at("never saved", None),
+            modified_at("soon after", Some(since + Duration::minutes(5))),
+        ];
+        let names: Vec<&str> = modified_since(&tasks, since)
+            .iter()
+            .map(|t| t.summary.as_str())
+            .collect();
+        assert_eq!(names, vec!["at cutoff", "soon after", "later"]);
+    }
+
+    #[test]
+    fn test_shift_due() {
+        let due = Utc.with_ymd_and_hms(2024, 7, 1, 9, 0, 0).unwrap();
+        let task = |summary: &str, project: &str, due: Option<DateTime<Utc>>| {
+            let mut task = Task::new(summary.to_string());
+            task.project = project.to_string();
+            task.due = due;
+            task.write_pending = false;
+            task
+        };
+        let mut resolved = task("resolved", "work", Some(due));
+        resolved.status = STATUS_RESOLVED.to_string();
+
+        let mut tasks = vec![
+            task("report", "work", Some(due)),
+            task("undated", "work", None),
+            task("garden", "home", Some(due)),
+            resolved,
+        ];
+
+        let filter = Query {
+            project: "work".to_string(),
+            ..Default::default()
+        };
+        assert_eq!(
+            shift_due(&mut tasks, &filter, Duration::weeks(1)).unwrap(),
+            1
+        );
+
+        assert_eq!(tasks[0].due, Some(due + Duration::weeks(1)));
+        assert!(tasks[0].is_dirty());
+        assert_eq!(tasks[1].due, None);
+        assert!(!tasks[1].is_dirty());
+        assert_eq!(tasks[2].due, Some(due));
+        assert_eq!(tasks[3].due, Some(due));
+
+        // Too far for one task means no task moves
+        let shifted = tasks[0].due;
+        assert!(matches!(
+            shift_due(&mut tasks, &Query::default(), Duration::MAX),
+            Err(RstaskError::Other(_))
+        ));
+        assert_eq!(tasks[0].due, shifted);
+        assert_eq!(tasks[2].due, Some(due));
+    }
+
+    #[test]
+    fn test_stale_tasks() {
+        let now = Utc.with_ymd_and_hms(2024, 6, 30, 0, 0, 0).unwrap();
+        let touched = |summary: &str, days_ago: i64| {
+            let mut task = Task::new(summary.to_string());
+            task.created = now - Duration::days(400);
+            task.modified = Some(now - Duration::days(days_ago));
+            task
+        };
+
+        let mut resolved = touched("resolved", 200);
+        resolved.status = STATUS_RESOLVED.to_string();
+        let mut never_saved = touched("never saved", 0);
+        never_saved.modified = None;
+
+        let tasks = vec![
+            touched("recent", 10),
+            touched("old", 120),
+            touched("borderline", 90),
+            touched("ancient", 300),
+            resolved,
+            never_saved,
+        ];
+        let names: Vec<&str> = stale_tasks(&tasks, now, Duration::days(90))
+            .iter()
+            .map(|t| t.summary.as_str())
+            .collect();
+        assert_eq!(names, vec!["never saved", "ancient", "old"]);
+    }
+
+    #[test]
+    fn test_save_stamps_modified() {
+        let now = Utc.with_ymd_and_hms(2024, 3, 1, 9, 30, 0).unwrap();
+        let dir = TempDir::new().unwrap();
+        let mut ts = TaskSet::new(dir.path().to_path_buf(), dir.path().join("ids.bin"));
+        ts.set_clock(FixedClock(now));
+
+        let task = ts.must_load_task(Task::new("saved".to_string())).unwrap();
+        assert_eq!(task.modified, None);
+        ts.save_pending_changes().unwrap();
+        assert_eq!(ts.get_by_uuid(&task.uuid).unwrap().modified, Some(now));
+    }
+
+    #[test]
+    fn test_unchanged_task_keeps_modified_stamp() {
+        let saved = Utc.with_ymd_and_hms(2024, 3, 1, 9, 30, 0).unwrap();
+        let later = Utc.with_ymd_and_hms(2024, 3, 2, 14, 0, 0).unwrap();
+        let dir = TempDir::new().unwrap();
+        let ids_file = dir.path().join("ids.bin");
+        let mut ts = TaskSet::new(dir.path().to_path_buf(), ids_file.clone());
+        ts.set_clock(FixedClock(saved));
+        let task = ts.must_load_task(Task::new("steady".to_string())).unwrap();
+        ts.save_pending_changes().unwrap();
+        let path = task.file_path(dir.path(), &Preferences::default());
+        let written = std::fs::read_to_string(&path).unwrap();
+
+        let mut ts = TaskSet::load(dir.path(), &ids_file, false).unwrap();
+        ts.set_clock(FixedClock(later));
+        let mut task = ts.get_by_uuid(&task.uuid).unwrap().clone();
+        task.mark_dirty();
+        ts.must_update_task(task.clone()).unwrap();
+        ts.save_pending_changes().unwrap();
+        assert_eq!(ts.get_by_uuid(&task.uuid).unwrap().modified, Some(saved));
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), written);
+
+        task.summary = "changed".to_string();
+        ts.must_update_task(task.clone()).unwrap();
+        ts.save_pending_changes().unwrap();
+        assert_eq!(ts.get_by_uuid(&task.uuid).unwrap().modified, Some(later));
+    }
+
+    #[test]
+    fn test_toggle_subtask_stamps_modified_on_save() {
+        let created = Utc.with_ymd_and_hms(2024, 3, 1, 9, 30, 0).unwrap();
+        let toggled = Utc.with_ymd_and_hms(2024, 3, 2, 14, 0, 0).unwrap();
+        let dir = TempDir::new().unwrap();
+        let mut ts = TaskSet::new(dir.path().to_path_buf(), dir.path().join("ids.bin"));
+        ts.set_clock(FixedClock(created));
+
+        let mut task = Task::new("release".to_string());
+        task.subtasks = vec![crate::task::SubTask {
+            summary: "tag".to_string(),
+            resolved: false,
+        }];
+        let task = ts.must_load_task(task).unwrap();
+        ts.save_pending_changes().unwrap();
+
+        ts.set_clock(FixedClock(toggled));
+        let mut task = ts.get_by_uuid(&task.uuid).unwrap().clone();
+        task.toggle_subtask(0).unwrap();
+        ts.must_update_task(task.clone()).unwrap();
+        ts.save_pending_changes().unwrap();
+        assert_eq!(ts.get_by_uuid(&task.uuid).unwrap().modified, Some(toggled));
+    }
+
+    #[test]
+    fn test_resolve_dependency_ref() {
+        let mut first = Task::new("first".to_string());
+        first.uuid = UUID_A.to_string();
+        first.id = 3;
+        let mut second = Task::new("second".to_string());
+        second.uuid = UUID_B.to_string();
+        second.id = 4;
+        let tasks = vec![first, second];
+
+        assert_eq!(resolve_dependency_ref("3", &tasks).unwrap(), UUID_A);
+        assert_eq!(resolve_dependency_ref(UUID_B, &tasks).unwrap(), UUID_B);
+        assert_eq!(
+            resolve_dependency_ref(&UUID_B.to_uppercase(), &tasks).unwrap(),
+            UUID_B
+        );
+
+        assert!(matches!(
+            resolve_dependency_ref("9", &tasks),
+            Err(RstaskError::TaskNotFound(_))
+        ));
+        assert!(matches!(
+            resolve_dependency_ref("33333333-3333-4333-8333-333333333333", &tasks),
+            Err(RstaskError::TaskNotFound(_))
+        ));
+        assert!(matches!(
+            resolve_dependency_ref("three", &tasks),
+            Err(RstaskError::InvalidUuid(_))
+        ));
+    }
+
+    #[test]
+    fn test_resolve_dependency_ref_ambiguous() {
+        let mut tasks = vec![Task::new("a".to_string()), Task::new("b".to_string())];
+        for task in &mut tasks {
+            task.id = 2;
+        }
+        assert!(matches!(
+            resolve_dependency_ref("2", &tasks),
+            Err(RstaskError::DuplicateId(2, _))
+        ));
+    }
+
+    #[test]
+    fn test_apply_and_clear_filter() {
+        let tagged = |summary: &str, tag: &str| {
+            let mut task = Task::new(summary.to_string());
+            task.tags = vec![tag.to_string()];
+            task
+        };
+        let mut tasks = vec![
+            tagged("report", "work"),
+            tagged("groceries", "home"),
+            tagged("deploy", "work"),
+        ];
+        tasks[0].filtered = true;
+
+        let filter = Query {
+            tags: vec!["work".to_string()],
+            ..Default::default()
+        };
+        apply_filter(&mut tasks, &filter);
+        let shown: Vec<&str> = tasks
+            .iter()
+            .filter(|task| !task.filtered)
+            .map(|task| task.summary.as_str())
+            .collect();
+        assert_eq!(shown, vec!["report", "deploy"]);
+
+        clear_filter(&mut tasks);
+        assert!(tasks.iter().all(|task| !task.filtered));
+    }
+
+    #[test]
+    fn test_oldest_pending() {
+        let now = Utc.with_ymd_and_hms(2024, 6, 10, 12, 0, 0).unwrap();
+        let created = |summary: &str, days_ago: i64, status: &str| {
+            let mut task = Task::new(summary.to_string());
+            task.created = now - Duration::days(days_ago);
+            task.status = status.to_string();
+            task
+        };
+        let mut deleted = created("deleted", 50, STATUS_PENDING);
+        deleted.deleted = true;
+
+        let tasks = vec![
+            created("recent", 2, STATUS_PENDING),
+            created("finished long ago", 90, STATUS_RESOLVED),
+            created("oldest open", 30, STATUS_ACTIVE),
+            created("middling", 10, STATUS_PENDING),
+            deleted,
+        ];
+        assert_eq!(oldest_pending(&tasks).unwrap().summary, "oldest open");
+        assert!(oldest_pending(&tasks[1..2]).is_none());
+    }
+
+    #[test]
+    fn test_tasks_missing_required() {
+        let prefs = Preferences {
+            required_fields: vec!["project".to_string(), "due".to_string()],
+            ..Default::default()
+        };
+        let task = |summary: &str, project: &str, due: bool| {
+            let mut task = Task::new(summary.to_string());
+            task.project = project.to_string();
+            task.due = due.then(Utc::now);
+            task
+        };
+
+        let tasks = vec![
+            task("complete", "web", true),
+            task("undated", "web", false),
+            task("bare", "", false),
+        ];
+        let missing: Vec<(&str, Vec<String>)> = tasks_missing_required(&tasks, &prefs)
+            .into_iter()
+            .map(|(task, fields)| (task.summary.as_str(), fields))
+            .collect();
+        assert_eq!(
+            missing,
+            vec![
+                ("undated", vec!["due".to_string()]),
+                ("bare", vec!["project".to_string(), "due".to_string()]),
+            ]
+        );
+
+        assert!(tasks_missing_required(&tasks, &Preferences::default()).is_empty());
+
+        // Built-in fields are never mistaken for user-defined ones
+        let prefs = Preferences {
+            required_fields: ["status", "created", "resolved", "notes_file"]
+                .map(String::from)
+                .to_vec(),
+            ..Default::default()
+        };
+        let missing = tasks_missing_required(&tasks[..1], &prefs);
+        assert_eq!(
+            missing[0].1,
+            vec!["resolved".to_string(), "notes_file".to_string()]
+        );
+    }
+
+    #[test]
+    fn test_next_id() {
+        let with_ids = |ids: &[i32]| -> Vec<Task> {
+            ids.iter()
+                .map(|&id| {
+                    let mut task = Task::new(format!("task {}", id));
+                    task.id = id;
+                    task
+                })
+                .collect()
+        };
+
+        assert_eq!(next_id(&[]), 1);
+        let prefs = Preferences {
+            id_base: 100,
+            ..Default::default()
+        };
+        assert_eq!(next_id_with(&[], &prefs), 100);
+        // Tasks without an ID count as an empty store
+        assert_eq!(next_id_with(&with_ids(&[0]), &prefs), 100);
+        // IDs below the base don't pull the next one under it
+        assert_eq!(next_id_with(&with_ids(&[3]), &prefs), 100);
+        assert_eq!(next_id_with(&with_ids(&[3, 120]), &prefs), 121);
+
+        assert_eq!(next_id(&with_ids(&[1, 2, 3])), 4);
+        assert_eq!(next_id(&with_ids(&[7, 2, 4])), 8);
+
+        let mut tasks = with_ids(&[1, 9]);
+        tasks[1].deleted = true;
+        assert_eq!(next_id(&tasks), 2);
+    }
+
+    #[test]
+    fn test_load_task_honours_id_base() {
+        let dir = TempDir::new().unwrap();
+        let mut ts = TaskSet::new(dir.path().to_path_buf(), dir.path().join("ids.bin"));
+        ts.preferences.id_base = 100;
+
+        let first = ts.must_load_task(Task::new("first".to_string())).unwrap();
+        let second = ts.must_load_task(Task::new("second".to_string())).unwrap();
+        assert_eq!((first.id, second.id), (100, 101));
+    }
+
+    #[test]
+    fn test_resolve_uuid_prefix() {
+        let uuids = [UUID_A, UUID_B, "22223333-2222-4222-8222-222222222222"];
+        let tasks: Vec<Task> = uuids
+            .iter()
+            .map(|uuid| {
+                let mut task = Task::new(uuid.to_string());
+                task.uuid = uuid.to_string();
+                task
+            })
+            .collect();
+
+        assert_eq!(resolve_uuid_prefix("1111", &tasks).unwrap().uuid, UUID_A);
+        assert_eq!(resolve_uuid_prefix("222222", &tasks).unwrap().uuid, UUID_B);
+
+        match resolve_uuid_prefix("2222", &tasks) {
+            Err(RstaskError::AmbiguousUuidPrefix(prefix, candidates)) => {
+                assert_eq!(prefix, "2222");
+                assert!(candidates.contains(UUID_B));
+                assert!(candidates.contains(uuids[2]));
+            }
+            other => panic!("expected an ambiguous prefix, got {:?}", other),
+        }
+
+        assert!(matches!(
+            resolve_uuid_prefix("abc", &tasks),
+            Err(RstaskError::TaskNotFound(_))
+        ));
+        assert!(matches!(
+            resolve_uuid_prefix("", &tasks),
+            Err(RstaskError::TaskNotFound(_))
+        ));
+    }
+
+    #[test]
+    fn test_purge_deleted() {
+        let now = Utc.with_ymd_and_hms(2024, 6, 30, 0, 0, 0).unwrap();
+        let mut old = Task::new("old".to_string());
+        old.soft_delete(now - Duration::days(40));
+        let mut recent = Task::new("recent".to_string());
+        recent.soft_delete(now - Duration::days(2));
+        let live = Task::new("live".to_string());
+
+        let mut tasks = vec![old, recent, live];
+        let purged = purge_deleted(&mut tasks, now, Duration::days(30));
+
+        let names = |tasks: &[Task]| tasks.iter().map(|t| t.summary.clone()).collect::<Vec<_>>();
+        assert_eq!(names(&purged), vec!["old"]);
+        assert_eq!(names(&tasks), vec!["recent", "live"]);
+    }
+
+    #[test]
+    fn test_someday_tasks_hidden() {
+        let dir = TempDir::new().unwrap();
+        let repo = dir.path().to_path_buf();
+        let ids_file = repo.join(".ids");
+
+        let mut parked = Task::new("learn the cello".to_string());
+        parked.defer_to_someday().unwrap();
+        parked.save_to_disk(&repo, &Preferences::default()).unwrap();
+        assert!(
+            repo.join(STATUS_SOMEDAY)
+                .join(format!("{}.md", parked.uuid))
+                .exists()
+        );
+
+        let mut ts = TaskSet::load(&repo, &ids_file, false).unwrap();
+        assert!(ts.get_by_uuid(&parked.uuid).unwrap().filtered);
+        assert!(ts.tasks().is_empty());
+        assert!(inbox_tasks(ts.all_tasks()).is_empty());
+        ts.unhide();
+        assert!(!ts.get_by_uuid(&parked.uuid).unwrap().filtered);
+    }
+
+    #[test]
+    fn test_sharded_store_roundtrip() {
+        let dir = TempDir::new().unwrap();
+        let repo = dir.path().to_path_buf();
+        let ids_file = repo.join(".ids");
+        let sharded = Preferences {
+            shard_uuids: true,
+            ..Default::default()
+        };
+
+        let mut first = Task::new("first".to_string());
+        first.uuid = UUID_A.to_string();
+        let mut second = Task::new("second".to_string());
+        second.uuid = UUID_B.to_string();
+        second.status = STATUS_ACTIVE.to_string();
+        // Written before sharding was turned on
+        let mut flat = Task::new("flat".to_string());
+        flat.save_to_disk(&repo, &Preferences::default()).unwrap();
+        for task in [&mut first, &mut second] {
+            task.save_to_disk(&repo, &sharded).unwrap();
+        }
+        assert!(
+            repo.join("pending/11")
+                .join(format!("{}.md", UUID_A))
+                .exists()
+        );
+        assert!(
+            repo.join("active/22")
+                .join(format!("{}.md", UUID_B))
+                .exists()
+        );
+
+        let ts = TaskSet::load_with_preferences(&repo, &ids_file, false, &sharded).unwrap();
+        assert_eq!(ts.tasks().len(), 3);
+        assert_eq!(ts.get_by_uuid(UUID_A).unwrap().summary, "first");
+        assert_eq!(ts.get_by_uuid(UUID_B).unwrap().status, STATUS_ACTIVE);
+
+        // Saving moves a flat file into its shard
+        let flat_path = flat.file_path(&repo, &Preferences::default());
+        flat.mark_dirty();
+        flat.save_to_disk(&repo, &sharded).unwrap();
+        assert!(!flat_path.exists());
+        assert!(flat.file_path(&repo, &sharded).exists());
+    }
+
+    #[test]
+    fn test_task_set_purge_deleted_removes_files() {
+        let now = Utc.with_ymd_and_hms(2024, 6, 30, 0, 0, 0).unwrap();
+        let dir = TempDir::new().unwrap();
+        let repo = dir.path().to_path_buf();
+        let ids_file = repo.join(".ids");
+
+        let mut old = Task::new("old".to_string());
+        old.notes = "kept elsewhere".to_string();
+        old.notes_file = Some(format!("notes/{}.md", old.uuid));
+        old.soft_delete(now - Duration::days(40));
+        old.save_to_disk(&repo, &Preferences::default()).unwrap();
+        let mut recent = Task::new("recent".to_string());
+        recent.soft_delete(now - Duration::days(2));
+        recent.save_to_disk(&repo, &Preferences::default()).unwrap();
+
+        let task_file = old.file_path(&repo, &Preferences::default());
+        let notes_file = repo.join(old.notes_file.as_ref().unwrap());
+        assert!(task_file.exists() && notes_file.exists());
+
+        let mut ts = TaskSet::load(&repo, &ids_file, false).unwrap();
+        ts.set_clock(FixedClock(now));
+        let purged = ts.purge_deleted(Duration::days(30)).unwrap();
+        assert_eq!(purged.len(), 1);
+        assert!(!task_file.exists());
+        assert!(!notes_file.exists());
+        assert!(ts.get_by_uuid(&old.uuid).is_none());
+
+        let ts = TaskSet::load(&repo, &ids_file, false).unwrap();
+        assert!(ts.get_by_uuid(&old.uuid).is_none());
+        assert!(ts.get_by_uuid(&recent.uuid).is_some());
+    }
+
+    #[test]
+    fn test_invalid_user_field_still_loads() {
+        let dir = TempDir::new().unwrap();
+        let repo = dir.path().to_path_buf();
+        let preferences: Preferences = serde_styx::from_str("user_fields {points number}").unwrap();
+
+        let uuid = Task::new("typo".to_string()).uuid;
+        std::fs::create_dir_all(repo.join(STATUS_PENDING)).unwrap();
+        std::fs::write(
+            repo.join(STATUS_PENDING).join(format!("{}.md", uuid)),
+            "---\nsummary: typo\ncreated: 2024-01-01T00:00:00Z\npoints: lots\n---\n",
+        )
+        .unwrap();
+
+        let ts =
+            TaskSet::load_with_preferences(&repo, &repo.join(".ids"), false, &preferences).unwrap();
+        let task = ts.get_by_uuid(&uuid).unwrap();
+        assert!(task.id > 0);
+        assert!(task.validate_user_fields(&preferences).is_err());
+    }
+
+    #[test]
+    fn test_soft_deleted_tasks_survive_save_and_load() {
+        let dir = TempDir::new().unwrap();
+        let repo = dir.path().to_path_buf();
+        let ids_file = repo.join(".ids");
+
+        let mut task = Task::new("soft".to_string());
+        task.soft_delete(Utc::now());
+        task.save_to_disk(&repo, &Preferences::default()).unwrap();
+
+        let ts = TaskSet::load(&repo, &ids_file, false).unwrap();
+        let loaded = ts.get_by_uuid(&task.uuid).unwrap();
+        assert!(loaded.deleted);
+        assert_eq!(loaded.id, 0);
+        assert!(ts.tasks().is_empty());
+    }
+
+    #[test]
+    fn test_find_duplicate_ids() {
+        let mut a = Task::new("a".to_string());
+        a.id = 3;
+        let mut b = Task::new("b".to_string());
+        b.id = 3;
+        let mut c = Task::new("c".to_string());
+        c.id = 4;
+
+        let duplicates = find_duplicate_ids(&[a.clone(), b.clone(), c]);
+        assert_eq!(duplicates, vec![(3, vec![a.uuid, b.uuid])]);
+    }
+
+    #[test]
+    fn test_duplicate_id_policy_error() {
+        let (_dir, ts) = load_with_policy(DuplicateIdPolicy::Error);
+        match ts {
+            Err(crate::RstaskError::DuplicateId(id, uuids)) => {
+                assert_eq!(id, 1);
+                assert!(uuids.contains(UUID_A) && uuids.contains(UUID_B));
+            }
+            _ => panic!("expected a duplicate ID error"),
+        }
+    }
+
+    #[test]
+    fn test_duplicate_id_policy_renumber() {
+        let (_dir, ts) = load_with_policy(DuplicateIdPolicy::Renumber);
+        let ts = ts.unwrap();
+
+        assert_eq!(ts.get_by_uuid(UUID_A).unwrap().id, 1);
+        assert_eq!(ts.get_by_uuid(UUID_B).unwrap().id, 2);
+        assert!(find_duplicate_ids(ts.all_tasks()).is_empty());
+    }
+
+    #[test]
+    fn test_duplicate_id_policy_ignore() {
+        let (_dir, ts) = load_with_policy(DuplicateIdPolicy::Ignore);
+        let ts = ts.unwrap();
+
+        assert_eq!(ts.get_by_uuid(UUID_A).unwrap().id, 1);
+        assert_eq!(ts.get_by_uuid(UUID_B).unwrap().id, 1);
+        assert_eq!(ts.get_by_id(1).unwrap().uuid, UUID_A);
+    }
+}
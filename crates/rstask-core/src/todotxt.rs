@@ -0,0 +1,175 @@
+// todo.txt import - reads tasks from the plain text todo.txt format
+//
+// An open task looks like `(A) 2024-01-01 Call mum +family @phone due:2024-01-05`:
+// an optional priority, an optional creation date, then the description with
+// +project, @context and key:value tokens anywhere in it. Completed tasks
+// start with `x` and an optional completion date, as in
+// `x 2024-01-02 2024-01-01 Call mum`.
+use crate::constants::*;
+use crate::preferences::Preferences;
+use crate::task::Task;
+use chrono::{DateTime, Local, NaiveDate, TimeZone, Utc};
+
+/// Maps a todo.txt priority letter to a priority: A is high, B normal and
+/// C to Z low. Critical is never assigned, as todo.txt has no equivalent.
+pub fn priority_from_letter(letter: char) -> Option<&'static str> {
+    match letter {
+        'A' => Some(PRIORITY_HIGH),
+        'B' => Some(PRIORITY_NORMAL),
+        'C'..='Z' => Some(PRIORITY_LOW),
+        _ => None,
+    }
+}
+
+fn parse_date(s: &str) -> Option<DateTime<Utc>> {
+    let date = NaiveDate::parse_from_str(s, "%Y-%m-%d").ok()?;
+    Local
+        .from_local_datetime(&date.and_hms_opt(0, 0, 0)?)
+        .earliest()
+        .map(|d| d.with_timezone(&Utc))
+}
+
+fn parse_priority(s: &str) -> Option<&'static str> {
+    let mut chars = s.strip_prefix('(')?.strip_suffix(')')?.chars();
+    match (chars.next(), chars.next()) {
+        (Some(letter), None) => priority_from_letter(letter),
+        _ => None,
+    }
+}
+
+/// Parses a todo.txt file into new tasks, one per non-blank line
+pub fn tasks_from_todotxt(content: &str) -> Vec<Task> {
+    tasks_from_todotxt_with(content, &Preferences::default())
+}
+
+/// Same as [`tasks_from_todotxt`], with @contexts becoming tags under the
+/// configured context prefix. The first +project becomes the project and
+/// any further ones become tags. `due:` and `pri:` (left on completed tasks
+/// by some clients) are read; other key:value tokens stay in the summary.
+pub fn tasks_from_todotxt_with(content: &str, preferences: &Preferences) -> Vec<Task> {
+    content
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| task_from_todotxt_line(line, preferences))
+        .collect()
+}
+
+fn task_from_todotxt_line(line: &str, preferences: &Preferences) -> Task {
+    let mut task = Task::new(String::new());
+    let mut words = line.split_whitespace().peekable();
+
+    if words.peek() == Some(&"x") {
+        words.next();
+        task.status = STATUS_RESOLVED.to_string();
+        task.resolved = words.peek().and_then(|w| parse_date(w));
+        if task.resolved.is_some() {
+            words.next();
+        }
+    }
+
+    if let Some(priority) = words.peek().and_then(|w| parse_priority(w)) {
+        task.priority = priority.to_string();
+        words.next();
+    }
+
+    if let Some(created) = words.peek().and_then(|w| parse_date(w)) {
+        task.created = created;
+        words.next();
+    }
+
+    // Without a completion date, the creation date is the best guess
+    if task.status == STATUS_RESOLVED && task.resolved.is_none() {
+        task.resolved = Some(task.created);
+    }
+
+    let mut summary = Vec::new();
+    for word in words {
+        if let Some(project) = word.strip_prefix('+').filter(|p| !p.is_empty()) {
+            if task.project.is_empty() {
+                task.project = project.to_string();
+            } else {
+                task.tags.push(project.to_string());
+            }
+        } else if let Some(context) = word.strip_prefix('@').filter(|c| !c.is_empty()) {
+            task.tags
+                .push(format!("{}{}", preferences.context_prefix, context));
+        } else if let Some(due) = word.strip_prefix("due:").and_then(parse_date) {
+            task.due = Some(due);
+        } else if let Some(priority) = word
+            .strip_prefix("pri:")
+            .and_then(|p| p.chars().next())
+            .and_then(priority_from_letter)
+        {
+            task.priority = priority.to_string();
+        } else {
+            summary.push(word);
+        }
+    }
+    task.summary = summary.join(" ");
+
+    task
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn local_date(y: i32, m: u32, d: u32) -> Option<DateTime<Utc>> {
+        parse_date(&format!("{:04}-{:02}-{:02}", y, m, d))
+    }
+
+    #[test]
+    fn test_prioritized_line() {
+        let tasks =
+            tasks_from_todotxt("(A) 2024-03-01 Call the bank due:2024-03-08\n\n(C) Tidy up");
+        assert_eq!(tasks.len(), 2);
+
+        let task = &tasks[0];
+        assert_eq!(task.summary, "Call the bank");
+        assert_eq!(task.priority, PRIORITY_HIGH);
+        assert_eq!(task.status, STATUS_PENDING);
+        assert_eq!(Some(task.created), local_date(2024, 3, 1));
+        assert_eq!(task.due, local_date(2024, 3, 8));
+
+        assert_eq!(tasks[1].priority, PRIORITY_LOW);
+        // Not a priority unless it opens the line
+        let tasks = tasks_from_todotxt("Read chapter (A)");
+        assert_eq!(tasks[0].priority, PRIORITY_NORMAL);
+        assert_eq!(tasks[0].summary, "Read chapter (A)");
+    }
+
+    #[test]
+    fn test_completed_line() {
+        let tasks =
+            tasks_from_todotxt("x 2024-03-05 2024-03-01 Renew passport pri:B\nx Water plants");
+
+        let task = &tasks[0];
+        assert_eq!(task.status, STATUS_RESOLVED);
+        assert_eq!(task.resolved, local_date(2024, 3, 5));
+        assert_eq!(Some(task.created), local_date(2024, 3, 1));
+        assert_eq!(task.priority, PRIORITY_NORMAL);
+        assert_eq!(task.summary, "Renew passport");
+
+        let task = &tasks[1];
+        assert_eq!(task.status, STATUS_RESOLVED);
+        assert_eq!(task.resolved, Some(task.created));
+        assert_eq!(task.summary, "Water plants");
+    }
+
+    #[test]
+    fn test_projects_and_contexts() {
+        let tasks = tasks_from_todotxt("Plan trip +holiday @computer +family @phone stay:3d");
+
+        let task = &tasks[0];
+        assert_eq!(task.summary, "Plan trip stay:3d");
+        assert_eq!(task.project, "holiday");
+        assert_eq!(task.tags, vec!["@computer", "family", "@phone"]);
+
+        let preferences = Preferences {
+            context_prefix: "ctx-".to_string(),
+            ..Default::default()
+        };
+        let tasks = tasks_from_todotxt_with("Plan trip @phone", &preferences);
+        assert_eq!(tasks[0].tags, vec!["ctx-phone"]);
+    }
+}
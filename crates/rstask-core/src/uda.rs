@@ -0,0 +1,66 @@
+// User-defined attributes - extra typed fields declared in preferences
+use crate::task::optional_datetime_rfc3339::parse_rfc3339_or_date;
+use crate::{Result, RstaskError};
+use chrono::{DateTime, Utc};
+use serde::Deserialize;
+#[cfg(test)]
+use serde::Serialize;
+use serde_yaml::Value;
+
+/// Type of a user-defined field
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(test, derive(Serialize))]
+#[serde(rename_all = "snake_case")]
+pub enum UdaType {
+    String,
+    Number,
+    /// RFC3339 timestamp or YYYY-MM-DD date
+    Date,
+    Bool,
+}
+
+/// Value of a user-defined field
+#[derive(Debug, Clone, PartialEq)]
+pub enum UdaValue {
+    String(String),
+    Number(f64),
+    Date(DateTime<Utc>),
+    Bool(bool),
+}
+
+impl UdaType {
+    /// Reads a frontmatter value as this type, None if it doesn't fit
+    pub fn parse(self, value: &Value) -> Option<UdaValue> {
+        match (self, value) {
+            (UdaType::String, Value::String(s)) => Some(UdaValue::String(s.clone())),
+            (UdaType::Number, Value::Number(n)) => n.as_f64().map(UdaValue::Number),
+            (UdaType::Date, Value::String(s)) => parse_rfc3339_or_date(s).ok().map(UdaValue::Date),
+            (UdaType::Bool, Value::Bool(b)) => Some(UdaValue::Bool(*b)),
+            _ => None,
+        }
+    }
+
+    /// Checks a value against this type, naming the field in the error
+    pub fn validate(self, name: &str, value: &Value) -> Result<UdaValue> {
+        self.parse(value).ok_or_else(|| {
+            let shown = serde_yaml::to_string(value).unwrap_or_default();
+            RstaskError::Parse(format!(
+                "field {} should be a {:?}, found {}",
+                name,
+                self,
+                shown.trim()
+            ))
+        })
+    }
+
+    /// Guesses the type of an undeclared value
+    pub fn infer(value: &Value) -> Option<UdaType> {
+        match value {
+            Value::Bool(_) => Some(UdaType::Bool),
+            Value::Number(_) => Some(UdaType::Number),
+            Value::String(s) if parse_rfc3339_or_date(s).is_ok() => Some(UdaType::Date),
+            Value::String(_) => Some(UdaType::String),
+            _ => None,
+        }
+    }
+}
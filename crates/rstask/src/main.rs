@@ -91,7 +91,13 @@ fn main() {
 
     // Load state for context
     let mut state = LocalState::load(&conf.state_file);
-    let mut ctx = state.context.clone();
+    let mut ctx = match state.effective_context(&conf.preferences) {
+        Ok(ctx) => ctx,
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            process::exit(1);
+        }
+    };
 
     // Check for context override from environment variable
     if let Ok(ctx_from_env) = env::var("RSTASK_CONTEXT")